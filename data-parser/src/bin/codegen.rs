@@ -0,0 +1,59 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use data_parser::codegen::generate;
+
+/// Generate Rust models for a prototype type from its JSON Schema.
+///
+/// The schema may be one written by `infer-schema --split-toplevel`, a schema
+/// of a whole dump written by `infer-schema` together with `--type`, or one
+/// curated by hand.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the JSON Schema
+    schema: PathBuf,
+
+    /// Generate models for this prototype type of a whole-dump schema
+    #[arg(short = 't', long = "type")]
+    kind: Option<String>,
+
+    /// Name of the root struct
+    ///
+    /// Defaults to the schema's title, or the prototype type.
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let schema = std::fs::read_to_string(&args.schema).context("reading schema")?;
+    let mut schema: serde_json::Value = serde_json::from_str(&schema).context("parsing schema")?;
+    if let Some(kind) = &args.kind {
+        schema = schema["properties"][kind]["additionalProperties"].take();
+        if !schema.is_object() {
+            return Err(anyhow!("the schema has no prototype type \"{kind}\""));
+        }
+        schema["title"] = kind.as_str().into();
+    }
+
+    let module = generate(&schema, args.name.as_deref()).context("generating models")?;
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(module.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, module).context("writing output file")?;
+    }
+
+    Ok(())
+}
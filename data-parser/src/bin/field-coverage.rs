@@ -0,0 +1,164 @@
+use std::{fmt::Write as _, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use data_parser::{
+    coverage::{coverage, FieldCoverage, TypeCoverage},
+    validate::MODELS,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// List every field used by each prototype type of a data dump, how many
+/// prototypes use it, whether the typed model captures it, and example values.
+///
+/// This shows which fields to model next, and which fields a Factorio update
+/// or a mod has added.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Survey only these prototype types, such as `recipe`
+    #[arg(short = 't', long = "type")]
+    types: Vec<String>,
+
+    /// Survey only types with a model
+    #[arg(long)]
+    modeled: bool,
+
+    /// List only fields which are not captured by every prototype
+    #[arg(long)]
+    uncaptured: bool,
+
+    /// Number of example values to show for each field
+    #[arg(short, long, default_value_t = 3)]
+    examples: usize,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+/// Shorten an example value to fit in a table.
+fn example(value: &serde_json::Value) -> String {
+    const WIDTH: usize = 32;
+
+    let example = value.to_string();
+    if example.chars().count() <= WIDTH {
+        return example;
+    }
+    let mut example = example.chars().take(WIDTH - 1).collect::<String>();
+    example.push('…');
+    example
+}
+
+fn captured(field: &FieldCoverage) -> String {
+    match field.captured {
+        None => "-".into(),
+        Some(0) => "no".into(),
+        Some(count) if count == field.count => "yes".into(),
+        Some(count) => format!("{count} of {}", field.count),
+    }
+}
+
+fn render_text(types: &[TypeCoverage]) -> String {
+    let mut rendered = String::new();
+    for coverage in types {
+        if !rendered.is_empty() {
+            rendered.push('\n');
+        }
+        writeln!(
+            rendered,
+            "{}: {} prototypes, {}",
+            coverage.kind,
+            coverage.prototypes,
+            if coverage.modeled {
+                "modeled"
+            } else {
+                "not modeled"
+            },
+        )
+        .unwrap();
+
+        let rows = coverage
+            .fields
+            .iter()
+            .map(|(path, field)| {
+                let examples = field.examples.iter().map(example).collect::<Vec<_>>();
+                [
+                    path.clone(),
+                    field.count.to_string(),
+                    captured(field),
+                    examples.join(", "),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let header = ["field", "used", "captured", "examples"].map(String::from);
+        let mut widths = header.clone().map(|cell| cell.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for [path, count, captured, examples] in std::iter::once(&header).chain(&rows) {
+            let line = format!(
+                "  {path:0$}  {count:>1$}  {captured:2$}  {examples}",
+                widths[0], widths[1], widths[2]
+            );
+            rendered.push_str(line.trim_end());
+            rendered.push('\n');
+        }
+    }
+    rendered
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let dump = data_parser::load(&args.data).context("loading data")?;
+    let mut types = coverage(&dump, args.examples, |kind| {
+        (args.types.is_empty() || args.types.iter().any(|wanted| wanted == kind))
+            && (!args.modeled || MODELS.iter().any(|model| model.kind == kind))
+    });
+    if args.uncaptured {
+        for coverage in &mut types {
+            coverage.fields.retain(|_, field| !field.is_captured());
+        }
+        types.retain(|coverage| !coverage.fields.is_empty());
+    }
+
+    let rendered = match args.format {
+        Format::Text => render_text(&types),
+        Format::Json => {
+            let mut rendered =
+                serde_json::to_string_pretty(&types).context("serializing coverage")?;
+            rendered.push('\n');
+            rendered
+        }
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
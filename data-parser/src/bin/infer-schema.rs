@@ -0,0 +1,83 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use data_parser::{
+    load_value,
+    schema::{dump_schema, infer, prototype_schema},
+};
+
+/// Infer a JSON Schema for each prototype type of a data dump.
+///
+/// The schemas record the fields of each type and which are always present,
+/// the types of their values, the values of strings which take few, and the
+/// range of numbers. They can validate other dumps, such as those of mod
+/// packs, and bootstrap new models.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(default_value = "-")]
+    output: String,
+
+    /// Infer schemas only for these prototype types, such as `recipe`
+    #[arg(short = 't', long = "type")]
+    types: Vec<String>,
+
+    /// Most distinct values a string may take to be inferred as an enumeration
+    #[arg(long, default_value_t = 16)]
+    max_enum: usize,
+
+    /// Split the output by prototype type
+    ///
+    /// This is only meaningful when the output is a particular path, not
+    /// stdout. The output path becomes a directory, into which the schema of
+    /// each type is written as a standalone file, such as `recipe.json`.
+    /// Otherwise, a single schema describes the whole dump.
+    #[arg(short = 'S', long)]
+    split_toplevel: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let dump = load_value(&args.data).context("loading data")?;
+    let mut shapes = infer(&dump, args.max_enum);
+    if !args.types.is_empty() {
+        shapes.retain(|kind, _| args.types.contains(kind));
+    }
+
+    if args.split_toplevel && args.output != "-" {
+        let dir = PathBuf::from(&args.output);
+        std::fs::create_dir_all(&dir).context("creating output directory")?;
+        for (kind, shape) in &shapes {
+            let mut rendered = serde_json::to_string_pretty(&prototype_schema(kind, shape))
+                .context("serializing schema")?;
+            rendered.push('\n');
+            std::fs::write(dir.join(format!("{kind}.json")), rendered)
+                .with_context(|| format!("writing schema for \"{kind}\""))?;
+        }
+        return Ok(());
+    }
+
+    let mut rendered =
+        serde_json::to_string_pretty(&dump_schema(&shapes)).context("serializing schema")?;
+    rendered.push('\n');
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
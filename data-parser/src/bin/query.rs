@@ -0,0 +1,190 @@
+use std::{borrow::Cow, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use data_parser::{
+    generic_transform::Value,
+    load_value,
+    query::{Query, Row},
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Search a data dump with a path, an optional filter and a projection.
+///
+/// For example, `query prototype-data.lua 'recipe.*' --where 'category ==
+/// "chemistry"' --select name,energy_required` lists every chemistry recipe
+/// and its crafting time.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Path selecting the values to query, such as `recipe.*` or
+    /// `recipe.*.ingredients[*]`
+    path: String,
+
+    /// Keep only values matching this filter, such as `energy_required > 10`
+    #[arg(short = 'w', long = "where")]
+    filter: Option<String>,
+
+    /// Fields to show for each value, as `PATH` or `NAME=PATH`
+    ///
+    /// May be repeated, or given as a comma-separated list. Without fields,
+    /// each value is shown whole.
+    #[arg(short = 's', long = "select", value_delimiter = ',')]
+    fields: Vec<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn json(value: &Value) -> serde_json::Value {
+    value.clone().into()
+}
+
+/// A table or CSV cell: scalars as plain text, anything else as JSON.
+fn cell(values: &[Cow<Value>]) -> String {
+    values
+        .iter()
+        .map(|value| match &**value {
+            Value::String(s) => s.to_string(),
+            Value::Float(n) => n.to_string(),
+            Value::Integer(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Nil => String::new(),
+            Value::Table(_) | Value::List(_) => json(value).to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The header and cells of each row.
+fn cells(query: &Query, rows: &[Row]) -> (Vec<String>, Vec<Vec<String>>) {
+    if query.fields.is_empty() {
+        let header = vec!["path".into(), "value".into()];
+        let rows = rows
+            .iter()
+            .map(|row| {
+                vec![
+                    row.location.to_string(),
+                    cell(std::slice::from_ref(&row.value)),
+                ]
+            })
+            .collect();
+        return (header, rows);
+    }
+
+    let header = query
+        .fields
+        .iter()
+        .map(|field| field.name.clone())
+        .collect();
+    let rows = rows
+        .iter()
+        .map(|row| row.fields.iter().map(|values| cell(values)).collect())
+        .collect();
+    (header, rows)
+}
+
+fn render(query: &Query, rows: &[Row], format: Format) -> Result<Vec<u8>> {
+    let rendered = match format {
+        Format::Json => {
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    if query.fields.is_empty() {
+                        return json(&row.value);
+                    }
+                    let fields = query.fields.iter().zip(&row.fields).map(|(field, values)| {
+                        let value = match values.as_slice() {
+                            [] => serde_json::Value::Null,
+                            [value] => json(value),
+                            values => values.iter().map(|value| json(value)).collect(),
+                        };
+                        (field.name.clone(), value)
+                    });
+                    serde_json::Value::Object(fields.collect())
+                })
+                .collect::<Vec<_>>();
+            let mut rendered = serde_json::to_vec_pretty(&rows).context("serializing rows")?;
+            rendered.push(b'\n');
+            rendered
+        }
+        Format::Csv => {
+            let (header, rows) = cells(query, rows);
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(&header)?;
+            for row in rows {
+                writer.write_record(&row)?;
+            }
+            writer.into_inner().context("writing csv")?
+        }
+        Format::Table => {
+            let (header, rows) = cells(query, rows);
+            let mut widths = header.iter().map(|h| h.chars().count()).collect::<Vec<_>>();
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            let mut rendered = String::new();
+            for row in std::iter::once(&header).chain(&rows) {
+                let line = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, &width)| format!("{cell:width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                rendered.push_str(line.trim_end());
+                rendered.push('\n');
+            }
+            rendered.into_bytes()
+        }
+    };
+    Ok(rendered)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let query = Query {
+        path: args.path.parse()?,
+        filter: args.filter.as_deref().map(str::parse).transpose()?,
+        fields: args
+            .fields
+            .iter()
+            .map(|field| field.parse())
+            .collect::<Result<_, _>>()?,
+    };
+
+    let data = load_value(&args.data).context("loading data")?;
+    let rows = query.run(&data);
+    let rendered = render(&query, &rows, args.format)?;
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(&rendered)
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
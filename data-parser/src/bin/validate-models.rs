@@ -0,0 +1,166 @@
+use std::{fmt::Write as _, io::Write, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use data_parser::validate::{Outcome, Report, MODELS};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Check that the typed models parse every prototype of a data dump, and
+/// reserialize to the same value.
+///
+/// Exits with an error if any prototype fails to parse or reserializes
+/// differently, so that it can be run against each mod pack in CI.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Validate only these prototype types, such as `recipe`
+    #[arg(short = 't', long = "type")]
+    types: Vec<String>,
+
+    /// Also list the fields which incomplete models do not capture
+    #[arg(long)]
+    show_missing: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn render_text(reports: &[Report], show_missing: bool) -> String {
+    let mut rendered = String::new();
+    for report in reports {
+        for (name, outcome) in &report.prototypes {
+            let (status, differences) = match outcome {
+                Outcome::Passed => continue,
+                Outcome::MissingFields(_) if !show_missing => continue,
+                Outcome::MissingFields(differences) => ("missing fields", differences),
+                Outcome::Mismatched(differences) => ("mismatched", differences),
+                Outcome::Failed(err) => {
+                    writeln!(rendered, "{}: {name}: parse error: {err}", report.kind).unwrap();
+                    continue;
+                }
+            };
+            writeln!(rendered, "{}: {name}: {status}", report.kind).unwrap();
+            for difference in differences {
+                writeln!(rendered, "  {difference}").unwrap();
+            }
+        }
+    }
+    if !rendered.is_empty() {
+        rendered.push('\n');
+    }
+
+    let header = [
+        "type",
+        "total",
+        "passed",
+        "missing fields",
+        "mismatched",
+        "failed",
+    ];
+    let rows = reports
+        .iter()
+        .map(|report| {
+            let summary = report.summary();
+            let kind = match report.complete {
+                true => report.kind.to_owned(),
+                false => format!("{} (partial)", report.kind),
+            };
+            [
+                kind,
+                summary.total.to_string(),
+                summary.passed.to_string(),
+                summary.missing_fields.to_string(),
+                summary.mismatched.to_string(),
+                summary.failed.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let header = header.map(String::from);
+    for row in std::iter::once(&header).chain(&rows) {
+        let (kind, counts) = row.split_first().unwrap();
+        write!(rendered, "{kind:0$}", widths[0]).unwrap();
+        for (count, width) in counts.iter().zip(&widths[1..]) {
+            write!(rendered, "  {count:>width$}").unwrap();
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let models = MODELS
+        .iter()
+        .filter(|model| args.types.is_empty() || args.types.iter().any(|t| t == model.kind))
+        .collect::<Vec<_>>();
+    if let Some(unknown) = args
+        .types
+        .iter()
+        .find(|t| !MODELS.iter().any(|model| &model.kind == t))
+    {
+        let known = MODELS.iter().map(|model| model.kind).collect::<Vec<_>>();
+        bail!(
+            "no model for type {unknown}; expected one of {}",
+            known.join(", ")
+        );
+    }
+
+    let dump = data_parser::load(&args.data).context("loading data")?;
+    let reports = models
+        .into_iter()
+        .map(|model| Report::new(model, &dump))
+        .collect::<Vec<_>>();
+
+    let rendered = match args.format {
+        Format::Text => render_text(&reports, args.show_missing),
+        Format::Json => {
+            let mut rendered =
+                serde_json::to_string_pretty(&reports).context("serializing reports")?;
+            rendered.push('\n');
+            rendered
+        }
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    let errors = reports
+        .iter()
+        .flat_map(|report| report.prototypes.values())
+        .filter(|outcome| outcome.is_error())
+        .count();
+    if errors > 0 {
+        bail!("{errors} prototypes failed validation");
+    }
+    Ok(())
+}
@@ -0,0 +1,657 @@
+//! Generation of Rust models from JSON Schemas.
+//!
+//! [`generate`] turns the schema of a prototype type, as inferred by
+//! [`schema`][crate::schema] or written by hand, into Serde structs and enums
+//! in the style of [`models`][crate::models]:
+//!
+//! - tables become structs, and keys which are not Rust identifiers are
+//!   renamed
+//! - fields which are not `required` are `Option`s, unless the schema gives a
+//!   `default`, in which case the default is neither required nor
+//!   reserialized; nullable fields are `Option`s either way, and are not
+//!   wrapped twice
+//! - strings with a single `enum` value become `MustBe!` tags
+//! - tuples (`prefixItems`) become Rust tuples, and `anyOf` alternatives become
+//!   untagged enums
+//! - an `anyOf` of tables alongside an object's `properties` becomes an
+//!   untagged enum flattened into the struct, for `result`/`results`-style
+//!   variance
+//!
+//! The generated code is a starting point: names and integer types are worth
+//! a second look before it joins the models.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+};
+
+use serde_json::Value;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Words which must be escaped to be used as field names.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while",
+];
+
+/// Type names which would shadow the prelude.
+const RESERVED: &[&str] = &["Box", "Option", "Result", "Self", "String", "Vec"];
+
+/// Convert a key such as `crafting-speed` to a type name such as
+/// `CraftingSpeed`.
+fn pascal_case(key: &str) -> String {
+    let mut name = String::new();
+    for word in key.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.push_str(chars.as_str());
+        }
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'T');
+    }
+    name
+}
+
+/// Convert a key such as `crafting-speed` to a field name such as
+/// `crafting_speed`.
+fn snake_case(key: &str) -> String {
+    let mut name = key
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.insert_str(0, "r#");
+    }
+    name
+}
+
+/// The name of one element of a list named `key`.
+fn singular(key: &str) -> String {
+    match key.strip_suffix('s') {
+        Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.into(),
+        _ => format!("{key}-item"),
+    }
+}
+
+/// A Rust literal of `value`, as a value of type `ty`.
+fn literal(value: &Value, ty: &str) -> Option<String> {
+    match value {
+        Value::Bool(b) if ty == "bool" => Some(b.to_string()),
+        Value::Number(n) if ty == "f64" => Some(format!("{:?}", n.as_f64()?)),
+        Value::Number(n) if ["u32", "u64", "i64"].contains(&ty) && !n.is_f64() => {
+            Some(n.to_string())
+        }
+        Value::String(s) if ty == "String" => Some(format!("{s:?}.into()")),
+        _ => None,
+    }
+}
+
+/// The name of the schema's `type`s, or of a single type if there is one.
+fn types(schema: &Value) -> Vec<&str> {
+    match &schema["type"] {
+        Value::String(ty) => vec![ty.as_str()],
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The alternatives of a schema: its `anyOf` or `oneOf`, or one alternative
+/// per `type` if it names several.
+fn alternatives(schema: &Value) -> Vec<Value> {
+    if let Some(Value::Array(alternatives)) = schema.get("anyOf").or(schema.get("oneOf")) {
+        return alternatives.clone();
+    }
+    let types = types(schema);
+    if types.len() < 2 {
+        return vec![schema.clone()];
+    }
+    types
+        .into_iter()
+        .map(|ty| {
+            let mut alternative = schema.clone();
+            alternative["type"] = ty.into();
+            alternative
+        })
+        .collect()
+}
+
+/// Accumulates the items of the generated module.
+#[derive(Debug, Default)]
+struct Generator {
+    /// Structs and enums, in the order they are first named.
+    items: Vec<String>,
+    /// Type names already taken.
+    names: BTreeSet<String>,
+    /// Default and `skip_serializing_if` functions by name.
+    helpers: BTreeMap<String, String>,
+    uses_must_be: bool,
+    uses_map: bool,
+}
+
+impl Generator {
+    /// Claim a type name based on `name`.
+    fn claim(&mut self, name: &str) -> String {
+        let mut name = pascal_case(name);
+        if RESERVED.contains(&name.as_str()) {
+            name.push_str("Data");
+        }
+        let mut candidate = name.clone();
+        for n in 2.. {
+            if self.names.insert(candidate.clone()) {
+                break;
+            }
+            candidate = format!("{name}{n}");
+        }
+        candidate
+    }
+
+    /// Reserve a slot for an item, so that it precedes the items it uses.
+    fn reserve(&mut self) -> usize {
+        self.items.push(String::new());
+        self.items.len() - 1
+    }
+
+    /// Add a helper function based on `name`, whose code `code` writes given
+    /// its final name, and return that name.
+    ///
+    /// Identical helpers are shared. A helper whose name is taken by different
+    /// code, such as the default of a field which has another default in
+    /// another struct, is numbered.
+    fn helper(&mut self, name: &str, code: impl Fn(&str) -> String) -> String {
+        let mut candidate = name.to_owned();
+        for n in 2.. {
+            let code = code(&candidate);
+            match self.helpers.get(&candidate) {
+                Some(existing) if *existing != code => candidate = format!("{name}{n}"),
+                Some(_) => break,
+                None => {
+                    self.helpers.insert(candidate.clone(), code);
+                    break;
+                }
+            }
+        }
+        candidate
+    }
+
+    /// The Rust type of values matching `schema`, generating any structs or
+    /// enums it needs; `name` suggests a name for them.
+    fn ty(&mut self, schema: &Value, name: &str, path: &str) -> Result<String> {
+        let alternatives = alternatives(schema);
+        if alternatives.len() > 1 {
+            let (nulls, others): (Vec<_>, Vec<_>) =
+                alternatives.iter().partition(|alt| types(alt) == ["null"]);
+            return match others.as_slice() {
+                [] => Err(Error::unsupported(path, "only null is allowed")),
+                [only] if !nulls.is_empty() => {
+                    Ok(format!("Option<{}>", self.ty(only, name, path)?))
+                }
+                _ => self.untagged(&others, name, path),
+            };
+        }
+
+        let ty = match types(schema).first().copied() {
+            Some("null") => "()".into(),
+            Some("boolean") => "bool".into(),
+            Some("number") => "f64".into(),
+            Some("integer") => {
+                let min = schema["minimum"].as_f64();
+                let max = schema["maximum"].as_f64();
+                match (min, max) {
+                    (Some(min), Some(max)) if min >= 0.0 && max <= u32::MAX as f64 => "u32".into(),
+                    (Some(min), _) if min >= 0.0 => "u64".into(),
+                    _ => "i64".into(),
+                }
+            }
+            Some("string") => match schema["enum"].as_array().map(Vec::as_slice) {
+                Some([Value::String(tag)]) => {
+                    self.uses_must_be = true;
+                    format!("MustBe!({tag:?})")
+                }
+                _ => "String".into(),
+            },
+            Some("array") => {
+                if let Some(Value::Array(positions)) = schema.get("prefixItems") {
+                    if positions.is_empty() {
+                        return Err(Error::unsupported(path, "empty prefixItems"));
+                    }
+                    let positions = positions
+                        .iter()
+                        .enumerate()
+                        .map(|(index, position)| {
+                            self.ty(
+                                position,
+                                &format!("{name}-{index}"),
+                                &format!("{path}/{index}"),
+                            )
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    match positions.as_slice() {
+                        [only] => format!("({only},)"),
+                        _ => format!("({})", positions.join(", ")),
+                    }
+                } else {
+                    match schema.get("items") {
+                        Some(items @ Value::Object(_)) => {
+                            let item = self.ty(items, &singular(name), &format!("{path}/*"))?;
+                            format!("Vec<{item}>")
+                        }
+                        _ => "Vec<serde_json::Value>".into(),
+                    }
+                }
+            }
+            Some("object") | None if schema.get("properties").is_some() => {
+                self.structure(schema, name, path, None)?
+            }
+            Some("object") => match schema.get("additionalProperties") {
+                Some(values @ Value::Object(_)) => {
+                    self.uses_map = true;
+                    let value = self.ty(values, &singular(name), &format!("{path}/*"))?;
+                    format!("BTreeMap<String, {value}>")
+                }
+                _ => "serde_json::Value".into(),
+            },
+            None => "serde_json::Value".into(),
+            Some(_) => return Err(Error::unsupported(path, "unknown type")),
+        };
+        Ok(ty)
+    }
+
+    /// An untagged enum with a variant per alternative.
+    fn untagged(&mut self, alternatives: &[&Value], name: &str, path: &str) -> Result<String> {
+        let enum_name = self.claim(name);
+        let slot = self.reserve();
+
+        let mut variants = Vec::new();
+        let mut taken = BTreeSet::new();
+        for (index, alternative) in alternatives.iter().enumerate() {
+            let kind = match types(alternative).first().copied() {
+                _ if alternative["title"].is_string() => {
+                    pascal_case(alternative["title"].as_str().unwrap())
+                }
+                Some("boolean") => "Bool".into(),
+                Some("integer") => "Integer".into(),
+                Some("number") => "Number".into(),
+                Some("string") => "String".into(),
+                Some("array") if alternative.get("prefixItems").is_some() => "Tuple".into(),
+                Some("array") => "List".into(),
+                _ => "Table".into(),
+            };
+            let mut variant = kind.clone();
+            for n in 2.. {
+                if taken.insert(variant.clone()) {
+                    break;
+                }
+                variant = format!("{kind}{n}");
+            }
+
+            let ty = self.ty(
+                alternative,
+                &format!("{enum_name}-{variant}"),
+                &format!("{path}/anyOf/{index}"),
+            )?;
+            // tuples are spelled as tuple variants, like `SimpleItem(String, u32)`
+            let fields = match ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+                Some(fields) => fields.to_owned(),
+                None => ty,
+            };
+            variants.push(format!("    {variant}({fields}),\n"));
+        }
+
+        self.items[slot] = format!(
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             #[serde(untagged)]\n\
+             pub enum {enum_name} {{\n{}}}\n",
+            variants.concat()
+        );
+        Ok(enum_name)
+    }
+
+    /// A struct with a field per property.
+    fn structure(
+        &mut self,
+        schema: &Value,
+        name: &str,
+        path: &str,
+        doc: Option<String>,
+    ) -> Result<String> {
+        let struct_name = self.claim(name);
+        let slot = self.reserve();
+
+        let empty = serde_json::Map::new();
+        let properties = schema["properties"].as_object().unwrap_or(&empty);
+        let required = schema["required"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect::<BTreeSet<_>>();
+
+        let mut fields = String::new();
+        for (key, property) in properties {
+            let field = snake_case(key);
+            let ty = self.ty(property, key, &format!("{path}/{key}"))?;
+
+            if let Some(description) = property["description"].as_str() {
+                for line in description.lines() {
+                    writeln!(fields, "    /// {line}").unwrap();
+                }
+            }
+            if let Some(Value::Array(values)) = property.get("enum") {
+                if values.len() > 1 {
+                    let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                    writeln!(fields, "    /// Observed values: {}.", values.join(", ")).unwrap();
+                }
+            }
+
+            let mut serde = Vec::new();
+            if field.trim_start_matches("r#") != key {
+                serde.push(format!("rename = {key:?}"));
+            }
+            let default = property
+                .get("default")
+                .filter(|_| !required.contains(key.as_str()));
+            let attributes = match default {
+                Some(default) => self.default_attributes(default, &ty, &field),
+                None => None,
+            };
+            let ty = match attributes {
+                _ if required.contains(key.as_str()) => ty,
+                Some(attributes) => {
+                    serde.extend(attributes);
+                    ty
+                }
+                None => {
+                    serde.extend([
+                        "default".into(),
+                        r#"skip_serializing_if = "Option::is_none""#.into(),
+                    ]);
+                    // a nullable field is already optional; absent and null
+                    // are not told apart
+                    if ty.starts_with("Option<") {
+                        ty
+                    } else {
+                        format!("Option<{ty}>")
+                    }
+                }
+            };
+            if !serde.is_empty() {
+                writeln!(fields, "    #[serde({})]", serde.join(", ")).unwrap();
+            }
+            writeln!(fields, "    pub {field}: {ty},").unwrap();
+        }
+
+        // alternative sets of fields, such as `result` versus `results`
+        let variants = schema
+            .get("anyOf")
+            .or(schema.get("oneOf"))
+            .and_then(Value::as_array)
+            .filter(|_| !properties.is_empty());
+        if let Some(variants) = variants {
+            let enum_name = format!("{struct_name}-variant");
+            let variants = variants.iter().collect::<Vec<_>>();
+            let ty = self.untagged(&variants, &enum_name, &format!("{path}/anyOf"))?;
+            writeln!(fields, "    #[serde(flatten)]").unwrap();
+            writeln!(fields, "    pub variant: {ty},").unwrap();
+        }
+
+        let doc = doc.or_else(|| schema["description"].as_str().map(Into::into));
+        let doc = doc
+            .map(|doc| {
+                doc.lines()
+                    .map(|line| format!("/// {line}\n"))
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+        self.items[slot] = format!(
+            "{doc}#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             pub struct {struct_name} {{\n{fields}}}\n"
+        );
+        Ok(struct_name)
+    }
+
+    /// The Serde attributes giving a field of type `ty` its default, or `None`
+    /// if the default cannot be expressed.
+    fn default_attributes(
+        &mut self,
+        default: &Value,
+        ty: &str,
+        field: &str,
+    ) -> Option<Vec<String>> {
+        if ty.starts_with("Option<") {
+            return None;
+        }
+        let skip = |name: &str| format!("skip_serializing_if = {name:?}");
+        let attributes = match default {
+            Value::Bool(false) => {
+                let is_false = self.helper("is_false", |name| {
+                    format!("fn {name}(v: &bool) -> bool {{\n    !*v\n}}\n")
+                });
+                vec!["default".into(), skip(&is_false)]
+            }
+            Value::Bool(true) => {
+                let true_ = self.helper("true_", |name| {
+                    format!("fn {name}() -> bool {{\n    true\n}}\n")
+                });
+                let is_true = self.helper("is_true", |name| {
+                    format!("fn {name}(v: &bool) -> bool {{\n    *v\n}}\n")
+                });
+                vec![format!("default = {true_:?}"), skip(&is_true)]
+            }
+            Value::String(s) if s.is_empty() && ty == "String" => {
+                vec!["default".into(), skip("String::is_empty")]
+            }
+            Value::Array(a) if a.is_empty() && ty.starts_with("Vec<") => {
+                vec!["default".into(), skip("Vec::is_empty")]
+            }
+            Value::Number(n)
+                if n.as_f64() == Some(0.0) && ["f64", "u32", "u64", "i64"].contains(&ty) =>
+            {
+                let zero = literal(default, ty)?;
+                let is_zero = self.helper(&format!("is_zero_{ty}"), |name| {
+                    format!("fn {name}(v: &{ty}) -> bool {{\n    *v == {zero}\n}}\n")
+                });
+                vec!["default".into(), skip(&is_zero)]
+            }
+            Value::Number(n)
+                if n.as_f64() == Some(1.0) && ["f64", "u32", "u64", "i64"].contains(&ty) =>
+            {
+                let one = literal(default, ty)?;
+                let one_fn = self.helper(&format!("one_{ty}"), |name| {
+                    format!("fn {name}() -> {ty} {{\n    {one}\n}}\n")
+                });
+                let is_one = self.helper(&format!("is_one_{ty}"), |name| {
+                    format!("fn {name}(v: &{ty}) -> bool {{\n    *v == {one}\n}}\n")
+                });
+                vec![format!("default = {one_fn:?}"), skip(&is_one)]
+            }
+            Value::Number(_) | Value::String(_) => {
+                let value = literal(default, ty)?;
+                let field = field.trim_start_matches("r#");
+                let default_fn = self.helper(&format!("default_{field}"), |name| {
+                    format!("fn {name}() -> {ty} {{\n    {value}\n}}\n")
+                });
+                let compare = match default {
+                    Value::String(s) => format!("*v == {s:?}"),
+                    _ => format!("*v == {value}"),
+                };
+                let is_default = self.helper(&format!("is_default_{field}"), |name| {
+                    format!("fn {name}(v: &{ty}) -> bool {{\n    {compare}\n}}\n")
+                });
+                vec![format!("default = {default_fn:?}"), skip(&is_default)]
+            }
+            _ => return None,
+        };
+        Some(attributes)
+    }
+}
+
+/// Generate a module of Rust models for values matching `schema`.
+///
+/// The root struct is named `name`, or else after the schema's `title`, such
+/// as `AssemblingMachine` for `assembling-machine`.
+pub fn generate(schema: &Value, name: Option<&str>) -> Result<String> {
+    let kind = name
+        .or(schema["title"].as_str())
+        .ok_or(Error::MissingName)?;
+    let doc = schema["title"]
+        .as_str()
+        .map(|title| format!("A `{title}` prototype."));
+
+    let mut generator = Generator::default();
+    if schema.get("properties").is_some() {
+        generator.structure(schema, kind, "", doc)?;
+    } else {
+        generator.ty(schema, kind, "")?;
+    }
+
+    let mut module = String::new();
+    if generator.uses_map {
+        module.push_str("use std::collections::BTreeMap;\n\n");
+    }
+    if generator.uses_must_be {
+        module.push_str("use monostate::MustBe;\n");
+    }
+    module.push_str("use serde::{Deserialize, Serialize};\n");
+    for helper in generator.helpers.values() {
+        module.push('\n');
+        module.push_str(helper);
+    }
+    for item in generator.items {
+        module.push('\n');
+        module.push_str(&item);
+    }
+    Ok(module)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the schema has no title, so the root type must be named")]
+    MissingName,
+    #[error("unsupported schema at \"{path}\": {reason}")]
+    Unsupported { path: String, reason: &'static str },
+}
+
+impl Error {
+    fn unsupported(path: &str, reason: &'static str) -> Self {
+        Self::Unsupported {
+            path: if path.is_empty() {
+                "/".into()
+            } else {
+                path.into()
+            },
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema::{infer, prototype_schema};
+
+    #[test]
+    fn generates_curated_models() {
+        let schema = json!({
+            "title": "recipe",
+            "type": "object",
+            "properties": {
+                "type": {"type": "string", "enum": ["recipe"]},
+                "name": {"type": "string"},
+                "enabled": {"type": "boolean", "default": true},
+                "energy_required": {"type": "number", "default": 0.5},
+                "allow-decomposition": {"type": "boolean"}
+            },
+            "required": ["type", "name"],
+            "anyOf": [
+                {"title": "single", "properties": {"result": {"type": "string"}}, "required": ["result"]},
+                {"title": "many", "properties": {"results": {"type": "array"}}, "required": ["results"]}
+            ]
+        });
+
+        let module = generate(&schema, None).unwrap();
+        for expect in [
+            "use monostate::MustBe;",
+            "fn true_() -> bool {\n    true\n}",
+            "fn is_default_energy_required(v: &f64) -> bool {\n    *v == 0.5\n}",
+            "/// A `recipe` prototype.\n#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\npub struct Recipe {",
+            "    #[serde(rename = \"allow-decomposition\", default, skip_serializing_if = \"Option::is_none\")]\n    pub allow_decomposition: Option<bool>,",
+            "    #[serde(default = \"true_\", skip_serializing_if = \"is_true\")]\n    pub enabled: bool,",
+            "    pub r#type: MustBe!(\"recipe\"),",
+            "    #[serde(flatten)]\n    pub variant: RecipeVariant,",
+            "#[serde(untagged)]\npub enum RecipeVariant {\n    Single(RecipeVariantSingle),\n    Many(RecipeVariantMany),\n}",
+            "pub struct RecipeVariantMany {\n    pub results: Vec<serde_json::Value>,\n}",
+        ] {
+            assert!(module.contains(expect), "missing {expect:?} from:\n{module}");
+        }
+
+        assert!(matches!(
+            generate(&json!({"type": "object"}), None),
+            Err(Error::MissingName)
+        ));
+    }
+
+    #[test]
+    fn distinguishes_defaults() {
+        let schema = json!({
+            "title": "recipe",
+            "type": "object",
+            "properties": {
+                "energy_required": {"type": "number", "default": 0.5},
+                "normal": {
+                    "type": "object",
+                    "properties": {
+                        "energy_required": {"type": "number", "default": 2},
+                        "result_count": {"type": ["integer", "null"], "minimum": 1}
+                    }
+                }
+            }
+        });
+
+        let module = generate(&schema, None).unwrap();
+        for expect in [
+            "fn default_energy_required() -> f64 {\n    0.5\n}",
+            "fn default_energy_required2() -> f64 {\n    2.0\n}",
+            "    #[serde(default = \"default_energy_required2\", skip_serializing_if = \"is_default_energy_required2\")]\n    pub energy_required: f64,",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub result_count: Option<u64>,",
+        ] {
+            assert!(module.contains(expect), "missing {expect:?} from:\n{module}");
+        }
+    }
+
+    #[test]
+    fn generates_inferred_models() {
+        let dump = crate::parse_lua_value(
+            r#"{
+                recipe = {
+                    a = {type = "recipe", name = "a", ingredients = {{"b", 1}}, results = {{name = "a", amount = 1}}},
+                    b = {type = "recipe", name = "b", ingredients = {{type = "fluid", name = "c", amount = 10}}}
+                }
+            }"#,
+        )
+        .unwrap();
+        let schema = prototype_schema("recipe", &infer(&dump, 4)["recipe"]);
+
+        let module = generate(&schema, None).unwrap();
+        for expect in [
+            "    pub ingredients: Vec<Ingredient>,",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub results: Option<Vec<ResultData>>,",
+            "pub enum Ingredient {\n    Table(IngredientTable),\n    Tuple(String, u32),\n}",
+            "pub struct ResultData {\n    pub amount: u32,\n    pub name: String,\n}",
+        ] {
+            assert!(module.contains(expect), "missing {expect:?} from:\n{module}");
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! How much of the prototype data the typed models capture.
+//!
+//! For each prototype type in a dump, every field observed is listed with the
+//! number of prototypes which use it and some example values. Fields nested in
+//! tables are listed by their [`Path`], such as `energy_source.type`, and the
+//! elements of lists by a wildcard, such as `results.*.amount`.
+//!
+//! For types with a model in [`MODELS`], each field is also checked against
+//! the model: a field is captured by a prototype if it survives the round trip
+//! described in [`validate`][crate::validate].
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    query::{Path, Segment},
+    validate::{Outcome, MODELS},
+};
+
+/// How one field of a prototype type is used.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldCoverage {
+    /// The number of prototypes with the field.
+    pub count: usize,
+    /// The number of prototypes whose model captures the field, if the type
+    /// has a model.
+    pub captured: Option<usize>,
+    /// Distinct values of the field, in the order first seen.
+    pub examples: Vec<Value>,
+}
+
+impl FieldCoverage {
+    /// `true` if the type has a model, and it captures the field in every
+    /// prototype.
+    pub fn is_captured(&self) -> bool {
+        self.captured == Some(self.count)
+    }
+}
+
+/// How the fields of one prototype type are used.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeCoverage {
+    pub kind: String,
+    /// The number of prototypes of the type.
+    pub prototypes: usize,
+    pub modeled: bool,
+    /// Coverage by field path.
+    pub fields: BTreeMap<String, FieldCoverage>,
+}
+
+/// Visit every field nested in `value`, with its path and JSON pointer.
+fn fields<'v>(
+    value: &'v Value,
+    path: &mut Vec<Segment>,
+    pointer: &str,
+    visit: &mut impl FnMut(&[Segment], &str, &'v Value),
+) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let pointer = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                path.push(Segment::Key(key.clone()));
+                visit(path, &pointer, value);
+                fields(value, path, &pointer, visit);
+                path.pop();
+            }
+        }
+        Value::Array(array) => {
+            path.push(Segment::Wildcard);
+            for (index, value) in array.iter().enumerate() {
+                fields(value, path, &format!("{pointer}/{index}"), visit);
+            }
+            path.pop();
+        }
+        _ => {}
+    }
+}
+
+impl TypeCoverage {
+    /// Survey the prototypes of type `kind`, keeping up to `examples` example
+    /// values of each field.
+    pub fn new<'v>(
+        kind: &str,
+        prototypes: impl IntoIterator<Item = &'v Value>,
+        examples: usize,
+    ) -> Self {
+        let model = MODELS.iter().find(|model| model.kind == kind);
+        let mut coverage = Self {
+            kind: kind.into(),
+            prototypes: 0,
+            modeled: model.is_some(),
+            fields: BTreeMap::new(),
+        };
+
+        for prototype in prototypes {
+            coverage.prototypes += 1;
+
+            // pointers to the fields the model drops; everything within them
+            // is dropped too
+            let missing = match model.map(|model| Outcome::new(model, prototype)) {
+                Some(Outcome::MissingFields(differences) | Outcome::Mismatched(differences)) => {
+                    differences
+                        .into_iter()
+                        .filter(|difference| difference.is_missing())
+                        .map(|difference| difference.pointer)
+                        .collect()
+                }
+                Some(Outcome::Failed(_)) => vec![String::new()],
+                Some(Outcome::Passed) | None => Vec::new(),
+            };
+            let is_missing = |pointer: &str| {
+                missing.iter().any(|missing| {
+                    pointer
+                        .strip_prefix(missing.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+            };
+
+            // whether each field is captured everywhere it appears
+            let mut used = BTreeMap::<String, bool>::new();
+            fields(
+                prototype,
+                &mut Vec::new(),
+                "",
+                &mut |path, pointer, value| {
+                    let path = Path(path.to_vec()).to_string();
+                    let captured = used.entry(path.clone()).or_insert(true);
+                    *captured &= !is_missing(pointer);
+
+                    let field = coverage
+                        .fields
+                        .entry(path)
+                        .or_insert_with(|| FieldCoverage {
+                            count: 0,
+                            captured: model.map(|_| 0),
+                            examples: Vec::new(),
+                        });
+                    if field.examples.len() < examples && !field.examples.contains(value) {
+                        field.examples.push(value.clone());
+                    }
+                },
+            );
+
+            for (path, captured) in used {
+                let field = coverage.fields.get_mut(&path).unwrap();
+                field.count += 1;
+                if let Some(count) = &mut field.captured {
+                    *count += usize::from(captured);
+                }
+            }
+        }
+        coverage
+    }
+}
+
+/// Survey the prototype types in `dump` for which `include` holds, keeping up
+/// to `examples` example values of each field.
+///
+/// Surveying round-trips every prototype of a modeled type, so types which
+/// are not needed are best left out here rather than filtered afterwards.
+pub fn coverage(
+    dump: &Value,
+    examples: usize,
+    mut include: impl FnMut(&str) -> bool,
+) -> Vec<TypeCoverage> {
+    let Some(types) = dump.as_object() else {
+        return Vec::new();
+    };
+    types
+        .iter()
+        .filter(|(kind, _)| include(kind))
+        .filter_map(|(kind, prototypes)| {
+            let prototypes = prototypes.as_object()?;
+            Some(TypeCoverage::new(kind, prototypes.values(), examples))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn surveys_fields() {
+        let dump = json!({
+            "recipe": {
+                "copper-cable": {
+                    "type": "recipe", "name": "copper-cable",
+                    "ingredients": [["copper-plate", 1]],
+                    "result": "copper-cable", "result_count": 2
+                },
+                "plastic-bar": {
+                    "type": "recipe", "name": "plastic-bar", "category": "chemistry",
+                    "ingredients": [
+                        {"type": "fluid", "name": "petroleum-gas", "amount": 20},
+                        {"type": "item", "name": "coal", "amount": 1}
+                    ],
+                    "results": [{"type": "item", "name": "plastic-bar", "amount": 2}],
+                    "crafting_machine_tint": {"primary": {"r": 1}}
+                }
+            },
+            "tile": {
+                "grass-1": {"type": "tile", "name": "grass-1", "walking_speed_modifier": 1}
+            }
+        });
+
+        let found = coverage(&dump, 2, |_| true);
+        let [recipe, tile] = found.as_slice() else {
+            panic!("expected two types");
+        };
+
+        assert_eq!(recipe.prototypes, 2);
+        assert!(recipe.modeled);
+        assert_eq!(
+            recipe.fields["name"],
+            FieldCoverage {
+                count: 2,
+                captured: Some(2),
+                examples: vec![json!("copper-cable"), json!("plastic-bar")],
+            }
+        );
+        assert!(recipe.fields["ingredients.*.amount"].is_captured());
+        assert_eq!(recipe.fields["ingredients.*.amount"].count, 1);
+        assert_eq!(
+            recipe.fields["ingredients.*.type"].examples,
+            [json!("fluid"), json!("item")]
+        );
+        assert_eq!(recipe.fields["crafting_machine_tint"].captured, Some(0));
+        assert_eq!(
+            recipe.fields["crafting_machine_tint.primary.r"].captured,
+            Some(0)
+        );
+
+        assert!(!tile.modeled);
+        assert_eq!(tile.fields["walking_speed_modifier"].captured, None);
+    }
+}
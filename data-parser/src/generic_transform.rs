@@ -76,6 +76,29 @@ impl From<Value> for serde_json::Value {
     }
 }
 
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Object(object) => Value::Table(
+                object
+                    .into_iter()
+                    .map(|(k, v)| (k.as_str().into(), v.into()))
+                    .collect(),
+            ),
+            serde_json::Value::Array(array) => {
+                Value::List(array.into_iter().map(Into::into).collect())
+            }
+            serde_json::Value::String(s) => Value::String(s.as_str().into()),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(n) => Value::Integer(n),
+                None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Null => Value::Nil,
+        }
+    }
+}
+
 fn parse_token_ref(token_ref: &full_moon::tokenizer::TokenReference) -> Result<Value> {
     match token_ref.token_type() {
         full_moon::tokenizer::TokenType::StringLiteral { literal: value, .. }
@@ -1,6 +1,6 @@
 //! # Factorio Recipe Planner: Data Parser
 //!
-//! This crate implements low-level parsing of Factorio data. It offers three
+//! This crate implements low-level parsing of Factorio data. It offers these
 //! key facilities:
 //!
 //! ## `into-json` script
@@ -23,6 +23,114 @@
 //! This function offers a programmatic interface to producing JSON from
 //! Factorio Lua prototypes.
 //!
+//! The related [`load`] function reads a dump from disk, in whichever form it
+//! happens to be: the raw Lua dump, a single JSON file, or a directory of JSON
+//! files produced by `into-json --split-toplevel`.
+//!
+//! [`parse_lua_value`] and [`load_value`] do the same, but produce a
+//! [`generic_transform::Value`] rather than JSON.
+//!
+//! ## `query` script
+//!
+//! This script searches a data dump, without splitting it into JSON first,
+//! and prints the results as a table, JSON or CSV.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin query -- prototype-data.lua 'recipe.*' \
+//!     --where 'category == "chemistry" and energy_required > 2' \
+//!     --select name,time=energy_required,inputs='ingredients[*].name'
+//! ```
+//!
+//! ## [`query`] module
+//!
+//! A [`Query`][query::Query] selects values with a path such as
+//! `recipe.*.ingredients[*]`, keeps those matching a filter, and projects
+//! each onto a list of fields. Keys a prototype leaves out fall back to its
+//! `normal` difficulty variant and then to the game's defaults, so filters see
+//! recipes as the game does.
+//!
+//! ## `validate-models` script
+//!
+//! This script checks that the [`models`] parse every prototype of a data
+//! dump and reserialize to the same value, and summarizes the outcome for each
+//! prototype type. Mismatches are listed field by field as JSON pointers.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin validate-models -- prototype-data.lua --type recipe
+//! ```
+//!
+//! The script exits with an error if any prototype fails to parse or
+//! reserializes differently, so that it can be run against each mod pack in
+//! CI. Fields which incomplete models leave out are not errors, but can be
+//! listed with `--show-missing`.
+//!
+//! ## [`validate`] module
+//!
+//! A [`Report`][validate::Report] holds the [`Outcome`][validate::Outcome] of
+//! round-tripping each prototype of one type through its model, as listed in
+//! [`MODELS`][validate::MODELS].
+//!
+//! ## `field-coverage` script
+//!
+//! This script lists every field used by each prototype type of a data dump,
+//! how many prototypes use it, whether the typed model captures it, and some
+//! example values. It shows which fields to model next, and which fields a
+//! Factorio update or a mod has added.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin field-coverage -- prototype-data.lua --modeled --uncaptured
+//! ```
+//!
+//! ## [`coverage`] module
+//!
+//! A [`TypeCoverage`][coverage::TypeCoverage] surveys the fields of one
+//! prototype type, nested fields included, and checks each against the type's
+//! model with the same round trip as [`validate`].
+//!
+//! ## `infer-schema` script
+//!
+//! This script infers a JSON Schema for each prototype type of a data dump,
+//! to validate other dumps, such as those of mod packs, or to bootstrap new
+//! models.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin infer-schema -- --split-toplevel prototype-data.lua schemas
+//! ```
+//!
+//! ## [`schema`] module
+//!
+//! A [`Shape`][schema::Shape] accumulates what has been observed of a set of
+//! [`generic_transform::Value`]s: the fields of tables and how often each
+//! appears, the types of values, the range of numbers, and the values of
+//! strings which take few. It can be emitted as a JSON Schema.
+//!
+//! ## `codegen` script
+//!
+//! This script generates Rust models for a prototype type from its JSON
+//! Schema, whether inferred by `infer-schema` or curated by hand, in the style
+//! of the [`models`] module.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin codegen -- schemas/lab.json --output data-parser/src/models/lab.rs
+//! ```
+//!
+//! ## [`codegen`] module
+//!
+//! The [`generate`][codegen::generate] function turns a JSON Schema into Serde
+//! structs and enums: optional fields become `Option`s or take the schema's
+//! `default`, single-valued strings become `MustBe!` tags, and alternatives
+//! become untagged enums.
+//!
 //! ## [`models`] module
 //!
 //! This module contains low-level Serde-compatible models which can be used to
@@ -30,9 +138,12 @@
 //!
 //! This module is intentionally incomplete, as Factorio uses a very flexible
 //! prototype system which is presumably quite nice to write by hand but which
-//! is a pain to parse precisely. For now, the only type which is complete is
-//! the [`Recipe`][models::Recipe] struct, as that is the focus of the
-//! downstream tooling for which this library was initially written.
+//! is a pain to parse precisely. The [`Recipe`][models::Recipe] struct is the
+//! most thorough, as that is the focus of the downstream tooling for which this
+//! library was initially written, but it still leaves out presentational fields
+//! such as `crafting_machine_tint`. Other models, such as
+//! [`CraftingMachine`][models::CraftingMachine] and [`Module`][models::Module],
+//! capture only the fields which the downstream tooling needs.
 //!
 //! Models which have been indicated to be complete parse losslessly from
 //! Factorio definitions. They do not quite reserialize identically to the
@@ -47,11 +158,24 @@
 //! main logic of your program. In the future, a crate in this workspace will
 //! provide appropriate higher-level models.
 
+use std::path::Path;
+
+pub mod codegen;
+pub mod coverage;
 pub mod generic_transform;
 pub mod models;
+pub mod query;
+pub mod schema;
+pub mod validate;
 
 /// Parse the definitions into a `serde_json::Value`.
-pub fn parse_lua(mut prototype_data: &str) -> Result<serde_json::Value, Error> {
+pub fn parse_lua(prototype_data: &str) -> Result<serde_json::Value, Error> {
+    Ok(parse_lua_value(prototype_data)?.into())
+}
+
+/// Parse the definitions into a [`generic_transform::Value`], without
+/// converting them to JSON.
+pub fn parse_lua_value(mut prototype_data: &str) -> Result<generic_transform::Value, Error> {
     const EXPECTED_HEADER: &str = "Script @__DataRawSerpent__/data-final-fixes.lua:1: ";
 
     // strip the header if it appears; don't change the input otherwise
@@ -97,9 +221,53 @@ pub fn parse_lua(mut prototype_data: &str) -> Result<serde_json::Value, Error> {
         .expect("we created a single-expression assignment but no expressions found")
         .value();
 
-    let gt_value = generic_transform::parse_value(expression)?;
+    Ok(generic_transform::parse_value(expression)?)
+}
+
+/// Load a data dump from disk into a `serde_json::Value`.
+///
+/// - Directories are assumed to have been produced by `into-json
+///   --split-toplevel`: each `*.json` file within becomes the top-level key
+///   named by its file stem.
+/// - Files with a `.json` extension are parsed as JSON.
+/// - Anything else is parsed as a Lua dump.
+pub fn load(path: impl AsRef<Path>) -> Result<serde_json::Value, Error> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        let mut object = serde_json::Map::new();
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let data = std::fs::read_to_string(&path)?;
+            object.insert(name.to_owned(), serde_json::from_str(&data)?);
+        }
+        return Ok(object.into());
+    }
+
+    let data = std::fs::read_to_string(path)?;
+    if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+        Ok(serde_json::from_str(&data)?)
+    } else {
+        parse_lua(&data)
+    }
+}
 
-    Ok(gt_value.into())
+/// Load a data dump from disk into a [`generic_transform::Value`].
+///
+/// Lua dumps are parsed directly; JSON dumps are converted. See [`load`] for
+/// the accepted formats.
+pub fn load_value(path: impl AsRef<Path>) -> Result<generic_transform::Value, Error> {
+    let path = path.as_ref();
+    if path.is_dir() || path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+        return Ok(load(path)?.into());
+    }
+    parse_lua_value(&std::fs::read_to_string(path)?)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -108,4 +276,8 @@ pub enum Error {
     Lua(#[from] Box<full_moon::Error>),
     #[error("performing generic tranform on input data")]
     GenericTransform(#[from] generic_transform::Error),
+    #[error("reading input data")]
+    Io(#[from] std::io::Error),
+    #[error("parsing json input")]
+    Json(#[from] serde_json::Error),
 }
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+use super::module::ModuleSpecification;
+
+/// A `beacon` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beacon {
+    pub name: String,
+    pub r#type: String,
+    pub energy_usage: String,
+    pub distribution_effectivity: f64,
+    pub module_specification: ModuleSpecification,
+    /// The effects which this beacon transmits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_effects: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_beacon() {
+        const BEACON: &str = r#"{"allowed_effects":["consumption","speed","pollution"],"distribution_effectivity":0.5,"energy_usage":"480kW","module_specification":{"module_slots":2},"name":"beacon","type":"beacon"}"#;
+
+        let expect = Beacon {
+            name: "beacon".into(),
+            r#type: "beacon".into(),
+            energy_usage: "480kW".into(),
+            distribution_effectivity: 0.5,
+            module_specification: ModuleSpecification { module_slots: 2 },
+            allowed_effects: vec!["consumption".into(), "speed".into(), "pollution".into()],
+        };
+
+        let found = serde_json::from_str::<Beacon>(BEACON).unwrap();
+        assert_eq!(found, expect);
+    }
+}
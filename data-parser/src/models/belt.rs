@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// A `transport-belt` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransportBelt {
+    pub name: String,
+    pub r#type: String,
+    /// Belt speed in tiles per tick.
+    pub speed: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_transport_belt() {
+        const BELT: &str =
+            r#"{"name":"fast-transport-belt","speed":0.0625,"type":"transport-belt"}"#;
+
+        let expect = TransportBelt {
+            name: "fast-transport-belt".into(),
+            r#type: "transport-belt".into(),
+            speed: 0.0625,
+        };
+
+        let found = serde_json::from_str::<TransportBelt>(BELT).unwrap();
+        assert_eq!(found, expect);
+    }
+}
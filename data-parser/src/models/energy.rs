@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+fn is_zero(v: &f64) -> bool {
+    *v == 0.0
+}
+
+/// The energy source of an entity: `electric`, `burner`, `heat`, `fluid` or
+/// `void`.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergySource {
+    pub r#type: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub emissions_per_minute: f64,
+    /// Power drawn by an electric entity even when idle. Defaults to 1/30 of
+    /// the entity's `energy_usage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drain: Option<String>,
+    /// The fuel category burned by a burner entity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel_category: Option<String>,
+    /// The efficiency with which a burner entity converts fuel to energy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effectivity: Option<f64>,
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+fn one_f64() -> f64 {
+    1.0
+}
+
+fn is_one_f64(v: &f64) -> bool {
+    *v == 1.0
+}
+
+/// A `generator` prototype, such as the steam engine.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Generator {
+    pub name: String,
+    pub r#type: String,
+    /// Units of fluid consumed per tick at full output.
+    pub fluid_usage_per_tick: f64,
+    /// The hottest fluid temperature which adds to the output.
+    pub maximum_temperature: f64,
+    #[serde(default = "one_f64", skip_serializing_if = "is_one_f64")]
+    pub effectivity: f64,
+    /// Overrides the output computed from fluid usage and temperature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_power_output: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_generator() {
+        const STEAM_ENGINE: &str = r#"{"effectivity":1,"fluid_usage_per_tick":0.5,"maximum_temperature":165,"name":"steam-engine","type":"generator"}"#;
+
+        let expect = Generator {
+            name: "steam-engine".into(),
+            r#type: "generator".into(),
+            fluid_usage_per_tick: 0.5,
+            maximum_temperature: 165.0,
+            effectivity: 1.0,
+            max_power_output: None,
+        };
+
+        let found = serde_json::from_str::<Generator>(STEAM_ENGINE).unwrap();
+        assert_eq!(found, expect);
+    }
+}
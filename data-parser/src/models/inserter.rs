@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+fn is_false(v: &bool) -> bool {
+    !*v
+}
+
+/// An `inserter` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inserter {
+    pub name: String,
+    pub r#type: String,
+    /// Rotation speed in revolutions per tick.
+    pub rotation_speed: f64,
+    /// Extension speed in tiles per tick.
+    pub extension_speed: f64,
+    /// Whether this inserter benefits from stack inserter capacity bonuses.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub stack: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_inserter() {
+        const INSERTER: &str = r#"{"extension_speed":0.07,"name":"stack-inserter","rotation_speed":0.04,"stack":true,"type":"inserter"}"#;
+
+        let expect = Inserter {
+            name: "stack-inserter".into(),
+            r#type: "inserter".into(),
+            rotation_speed: 0.04,
+            extension_speed: 0.07,
+            stack: true,
+        };
+
+        let found = serde_json::from_str::<Inserter>(INSERTER).unwrap();
+        assert_eq!(found, expect);
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// An `item` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub name: String,
+    pub r#type: String,
+    pub stack_size: u32,
+    /// The energy released by burning one of this item, such as `"4MJ"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel_value: Option<String>,
+    /// The fuel category, such as `chemical`, of a burnable item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel_category: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fuel() {
+        const COAL: &str = r#"{"fuel_category":"chemical","fuel_value":"4MJ","name":"coal","stack_size":50,"type":"item"}"#;
+
+        let expect = Item {
+            name: "coal".into(),
+            r#type: "item".into(),
+            stack_size: 50,
+            fuel_value: Some("4MJ".into()),
+            fuel_category: Some("chemical".into()),
+        };
+
+        let found = serde_json::from_str::<Item>(COAL).unwrap();
+        assert_eq!(found, expect);
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::{energy::EnergySource, module::ModuleSpecification};
+
+fn is_zero(v: &f64) -> bool {
+    *v == 0.0
+}
+
+/// A crafting machine: an `assembling-machine`, `furnace`, or `rocket-silo`
+/// prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CraftingMachine {
+    pub name: String,
+    pub r#type: String,
+    pub crafting_speed: f64,
+    pub crafting_categories: Vec<String>,
+    pub energy_usage: String,
+    pub energy_source: EnergySource,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub base_productivity: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module_specification: Option<ModuleSpecification>,
+    /// The module and beacon effects which apply to this machine. None are
+    /// allowed by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_effects: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_assembling_machine() {
+        const MACHINE: &str = r#"{"crafting_categories":["basic-crafting","crafting","advanced-crafting","crafting-with-fluid"],"crafting_speed":0.75,"energy_source":{"emissions_per_minute":3,"type":"electric","usage_priority":"secondary-input"},"energy_usage":"150kW","module_specification":{"module_slots":2},"allowed_effects":["consumption","speed","productivity","pollution"],"name":"assembling-machine-2","type":"assembling-machine"}"#;
+
+        let expect = CraftingMachine {
+            name: "assembling-machine-2".into(),
+            r#type: "assembling-machine".into(),
+            crafting_speed: 0.75,
+            crafting_categories: vec![
+                "basic-crafting".into(),
+                "crafting".into(),
+                "advanced-crafting".into(),
+                "crafting-with-fluid".into(),
+            ],
+            energy_usage: "150kW".into(),
+            energy_source: EnergySource {
+                r#type: "electric".into(),
+                emissions_per_minute: 3.0,
+                drain: None,
+                fuel_category: None,
+                effectivity: None,
+            },
+            base_productivity: 0.0,
+            module_specification: Some(ModuleSpecification { module_slots: 2 }),
+            allowed_effects: vec![
+                "consumption".into(),
+                "speed".into(),
+                "productivity".into(),
+                "pollution".into(),
+            ],
+        };
+
+        let found = serde_json::from_str::<CraftingMachine>(MACHINE).unwrap();
+        assert_eq!(found, expect);
+    }
+}
@@ -1 +1,23 @@
+pub mod beacon;
+pub mod belt;
+pub mod energy;
+pub mod generator;
+pub mod inserter;
+pub mod item;
+pub mod machine;
+pub mod module;
 pub mod recipe;
+pub mod solar_panel;
+pub mod technology;
+
+pub use beacon::Beacon;
+pub use belt::TransportBelt;
+pub use energy::EnergySource;
+pub use generator::Generator;
+pub use inserter::Inserter;
+pub use item::Item;
+pub use machine::CraftingMachine;
+pub use module::Module;
+pub use recipe::Recipe;
+pub use solar_panel::SolarPanel;
+pub use technology::Technology;
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// A single effect bonus, such as `{"bonus": 0.5}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EffectValue {
+    pub bonus: f64,
+}
+
+/// The effects of a module, as fractional bonuses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Effect {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consumption: Option<EffectValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<EffectValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub productivity: Option<EffectValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pollution: Option<EffectValue>,
+}
+
+/// The module slots of a machine or beacon.
+///
+/// This model is incomplete: it omits the placement of module icons.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleSpecification {
+    #[serde(default)]
+    pub module_slots: u32,
+}
+
+/// A `module` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Module {
+    pub name: String,
+    pub r#type: String,
+    pub category: String,
+    pub tier: u32,
+    pub effect: Effect,
+    /// The recipes in which this module may be used. Empty if unrestricted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub limitation: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_productivity_module() {
+        const MODULE: &str = r#"{"category":"productivity","effect":{"consumption":{"bonus":0.4},"pollution":{"bonus":0.05},"productivity":{"bonus":0.04},"speed":{"bonus":-0.05}},"limitation":["copper-cable","electronic-circuit"],"name":"productivity-module","tier":1,"type":"module"}"#;
+
+        let expect = Module {
+            name: "productivity-module".into(),
+            r#type: "module".into(),
+            category: "productivity".into(),
+            tier: 1,
+            effect: Effect {
+                consumption: Some(EffectValue { bonus: 0.4 }),
+                speed: Some(EffectValue { bonus: -0.05 }),
+                productivity: Some(EffectValue { bonus: 0.04 }),
+                pollution: Some(EffectValue { bonus: 0.05 }),
+            },
+            limitation: vec!["copper-cable".into(), "electronic-circuit".into()],
+        };
+
+        let found = serde_json::from_str::<Module>(MODULE).unwrap();
+        assert_eq!(found, expect);
+    }
+}
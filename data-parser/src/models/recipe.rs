@@ -15,6 +15,10 @@ fn is_true(v: &bool) -> bool {
     *v
 }
 
+fn is_false(v: &bool) -> bool {
+    !*v
+}
+
 const fn half_second() -> Duration {
     HALF_SECOND
 }
@@ -117,6 +121,8 @@ pub enum Ingredient {
 pub struct RecipeData {
     #[serde(default = "true_", skip_serializing_if = "is_true")]
     pub enabled: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
     #[serde_as(as = "DurationSecondsWithFrac<f64>")]
     #[serde(
         rename = "energy_required",
@@ -124,6 +130,9 @@ pub struct RecipeData {
         skip_serializing_if = "is_half_second"
     )]
     pub duration: Duration,
+    /// Scales the pollution emitted by machines crafting this recipe.
+    #[serde(default = "one_f64", skip_serializing_if = "is_one_f64")]
+    pub emissions_multiplier: f64,
     pub ingredients: Vec<Ingredient>,
     #[serde(flatten)]
     pub output: Output,
@@ -131,8 +140,8 @@ pub struct RecipeData {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecipeDataWithHardMode {
-    normal: RecipeData,
-    expensive: RecipeData,
+    pub normal: RecipeData,
+    pub expensive: RecipeData,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -189,6 +198,7 @@ mod tests {
                 order: None,
                 recipe_data: RecipeDataEnum::Simple(RecipeData {
                     enabled: true,
+                    hidden: false,
                     ingredients: vec![Ingredient::SimpleItem("iron-plate".into(), 1)],
                     output: Output::Single(SingleOutput {
                         name: "iron-stick".into(),
@@ -196,6 +206,7 @@ mod tests {
                         probability: 1.0,
                     }),
                     duration: Duration::milliseconds(500),
+                    emissions_multiplier: 1.0,
                 }),
                 icon: None,
             };
@@ -224,6 +235,7 @@ mod tests {
                 order: None,
                 recipe_data: RecipeDataEnum::Simple(RecipeData {
                     enabled: true,
+                    hidden: false,
                     ingredients: vec![Ingredient::SimpleItem("copper-plate".into(), 1)],
                     output: Output::Single(SingleOutput {
                         name: "copper-cable".into(),
@@ -231,6 +243,7 @@ mod tests {
                         probability: 1.0,
                     }),
                     duration: Duration::milliseconds(500),
+                    emissions_multiplier: 1.0,
                 }),
                 icon: None,
             };
@@ -265,7 +278,9 @@ mod tests {
                 }),
                 recipe_data: RecipeDataEnum::Simple(RecipeData {
                     enabled: false,
+                    hidden: false,
                     duration: Duration::seconds(12),
+                    emissions_multiplier: 1.0,
                     ingredients: vec![Ingredient::SimpleItem("uranium-ore".into(), 10)],
                     output: Output::Many(ManyOutputs {
                         outputs: vec![
@@ -316,7 +331,9 @@ mod tests {
                 }),
                 recipe_data: RecipeDataEnum::Simple(RecipeData {
                     enabled: false,
+                    hidden: false,
                     duration: Duration::seconds(5),
+                    emissions_multiplier: 1.0,
                     ingredients: vec![
                         Ingredient::Fluid(Fluid {
                             amount: 50,
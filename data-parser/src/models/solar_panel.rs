@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// A `solar-panel` prototype.
+///
+/// This model is incomplete: it captures only the fields needed to plan
+/// production.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarPanel {
+    pub name: String,
+    pub r#type: String,
+    /// Power output in full daylight, such as `"60kW"`.
+    pub production: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_solar_panel() {
+        const SOLAR_PANEL: &str =
+            r#"{"name":"solar-panel","production":"60kW","type":"solar-panel"}"#;
+
+        let expect = SolarPanel {
+            name: "solar-panel".into(),
+            r#type: "solar-panel".into(),
+            production: "60kW".into(),
+        };
+
+        let found = serde_json::from_str::<SolarPanel>(SOLAR_PANEL).unwrap();
+        assert_eq!(found, expect);
+    }
+}
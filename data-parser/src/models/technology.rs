@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// A modifier applied when a technology is researched, such as
+/// `{"type":"unlock-recipe","recipe":"fast-inserter"}`.
+///
+/// This model is incomplete: it captures only recipe unlocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TechnologyEffect {
+    pub r#type: String,
+    /// The recipe enabled by an `unlock-recipe` effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<String>,
+}
+
+/// A `technology` prototype.
+///
+/// This model is incomplete: it captures only what a technology unlocks and
+/// requires, and ignores `normal` and `expensive` variants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Technology {
+    pub name: String,
+    pub r#type: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<TechnologyEffect>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerequisites: Vec<String>,
+}
+
+impl Technology {
+    /// The recipes enabled by researching this technology.
+    pub fn unlocked_recipes(&self) -> impl Iterator<Item = &str> {
+        self.effects
+            .iter()
+            .filter(|effect| effect.r#type == "unlock-recipe")
+            .filter_map(|effect| effect.recipe.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_technology() {
+        const TECHNOLOGY: &str = r#"{"effects":[{"recipe":"fast-transport-belt","type":"unlock-recipe"},{"recipe":"fast-underground-belt","type":"unlock-recipe"},{"modifier":1,"type":"inserter-stack-size-bonus"}],"name":"logistics-2","prerequisites":["logistics","logistic-science-pack"],"type":"technology"}"#;
+
+        let found = serde_json::from_str::<Technology>(TECHNOLOGY).unwrap();
+        assert_eq!(found.name, "logistics-2");
+        assert_eq!(found.prerequisites, ["logistics", "logistic-science-pack"]);
+        assert_eq!(
+            found.unlocked_recipes().collect::<Vec<_>>(),
+            ["fast-transport-belt", "fast-underground-belt"]
+        );
+    }
+}
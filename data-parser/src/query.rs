@@ -0,0 +1,767 @@
+//! Queries over generic values.
+//!
+//! A [`Query`] selects values from a data dump with a [`Path`], keeps those
+//! matching a [`Filter`], and projects each onto a list of [`Field`]s:
+//!
+//! - paths are dot-separated keys, where `*` matches every key of a table and
+//!   `[*]` or `[n]` the elements of a list: `recipe.*.ingredients[*]`
+//! - filters compare paths relative to each value with literals, and combine
+//!   comparisons with `and`, `or`, `not` and parentheses:
+//!   `category == "chemistry" and energy_required > 10`
+//! - fields are paths relative to each value, optionally named: `time=energy_required`
+//!
+//! Queries understand a little of Factorio's prototype semantics. A key
+//! missing from a prototype is looked up in its `normal` difficulty variant,
+//! and then among the defaults the game applies, so that `category ==
+//! "crafting"` matches recipes which never name a category.
+
+use std::{borrow::Cow, cmp::Ordering, fmt, str::FromStr};
+
+use crate::generic_transform::{Table, Value};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// One step of a [`Path`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// A key of a table.
+    Key(String),
+    /// An element of a list.
+    Index(usize),
+    /// Every value of a table or list.
+    Wildcard,
+}
+
+/// A route from a value to the values nested within it.
+///
+/// The empty path selects the value itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path(pub Vec<Segment>);
+
+/// A value selected by a path, and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'v> {
+    pub location: Path,
+    pub value: Cow<'v, Value>,
+}
+
+impl Path {
+    /// Select every value this path leads to from `value`.
+    ///
+    /// The values of tables are visited in key order.
+    pub fn select<'v>(&self, value: &'v Value) -> Vec<Match<'v>> {
+        self.select_from(Cow::Borrowed(value))
+    }
+
+    fn select_from<'v>(&self, value: Cow<'v, Value>) -> Vec<Match<'v>> {
+        let mut matches = vec![Match {
+            location: Path::default(),
+            value,
+        }];
+        for segment in &self.0 {
+            matches = matches
+                .into_iter()
+                .flat_map(|parent| {
+                    let children = match parent.value {
+                        Cow::Borrowed(value) => children(value, segment),
+                        // defaults are scalars, so this is rarely more than a clone
+                        Cow::Owned(value) => children(&value, segment)
+                            .into_iter()
+                            .map(|(segment, child)| (segment, Cow::Owned(child.into_owned())))
+                            .collect(),
+                    };
+                    children.into_iter().map(move |(segment, value)| {
+                        let mut location = parent.location.clone();
+                        location.0.push(segment);
+                        Match { location, value }
+                    })
+                })
+                .collect();
+        }
+        matches
+    }
+}
+
+/// The values of `value` selected by a single segment, with the concrete
+/// segment leading to each.
+fn children<'v>(value: &'v Value, segment: &Segment) -> Vec<(Segment, Cow<'v, Value>)> {
+    match (segment, value) {
+        (Segment::Key(key), Value::Table(table)) => lookup(table, key)
+            .map(|child| (segment.clone(), child))
+            .into_iter()
+            .collect(),
+        (Segment::Key(key), Value::List(_)) => match key.parse() {
+            Ok(index) => children(value, &Segment::Index(index)),
+            Err(_) => Vec::new(),
+        },
+        (Segment::Index(index), Value::List(list)) => list
+            .get(*index)
+            .map(|child| (segment.clone(), Cow::Borrowed(child)))
+            .into_iter()
+            .collect(),
+        (Segment::Wildcard, Value::Table(table)) => {
+            let mut entries = table.iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            entries
+                .into_iter()
+                .map(|(key, child)| (Segment::Key(key.to_string()), Cow::Borrowed(child)))
+                .collect()
+        }
+        (Segment::Wildcard, Value::List(list)) => list
+            .iter()
+            .enumerate()
+            .map(|(index, child)| (Segment::Index(index), Cow::Borrowed(child)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Look up a key of a table, falling back as the game does for prototypes.
+fn lookup<'v>(table: &'v Table, key: &str) -> Option<Cow<'v, Value>> {
+    if let Some(value) = table.get(key) {
+        return Some(Cow::Borrowed(value));
+    }
+    if let Some(Value::Table(normal)) = table.get("normal") {
+        if let Some(value) = normal.get(key) {
+            return Some(Cow::Borrowed(value));
+        }
+    }
+    let Some(Value::String(kind)) = table.get("type") else {
+        return None;
+    };
+    prototype_default(kind, key).map(Cow::Owned)
+}
+
+/// The value the game assumes for a key a prototype leaves out.
+fn prototype_default(kind: &str, key: &str) -> Option<Value> {
+    let value = match (kind, key) {
+        ("recipe", "category") => Value::String("crafting".into()),
+        ("recipe", "energy_required") => Value::Float(0.5),
+        ("recipe", "enabled") => Value::Bool(true),
+        ("recipe", "hidden") => Value::Bool(false),
+        ("recipe", "result_count") => Value::Integer(1),
+        _ => return None,
+    };
+    Some(value)
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        let invalid = |reason| Error::Path {
+            path: path.into(),
+            reason,
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = path;
+        if rest.is_empty() || rest == "." {
+            return Ok(Self(segments));
+        }
+        loop {
+            if let Some(inner) = rest.strip_prefix('[') {
+                let (inner, after) = inner
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unclosed bracket"))?;
+                let segment = if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(key) = inner
+                    .strip_prefix('"')
+                    .and_then(|inner| inner.strip_suffix('"'))
+                {
+                    Segment::Key(key.into())
+                } else {
+                    let index = inner
+                        .parse()
+                        .map_err(|_| invalid("expected an index, `*` or a quoted key"))?;
+                    Segment::Index(index)
+                };
+                segments.push(segment);
+                rest = after;
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let key = &rest[..end];
+                if key.is_empty() {
+                    return Err(invalid("empty key"));
+                }
+                segments.push(if key == "*" {
+                    Segment::Wildcard
+                } else {
+                    Segment::Key(key.into())
+                });
+                rest = &rest[end..];
+            }
+
+            if rest.is_empty() {
+                return Ok(Self(segments));
+            }
+            if let Some(after) = rest.strip_prefix('.') {
+                rest = after;
+                if rest.is_empty() {
+                    return Err(invalid("trailing dot"));
+                }
+            } else if !rest.starts_with('[') {
+                return Err(invalid("expected `.` or `[` after `]`"));
+            }
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str(".");
+        }
+        for (index, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if key.contains(['.', '[', ']']) => write!(f, "[\"{key}\"]")?,
+                Segment::Key(key) if index == 0 => f.write_str(key)?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+                Segment::Wildcard if index == 0 => f.write_str("*")?,
+                Segment::Wildcard => f.write_str(".*")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a [`Filter`] compares a value with a literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// A string contains a substring, a list contains an element, or a table
+    /// contains a key.
+    Contains,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Contains => "contains",
+        })
+    }
+}
+
+/// A condition on a value.
+///
+/// A path may select several values, as `ingredients[*].name` does; a
+/// comparison holds if it holds for any of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// The path selects a value other than `nil` or `false`.
+    Truthy(Path),
+    Compare {
+        path: Path,
+        op: Op,
+        literal: Value,
+    },
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Truthy(path) => path
+                .select(value)
+                .iter()
+                .any(|selected| !matches!(*selected.value, Value::Nil | Value::Bool(false))),
+            Self::Compare { path, op, literal } => {
+                let selected = path.select(value);
+                match (op, literal) {
+                    // nothing at all is as good as nil
+                    (Op::Eq, Value::Nil) => selected.iter().all(|s| *s.value == Value::Nil),
+                    (Op::Ne, Value::Nil) => selected.iter().any(|s| *s.value != Value::Nil),
+                    _ => selected.iter().any(|s| compare(&s.value, *op, literal)),
+                }
+            }
+            Self::Not(filter) => !filter.matches(value),
+            Self::And(lhs, rhs) => lhs.matches(value) && rhs.matches(value),
+            Self::Or(lhs, rhs) => lhs.matches(value) || rhs.matches(value),
+        }
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(number) => Some(*number),
+        Value::Integer(number) => Some(*number as f64),
+        _ => None,
+    }
+}
+
+fn compare(value: &Value, op: Op, literal: &Value) -> bool {
+    if op == Op::Contains {
+        return match (value, literal) {
+            (Value::String(string), Value::String(substring)) => string.contains(&**substring),
+            (Value::List(list), _) => list.iter().any(|item| compare(item, Op::Eq, literal)),
+            (Value::Table(table), Value::String(key)) => table.contains_key(key),
+            _ => false,
+        };
+    }
+
+    let ordering = match (value, literal) {
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => match (number(a), number(b)) {
+            (Some(a), Some(b)) => match a.partial_cmp(&b) {
+                Some(ordering) => ordering,
+                None => return false,
+            },
+            // values of different types are never equal
+            _ => return op == Op::Ne,
+        },
+    };
+    match op {
+        Op::Eq => ordering == Ordering::Equal,
+        Op::Ne => ordering != Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+        Op::Contains => unreachable!(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Op(Op),
+    Literal(Value),
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => f.write_str("`(`"),
+            Self::Close => f.write_str("`)`"),
+            Self::Op(op) => write!(f, "`{op}`"),
+            Self::Literal(literal) => write!(f, "`{literal}`"),
+            Self::Word(word) => write!(f, "`{word}`"),
+        }
+    }
+}
+
+fn tokenize(filter: &str) -> Result<Vec<Token>, String> {
+    const SPECIAL: &str = "()\"'=!~<>";
+
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' => {
+                chars.next();
+                Token::Open
+            }
+            ')' => {
+                chars.next();
+                Token::Close
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => string.extend(chars.next()),
+                        Some(end) if end == c => break,
+                        Some(other) => string.push(other),
+                        None => return Err("unterminated string".into()),
+                    }
+                }
+                Token::Literal(Value::String(string.as_str().into()))
+            }
+            '=' | '!' | '~' | '<' | '>' => {
+                chars.next();
+                let equals = chars.next_if_eq(&'=').is_some();
+                let op = match (c, equals) {
+                    ('=', true) => Op::Eq,
+                    // `~=` is Lua's spelling
+                    ('!' | '~', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err(format!("unexpected `{c}`")),
+                };
+                Token::Op(op)
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !SPECIAL.contains(c)) {
+                    word.push(c);
+                }
+                let numeric =
+                    word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.');
+                match word.as_str() {
+                    "contains" => Token::Op(Op::Contains),
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "nil" => Token::Literal(Value::Nil),
+                    _ if numeric => match word.parse::<i64>() {
+                        Ok(number) => Token::Literal(Value::Integer(number)),
+                        Err(_) => Token::Literal(Value::Float(
+                            word.parse()
+                                .map_err(|_| format!("invalid number `{word}`"))?,
+                        )),
+                    },
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A recursive-descent parser over filter tokens.
+///
+/// ```text
+/// or         := and ("or" and)*
+/// and        := unary ("and" unary)*
+/// unary      := "not" unary | "(" or ")" | comparison
+/// comparison := PATH (OP LITERAL)?
+/// ```
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Word(word) if word == keyword))
+            .is_some()
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.unary()?;
+        while self.keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        if self.keyword("not") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        match self.tokens.next() {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err("expected `)`".into()),
+                }
+            }
+            Some(Token::Word(word)) if !matches!(word.as_str(), "and" | "or") => {
+                let path = word.parse::<Path>().map_err(|err| err.to_string())?;
+                let Some(Token::Op(op)) = self.tokens.next_if(|t| matches!(t, Token::Op(_))) else {
+                    return Ok(Filter::Truthy(path));
+                };
+                let literal = match self.tokens.next() {
+                    Some(Token::Literal(literal)) => literal,
+                    // an unquoted word is taken to be a string, as in `category == chemistry`
+                    Some(Token::Word(word)) => Value::String(word.as_str().into()),
+                    _ => return Err(format!("expected a value after `{op}`")),
+                };
+                Ok(Filter::Compare { path, op, literal })
+            }
+            Some(token) => Err(format!("unexpected {token}")),
+            None => Err("unexpected end of filter".into()),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(filter: &str) -> Result<Self> {
+        let invalid = |reason| Error::Filter {
+            filter: filter.into(),
+            reason,
+        };
+
+        let mut parser = Parser {
+            tokens: tokenize(filter).map_err(invalid)?.into_iter().peekable(),
+        };
+        let parsed = parser.or().map_err(invalid)?;
+        match parser.tokens.next() {
+            None => Ok(parsed),
+            Some(token) => Err(invalid(format!("unexpected {token}"))),
+        }
+    }
+}
+
+/// A named path, projected from each value a query selects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub path: Path,
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    /// Parse `NAME=PATH`, or a bare path which names itself.
+    fn from_str(field: &str) -> Result<Self> {
+        let (name, path) = field.split_once('=').unwrap_or((field, field));
+        if name.is_empty() {
+            return Err(Error::Field(field.into()));
+        }
+        Ok(Self {
+            name: name.into(),
+            path: path.parse()?,
+        })
+    }
+}
+
+/// A path selector, an optional filter and a projection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub path: Path,
+    pub filter: Option<Filter>,
+    /// The fields of each row. When empty, rows carry only their value.
+    pub fields: Vec<Field>,
+}
+
+/// A value selected by a query, and its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row<'v> {
+    pub location: Path,
+    pub value: Cow<'v, Value>,
+    /// The values selected by each of the query's fields, in order.
+    pub fields: Vec<Vec<Cow<'v, Value>>>,
+}
+
+impl Query {
+    pub fn run<'v>(&self, root: &'v Value) -> Vec<Row<'v>> {
+        self.path
+            .select(root)
+            .into_iter()
+            .filter(|selected| {
+                (self.filter.as_ref()).is_none_or(|filter| filter.matches(&selected.value))
+            })
+            .map(|selected| {
+                let fields = self
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        (field.path.select_from(selected.value.clone()).into_iter())
+                            .map(|field| field.value)
+                            .collect()
+                    })
+                    .collect();
+                Row {
+                    location: selected.location,
+                    value: selected.value,
+                    fields,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid path \"{path}\": {reason}")]
+    Path { path: String, reason: &'static str },
+    #[error("invalid filter \"{filter}\": {reason}")]
+    Filter { filter: String, reason: String },
+    #[error("invalid field \"{0}\": expected PATH or NAME=PATH")]
+    Field(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"{
+        recipe = {
+            ["copper-cable"] = {
+                type = "recipe", name = "copper-cable",
+                ingredients = {{"copper-plate", 1}}, result = "copper-cable", result_count = 2
+            },
+            ["plastic-bar"] = {
+                type = "recipe", name = "plastic-bar", category = "chemistry", energy_required = 1,
+                ingredients = {
+                    {type = "fluid", name = "petroleum-gas", amount = 20},
+                    {type = "item", name = "coal", amount = 1}
+                },
+                results = {{type = "item", name = "plastic-bar", amount = 2}}
+            },
+            ["rocket-part"] = {
+                type = "recipe", name = "rocket-part", category = "rocket-building",
+                energy_required = 3, hidden = true,
+                ingredients = {{"rocket-control-unit", 10}, {"low-density-structure", 10}},
+                result = "rocket-part"
+            },
+            ["iron-gear-wheel"] = {
+                type = "recipe", name = "iron-gear-wheel",
+                normal = {ingredients = {{"iron-plate", 2}}, result = "iron-gear-wheel"},
+                expensive = {ingredients = {{"iron-plate", 4}}, result = "iron-gear-wheel"}
+            }
+        }
+    }"#;
+
+    fn data() -> Value {
+        crate::parse_lua_value(DATA).unwrap()
+    }
+
+    fn names(query: &Query) -> Vec<String> {
+        query
+            .run(&data())
+            .iter()
+            .map(|row| row.location.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_paths() {
+        let path = "recipe.*.ingredients[*][0]".parse::<Path>().unwrap();
+        assert_eq!(
+            path.0,
+            [
+                Segment::Key("recipe".into()),
+                Segment::Wildcard,
+                Segment::Key("ingredients".into()),
+                Segment::Wildcard,
+                Segment::Index(0),
+            ]
+        );
+        assert_eq!(path.to_string(), "recipe.*.ingredients.*[0]");
+        assert_eq!(
+            r#"recipe["a.b"]"#.parse::<Path>().unwrap().to_string(),
+            r#"recipe["a.b"]"#
+        );
+        assert!("recipe.".parse::<Path>().is_err());
+        assert!("recipe[x]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn selects_with_defaults() {
+        let data = data();
+        let path = "recipe.*.energy_required".parse::<Path>().unwrap();
+        let times = path
+            .select(&data)
+            .into_iter()
+            .map(|selected| (selected.location.to_string(), selected.value.into_owned()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            times,
+            [
+                (
+                    "recipe.copper-cable.energy_required".into(),
+                    Value::Float(0.5)
+                ),
+                (
+                    "recipe.iron-gear-wheel.energy_required".into(),
+                    Value::Float(0.5)
+                ),
+                (
+                    "recipe.plastic-bar.energy_required".into(),
+                    Value::Integer(1)
+                ),
+                (
+                    "recipe.rocket-part.energy_required".into(),
+                    Value::Integer(3)
+                ),
+            ]
+        );
+
+        // the normal variant stands in for the recipe
+        let path = "recipe.iron-gear-wheel.ingredients[0][1]"
+            .parse::<Path>()
+            .unwrap();
+        assert_eq!(*path.select(&data)[0].value, Value::Integer(2));
+    }
+
+    #[test]
+    fn filters() {
+        let query = |filter: &str| Query {
+            path: "recipe.*".parse().unwrap(),
+            filter: Some(filter.parse().unwrap()),
+            fields: Vec::new(),
+        };
+
+        assert_eq!(
+            names(&query(r#"category == "chemistry""#)),
+            ["recipe.plastic-bar"]
+        );
+        assert_eq!(
+            names(&query("category == crafting and not hidden")),
+            ["recipe.copper-cable", "recipe.iron-gear-wheel"]
+        );
+        assert_eq!(
+            names(&query("energy_required > 0.5 and energy_required <= 3")),
+            ["recipe.plastic-bar", "recipe.rocket-part"]
+        );
+        assert_eq!(
+            names(&query(
+                "ingredients[*].type == fluid or (hidden and name contains rocket)"
+            )),
+            ["recipe.plastic-bar", "recipe.rocket-part"]
+        );
+        assert_eq!(
+            names(&query("results == nil and result_count ~= 1")),
+            ["recipe.copper-cable"]
+        );
+
+        assert!("category ==".parse::<Filter>().is_err());
+        assert!("(hidden".parse::<Filter>().is_err());
+        assert!(r#"name == "open"#.parse::<Filter>().is_err());
+        assert!("hidden hidden".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn projects_fields() {
+        let query = Query {
+            path: "recipe.*".parse().unwrap(),
+            filter: Some("category == chemistry".parse().unwrap()),
+            fields: vec![
+                "name".parse().unwrap(),
+                "inputs=ingredients[*].name".parse().unwrap(),
+            ],
+        };
+        let data = data();
+        let rows = query.run(&data);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(query.fields[1].name, "inputs");
+        assert_eq!(
+            rows[0].fields,
+            [
+                vec![Cow::Owned(Value::String("plastic-bar".into()))],
+                vec![
+                    Cow::Owned(Value::String("petroleum-gas".into())),
+                    Cow::Owned(Value::String("coal".into())),
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Inference of structural schemas from prototype data.
+//!
+//! A [`Shape`] accumulates what has been observed of a set of values: which
+//! types they take, the fields of tables and how often each appears, the
+//! lengths and elements of lists, the range of numbers, and the distinct
+//! values of strings while there are few of them. It can then be emitted as a
+//! [JSON Schema](https://json-schema.org/draft/2020-12/schema):
+//!
+//! - fields present in every table observed are `required`
+//! - strings become an `enum` when they take at most `max_enum` distinct
+//!   values, each seen at least twice on average
+//! - numbers carry their observed `minimum` and `maximum`
+//! - short lists of a fixed length whose positions differ in type, such as
+//!   `{"iron-plate", 2}`, become tuples with `prefixItems`
+//! - values observed with several types become an `anyOf`
+
+use std::collections::BTreeMap;
+
+use serde_json::json;
+
+use crate::generic_transform::Value;
+
+/// The longest list which may be inferred as a tuple.
+const MAX_TUPLE: usize = 4;
+
+/// The schema dialect emitted.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The observed range of some numbers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Numbers<T> {
+    pub count: usize,
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: Copy + PartialOrd> Numbers<T> {
+    fn observe(numbers: &mut Option<Self>, n: T) {
+        let numbers = numbers.get_or_insert(Numbers {
+            count: 0,
+            min: n,
+            max: n,
+        });
+        numbers.count += 1;
+        if n < numbers.min {
+            numbers.min = n;
+        }
+        if n > numbers.max {
+            numbers.max = n;
+        }
+    }
+}
+
+/// The observed values of some strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Strings {
+    pub count: usize,
+    /// How often each distinct value was seen, until there were too many of
+    /// them to track.
+    pub values: Option<BTreeMap<String, usize>>,
+}
+
+/// The observed fields of some tables.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tables {
+    pub count: usize,
+    pub fields: BTreeMap<String, Shape>,
+}
+
+/// The observed lengths and elements of some lists.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lists {
+    pub count: usize,
+    pub min_len: usize,
+    pub max_len: usize,
+    /// Every element of every list.
+    pub items: Box<Shape>,
+    /// The elements at each position, while every list is short enough to be a
+    /// tuple.
+    pub positions: Option<Vec<Shape>>,
+}
+
+/// What has been observed of a set of values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shape {
+    pub count: usize,
+    pub nil: usize,
+    pub booleans: usize,
+    pub integers: Option<Numbers<i64>>,
+    pub floats: Option<Numbers<f64>>,
+    pub strings: Option<Strings>,
+    pub tables: Option<Tables>,
+    pub lists: Option<Lists>,
+}
+
+impl Shape {
+    /// Infer the shape of `values`, tracking up to `max_enum` distinct values
+    /// of each string.
+    pub fn infer<'v>(values: impl IntoIterator<Item = &'v Value>, max_enum: usize) -> Self {
+        let mut shape = Self::default();
+        for value in values {
+            shape.observe(value, max_enum);
+        }
+        shape
+    }
+
+    /// Add `value` to the values observed.
+    pub fn observe(&mut self, value: &Value, max_enum: usize) {
+        self.count += 1;
+        match value {
+            Value::Nil => self.nil += 1,
+            Value::Bool(_) => self.booleans += 1,
+            Value::Integer(n) => Numbers::observe(&mut self.integers, *n),
+            Value::Float(n) => Numbers::observe(&mut self.floats, *n),
+            Value::String(s) => {
+                let strings = self.strings.get_or_insert_with(|| Strings {
+                    count: 0,
+                    values: Some(BTreeMap::new()),
+                });
+                strings.count += 1;
+                if let Some(values) = &mut strings.values {
+                    *values.entry(s.to_string()).or_default() += 1;
+                    if values.len() > max_enum {
+                        strings.values = None;
+                    }
+                }
+            }
+            Value::Table(table) => {
+                let tables = self.tables.get_or_insert_with(Tables::default);
+                tables.count += 1;
+                for (key, value) in table {
+                    tables
+                        .fields
+                        .entry(key.to_string())
+                        .or_default()
+                        .observe(value, max_enum);
+                }
+            }
+            Value::List(list) => {
+                let lists = self.lists.get_or_insert_with(|| Lists {
+                    min_len: list.len(),
+                    max_len: list.len(),
+                    positions: Some(Vec::new()),
+                    ..Lists::default()
+                });
+                lists.count += 1;
+                lists.min_len = lists.min_len.min(list.len());
+                lists.max_len = lists.max_len.max(list.len());
+                for item in list {
+                    lists.items.observe(item, max_enum);
+                }
+                if lists.max_len > MAX_TUPLE {
+                    lists.positions = None;
+                }
+                if let Some(positions) = &mut lists.positions {
+                    positions.resize_with(lists.max_len, Shape::default);
+                    for (position, item) in positions.iter_mut().zip(list) {
+                        position.observe(item, max_enum);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The JSON Schema types of the values observed.
+    pub fn types(&self) -> Vec<&'static str> {
+        let mut types = Vec::new();
+        if self.nil > 0 {
+            types.push("null");
+        }
+        if self.booleans > 0 {
+            types.push("boolean");
+        }
+        match (&self.integers, &self.floats) {
+            (Some(_), None) => types.push("integer"),
+            (_, Some(_)) => types.push("number"),
+            (None, None) => {}
+        }
+        if self.strings.is_some() {
+            types.push("string");
+        }
+        if self.tables.is_some() {
+            types.push("object");
+        }
+        if self.lists.is_some() {
+            types.push("array");
+        }
+        types
+    }
+
+    /// Emit the shape as a JSON Schema.
+    pub fn to_schema(&self) -> serde_json::Value {
+        let mut alternatives = Vec::new();
+        if self.nil > 0 {
+            alternatives.push(json!({"type": "null"}));
+        }
+        if self.booleans > 0 {
+            alternatives.push(json!({"type": "boolean"}));
+        }
+        if let Some(schema) = self.numbers_schema() {
+            alternatives.push(schema);
+        }
+        if let Some(strings) = &self.strings {
+            let mut schema = json!({"type": "string"});
+            if let Some(values) = &strings.values {
+                if values.len() * 2 <= strings.count {
+                    schema["enum"] = values.keys().cloned().collect();
+                }
+            }
+            alternatives.push(schema);
+        }
+        if let Some(tables) = &self.tables {
+            let properties = tables
+                .fields
+                .iter()
+                .map(|(key, shape)| (key.clone(), shape.to_schema()))
+                .collect::<serde_json::Map<_, _>>();
+            let required = tables
+                .fields
+                .iter()
+                .filter(|(_, shape)| shape.count == tables.count)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            let mut schema = json!({"type": "object", "properties": properties});
+            if !required.is_empty() {
+                schema["required"] = required.into();
+            }
+            alternatives.push(schema);
+        }
+        if let Some(lists) = &self.lists {
+            alternatives.push(lists.to_schema());
+        }
+
+        match alternatives.len() {
+            0 => json!({}),
+            1 => alternatives.pop().unwrap(),
+            _ => json!({"anyOf": alternatives}),
+        }
+    }
+
+    fn numbers_schema(&self) -> Option<serde_json::Value> {
+        let (kind, min, max) = match (&self.integers, &self.floats) {
+            (None, None) => return None,
+            (Some(integers), None) => ("integer", integers.min.into(), integers.max.into()),
+            (integers, Some(floats)) => {
+                let (mut min, mut max) = (floats.min, floats.max);
+                if let Some(integers) = integers {
+                    min = min.min(integers.min as f64);
+                    max = max.max(integers.max as f64);
+                }
+                ("number", json!(min), json!(max))
+            }
+        };
+        let mut schema = json!({"type": kind});
+        // non-finite floats have no JSON representation
+        if !min.is_null() {
+            schema["minimum"] = min;
+        }
+        if !max.is_null() {
+            schema["maximum"] = max;
+        }
+        Some(schema)
+    }
+}
+
+impl Lists {
+    fn to_schema(&self) -> serde_json::Value {
+        let mut schema = json!({"type": "array"});
+
+        // a tuple only if its positions hold different types of value
+        let tuple = self.positions.as_ref().filter(|positions| {
+            self.min_len == self.max_len
+                && positions
+                    .windows(2)
+                    .any(|pair| pair[0].types() != pair[1].types())
+        });
+        match tuple {
+            Some(positions) => {
+                let positions = positions.iter().map(Shape::to_schema).collect::<Vec<_>>();
+                schema["prefixItems"] = positions.into();
+                schema["items"] = false.into();
+            }
+            None => {
+                if self.items.count > 0 {
+                    schema["items"] = self.items.to_schema();
+                }
+                if self.min_len > 0 {
+                    schema["minItems"] = self.min_len.into();
+                }
+            }
+        }
+        schema
+    }
+}
+
+/// Infer the shape of the prototypes of each type in `dump`.
+pub fn infer(dump: &Value, max_enum: usize) -> BTreeMap<String, Shape> {
+    let Value::Table(types) = dump else {
+        return BTreeMap::new();
+    };
+    types
+        .iter()
+        .filter_map(|(kind, prototypes)| {
+            let Value::Table(prototypes) = prototypes else {
+                return None;
+            };
+            Some((
+                kind.to_string(),
+                Shape::infer(prototypes.values(), max_enum),
+            ))
+        })
+        .collect()
+}
+
+/// A standalone JSON Schema for the prototypes of type `kind`.
+pub fn prototype_schema(kind: &str, shape: &Shape) -> serde_json::Value {
+    let mut schema = json!({"$schema": DIALECT, "title": kind});
+    if let serde_json::Value::Object(inferred) = shape.to_schema() {
+        schema.as_object_mut().unwrap().extend(inferred);
+    }
+    schema
+}
+
+/// A JSON Schema for a whole dump, with the prototypes of each type in
+/// `shapes`.
+pub fn dump_schema(shapes: &BTreeMap<String, Shape>) -> serde_json::Value {
+    let properties = shapes
+        .iter()
+        .map(|(kind, shape)| {
+            let schema = json!({
+                "type": "object",
+                "additionalProperties": shape.to_schema(),
+            });
+            (kind.clone(), schema)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    json!({"$schema": DIALECT, "type": "object", "properties": properties})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"{
+        recipe = {
+            ["copper-cable"] = {
+                type = "recipe", name = "copper-cable",
+                ingredients = {{"copper-plate", 1}}, result = "copper-cable", result_count = 2
+            },
+            ["iron-gear-wheel"] = {
+                type = "recipe", name = "iron-gear-wheel",
+                ingredients = {{"iron-plate", 2}}, result = "iron-gear-wheel"
+            },
+            ["plastic-bar"] = {
+                type = "recipe", name = "plastic-bar", category = "chemistry", energy_required = 1,
+                ingredients = {
+                    {type = "fluid", name = "petroleum-gas", amount = 20},
+                    {type = "item", name = "coal", amount = 1}
+                },
+                results = {{type = "item", name = "plastic-bar", amount = 2}}
+            },
+            ["uranium-processing"] = {
+                type = "recipe", name = "uranium-processing", energy_required = 12.5,
+                ingredients = {{"uranium-ore", 10}},
+                results = {{name = "uranium-235", amount = 1, probability = 0.007}}
+            }
+        }
+    }"#;
+
+    #[test]
+    fn infers_shapes() {
+        let dump = crate::parse_lua_value(DATA).unwrap();
+        let shapes = infer(&dump, 4);
+        let recipe = &shapes["recipe"];
+        let fields = &recipe.tables.as_ref().unwrap().fields;
+
+        assert_eq!(recipe.count, 4);
+        assert_eq!(fields["name"].count, 4);
+        assert_eq!(fields["category"].count, 1);
+        assert_eq!(fields["energy_required"].types(), ["number"]);
+        assert_eq!(fields["result_count"].types(), ["integer"]);
+
+        let ingredients = fields["ingredients"].lists.as_ref().unwrap();
+        assert_eq!(ingredients.items.types(), ["object", "array"]);
+        assert_eq!((ingredients.min_len, ingredients.max_len), (1, 2));
+    }
+
+    #[test]
+    fn emits_json_schema() {
+        let dump = crate::parse_lua_value(DATA).unwrap();
+        let schema = prototype_schema("recipe", &infer(&dump, 4)["recipe"]);
+
+        assert_eq!(schema["title"], "recipe");
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"], json!(["ingredients", "name", "type"]));
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["type"],
+            json!({"type": "string", "enum": ["recipe"]})
+        );
+        // each name is seen once, so names are not an enumeration
+        assert_eq!(properties["name"], json!({"type": "string"}));
+        assert_eq!(
+            properties["energy_required"],
+            json!({"type": "number", "minimum": 1.0, "maximum": 12.5})
+        );
+
+        // simple ingredients are tuples; typed ingredients are tables
+        let ingredient = &properties["ingredients"]["items"]["anyOf"];
+        assert_eq!(ingredient[0]["properties"]["type"]["type"], "string");
+        assert_eq!(
+            ingredient[1],
+            json!({
+                "type": "array",
+                "prefixItems": [{"type": "string"}, {"type": "integer", "minimum": 1, "maximum": 10}],
+                "items": false,
+            })
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Validation of the typed models against a data dump.
+//!
+//! Each prototype of a modeled type is parsed with its model and reserialized,
+//! and the result compared with the original value. A [`Difference`] is
+//! located by a JSON pointer such as `/results/0/amount`, so that mismatches
+//! can be found in the dump directly.
+//!
+//! Numbers are compared by value, so `1` and `1.0` are equal, and a field
+//! which only restates the model's default, such as `energy_required = 0.5`,
+//! may be left out of the reserialized value. Other fields missing from it are
+//! expected of incomplete models, and are only errors for models which claim
+//! to be complete.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::models;
+
+/// A prototype type, and the model it is parsed with.
+#[derive(Clone, Copy)]
+pub struct Model {
+    /// The prototype type, such as `assembling-machine`.
+    pub kind: &'static str,
+    /// Whether the model captures every field of the prototype.
+    pub complete: bool,
+    roundtrip: fn(&Value) -> Result<Value, serde_json::Error>,
+}
+
+impl fmt::Debug for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Model")
+            .field("kind", &self.kind)
+            .field("complete", &self.complete)
+            .finish()
+    }
+}
+
+fn roundtrip<T: DeserializeOwned + Serialize>(value: &Value) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(T::deserialize(value)?)
+}
+
+const fn model<T: DeserializeOwned + Serialize>(kind: &'static str, complete: bool) -> Model {
+    Model {
+        kind,
+        complete,
+        roundtrip: roundtrip::<T>,
+    }
+}
+
+/// Every prototype type with a model.
+pub const MODELS: &[Model] = &[
+    model::<models::CraftingMachine>("assembling-machine", false),
+    model::<models::Beacon>("beacon", false),
+    model::<models::CraftingMachine>("furnace", false),
+    model::<models::Generator>("generator", false),
+    model::<models::Inserter>("inserter", false),
+    model::<models::Item>("item", false),
+    model::<models::Module>("module", false),
+    model::<models::Recipe>("recipe", false),
+    model::<models::CraftingMachine>("rocket-silo", false),
+    model::<models::SolarPanel>("solar-panel", false),
+    model::<models::Technology>("technology", false),
+    model::<models::TransportBelt>("transport-belt", false),
+];
+
+/// A value which did not survive reserialization unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Difference {
+    /// A JSON pointer to the value within its prototype.
+    pub pointer: String,
+    /// The value in the dump, if any.
+    pub original: Option<Value>,
+    /// The reserialized value, if any.
+    pub reserialized: Option<Value>,
+}
+
+impl Difference {
+    /// `true` if the model dropped the value.
+    pub fn is_missing(&self) -> bool {
+        self.reserialized.is_none()
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.original, &self.reserialized) {
+            (Some(original), None) => write!(f, "{}: missing {original}", self.pointer),
+            (None, Some(reserialized)) => write!(f, "{}: added {reserialized}", self.pointer),
+            (Some(original), Some(reserialized)) => {
+                write!(f, "{}: {original} → {reserialized}", self.pointer)
+            }
+            (None, None) => write!(f, "{}", self.pointer),
+        }
+    }
+}
+
+/// Escape a key for use in a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Collect the differences between `original` and `reserialized`.
+pub fn differences(original: &Value, reserialized: &Value) -> Vec<Difference> {
+    let mut found = Vec::new();
+    collect(
+        String::new(),
+        Some(original),
+        Some(reserialized),
+        &mut found,
+    );
+    found
+}
+
+fn collect(
+    pointer: String,
+    original: Option<&Value>,
+    reserialized: Option<&Value>,
+    found: &mut Vec<Difference>,
+) {
+    match (original, reserialized) {
+        (Some(Value::Object(original)), Some(Value::Object(reserialized))) => {
+            let mut keys = original.keys().collect::<Vec<_>>();
+            keys.extend(reserialized.keys().filter(|k| !original.contains_key(*k)));
+            keys.sort();
+            for key in keys {
+                collect(
+                    format!("{pointer}/{}", escape(key)),
+                    original.get(key),
+                    reserialized.get(key),
+                    found,
+                );
+            }
+        }
+        (Some(Value::Array(original)), Some(Value::Array(reserialized))) => {
+            for index in 0..original.len().max(reserialized.len()) {
+                collect(
+                    format!("{pointer}/{index}"),
+                    original.get(index),
+                    reserialized.get(index),
+                    found,
+                );
+            }
+        }
+        (Some(Value::Number(a)), Some(Value::Number(b))) if a.as_f64() == b.as_f64() => {}
+        (original, reserialized) if original == reserialized => {}
+        (original, reserialized) => found.push(Difference {
+            pointer,
+            original: original.cloned(),
+            reserialized: reserialized.cloned(),
+        }),
+    }
+}
+
+/// `true` if `difference` is a field which the model reads, but drops because
+/// it restates the default.
+///
+/// Such a field can be left out of `prototype` without changing the
+/// reserialized value, but not replaced with nonsense.
+fn is_default(
+    model: &Model,
+    prototype: &Value,
+    reserialized: &Value,
+    difference: &Difference,
+) -> bool {
+    /// Reserialize `prototype` with the field at `parent`/`key` replaced, or
+    /// removed if `replacement` is `None`.
+    fn with(
+        model: &Model,
+        prototype: &Value,
+        parent: &str,
+        key: &str,
+        replacement: Option<Value>,
+    ) -> Option<Value> {
+        let mut prototype = prototype.clone();
+        let Some(Value::Object(parent)) = prototype.pointer_mut(parent) else {
+            return None;
+        };
+        let key = key.replace("~1", "/").replace("~0", "~");
+        match replacement {
+            Some(replacement) => parent.insert(key, replacement),
+            None => parent.remove(&key),
+        };
+        (model.roundtrip)(&prototype).ok()
+    }
+
+    if !difference.is_missing() {
+        return false;
+    }
+    let Some((parent, key)) = difference.pointer.rsplit_once('/') else {
+        return false;
+    };
+    let unchanged = |value: Option<Value>| {
+        value.is_some_and(|value| differences(reserialized, &value).is_empty())
+    };
+    let nonsense = serde_json::json!({"": null});
+    unchanged(with(model, prototype, parent, key, None))
+        && !unchanged(with(model, prototype, parent, key, Some(nonsense)))
+}
+
+/// The result of validating one prototype.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "outcome", content = "details")]
+pub enum Outcome {
+    /// The prototype reserialized unchanged.
+    Passed,
+    /// The prototype reserialized unchanged, except for fields an incomplete
+    /// model does not capture.
+    MissingFields(Vec<Difference>),
+    /// The prototype reserialized differently.
+    Mismatched(Vec<Difference>),
+    /// The prototype could not be parsed.
+    Failed(String),
+}
+
+impl Outcome {
+    /// Validate one prototype against its model.
+    pub fn new(model: &Model, prototype: &Value) -> Self {
+        let reserialized = match (model.roundtrip)(prototype) {
+            Ok(reserialized) => reserialized,
+            Err(err) => return Self::Failed(err.to_string()),
+        };
+        let differences = differences(prototype, &reserialized)
+            .into_iter()
+            .filter(|difference| !is_default(model, prototype, &reserialized, difference))
+            .collect::<Vec<_>>();
+        if differences.is_empty() {
+            Self::Passed
+        } else if !model.complete && differences.iter().all(Difference::is_missing) {
+            Self::MissingFields(differences)
+        } else {
+            Self::Mismatched(differences)
+        }
+    }
+
+    /// `true` if the prototype failed to parse or reserialized differently.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Mismatched(_) | Self::Failed(_))
+    }
+}
+
+/// Counts of each [`Outcome`] for one prototype type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub passed: usize,
+    pub missing_fields: usize,
+    pub mismatched: usize,
+    pub failed: usize,
+}
+
+/// The outcome of validating every prototype of one type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub kind: &'static str,
+    pub complete: bool,
+    /// Outcomes by prototype name.
+    pub prototypes: BTreeMap<String, Outcome>,
+}
+
+impl Report {
+    /// Validate every prototype of `model`'s type in `dump`.
+    ///
+    /// A dump without the type produces an empty report.
+    pub fn new(model: &Model, dump: &Value) -> Self {
+        let prototypes = dump
+            .get(model.kind)
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, prototype)| (name.clone(), Outcome::new(model, prototype)))
+            .collect();
+        Self {
+            kind: model.kind,
+            complete: model.complete,
+            prototypes,
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary {
+            total: self.prototypes.len(),
+            ..Summary::default()
+        };
+        for outcome in self.prototypes.values() {
+            match outcome {
+                Outcome::Passed => summary.passed += 1,
+                Outcome::MissingFields(_) => summary.missing_fields += 1,
+                Outcome::Mismatched(_) => summary.mismatched += 1,
+                Outcome::Failed(_) => summary.failed += 1,
+            }
+        }
+        summary
+    }
+
+    /// `true` if any prototype failed to parse or reserialized differently.
+    pub fn has_errors(&self) -> bool {
+        self.prototypes.values().any(Outcome::is_error)
+    }
+}
+
+/// Validate every modeled prototype type in `dump`.
+pub fn validate(dump: &Value) -> Vec<Report> {
+    MODELS
+        .iter()
+        .map(|model| Report::new(model, dump))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn model(kind: &str) -> &'static Model {
+        MODELS.iter().find(|model| model.kind == kind).unwrap()
+    }
+
+    #[test]
+    fn finds_differences_by_pointer() {
+        let original = json!({"a/b": [1, {"c": 2}], "d": 3, "energy_required": 1});
+        let reserialized = json!({"a/b": [1, {"c": 4}], "e": 5, "energy_required": 1.0});
+
+        let found = differences(&original, &reserialized)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(found, ["/a~1b/1/c: 2 → 4", "/d: missing 3", "/e: added 5"]);
+    }
+
+    #[test]
+    fn validates_prototypes() {
+        let dump = json!({
+            "recipe": {
+                "copper-cable": {
+                    "type": "recipe", "name": "copper-cable", "energy_required": 0.5,
+                    "ingredients": [["copper-plate", 1]],
+                    "result": "copper-cable", "result_count": 2
+                },
+                "iron-gear-wheel": {
+                    "type": "recipe", "name": "iron-gear-wheel",
+                    "ingredients": [["iron-plate", 2]], "result": "iron-gear-wheel",
+                    "icon": "__base__/graphics/icons/iron-gear-wheel.png"
+                },
+                "plastic-bar": {
+                    "type": "recipe", "name": "plastic-bar", "category": "chemistry",
+                    "energy_required": 1, "enabled": false,
+                    "ingredients": [
+                        {"type": "fluid", "name": "petroleum-gas", "amount": 20},
+                        {"type": "item", "name": "coal", "amount": 1}
+                    ],
+                    "results": [{"type": "item", "name": "plastic-bar", "amount": 2}],
+                    "crafting_machine_tint": {
+                        "primary": {"r": 1, "g": 1, "b": 1, "a": 1},
+                        "secondary": {"r": 0.771, "g": 0.771, "b": 0.771, "a": 1}
+                    }
+                },
+                "broken": {"type": "recipe", "name": "broken"}
+            },
+            "item": {
+                "coal": {
+                    "type": "item", "name": "coal", "stack_size": 50,
+                    "icon": "__base__/graphics/icons/coal.png"
+                }
+            }
+        });
+
+        let recipes = Report::new(model("recipe"), &dump);
+        assert_eq!(recipes.prototypes["copper-cable"], Outcome::Passed);
+        // an icon without its size and mipmaps is dropped
+        let Outcome::MissingFields(differences) = &recipes.prototypes["iron-gear-wheel"] else {
+            panic!("expected missing fields");
+        };
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].pointer, "/icon");
+        // the tint only colors the machine
+        let Outcome::MissingFields(differences) = &recipes.prototypes["plastic-bar"] else {
+            panic!("expected missing fields");
+        };
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].pointer, "/crafting_machine_tint");
+        assert!(matches!(recipes.prototypes["broken"], Outcome::Failed(_)));
+        assert!(recipes.has_errors());
+
+        // a complete model may not drop anything
+        let complete = Model {
+            complete: true,
+            ..*model("recipe")
+        };
+        assert!(matches!(
+            Report::new(&complete, &dump).prototypes["iron-gear-wheel"],
+            Outcome::Mismatched(_)
+        ));
+
+        let items = Report::new(model("item"), &dump);
+        assert!(matches!(
+            items.prototypes["coal"],
+            Outcome::MissingFields(_)
+        ));
+        assert!(!items.has_errors());
+
+        let summaries = validate(&dump)
+            .iter()
+            .map(|report| (report.kind, report.summary()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(summaries["furnace"], Summary::default());
+        assert_eq!(summaries["recipe"].total, 4);
+        assert_eq!(summaries["recipe"].passed, 1);
+        assert_eq!(summaries["recipe"].missing_fields, 2);
+        assert_eq!(summaries["recipe"].failed, 1);
+    }
+}
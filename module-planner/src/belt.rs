@@ -0,0 +1,239 @@
+//! Belt-saturation planning.
+//!
+//! Rather than a rate in items per second, a target may be expressed as a
+//! number of belts, or belt lanes, of a given transport belt tier: "what do I
+//! need to fill one red belt with gears?" A [`BeltPlan`] answers that, and
+//! reports how many belts of each raw input the plan needs and which
+//! intermediates overflow a single belt.
+
+use std::collections::BTreeMap;
+
+use data_parser::models::TransportBelt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    number::Number,
+    plan::{PlanRequest, ProductionPlan},
+    recipe::ItemKind,
+    Error, Result,
+};
+
+/// Items carried per second by each lane of a belt moving one tile per tick.
+///
+/// A belt lane holds 4 items per tile, and a second has 60 ticks.
+const LANE_ITEMS_PER_TILE_TICK: u32 = 4 * 60;
+
+/// A transport belt tier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Belt {
+    pub name: String,
+    /// Belt speed in tiles per tick.
+    pub speed: f64,
+}
+
+impl Belt {
+    pub fn new(belt: &TransportBelt) -> Self {
+        Self {
+            name: belt.name.clone(),
+            speed: belt.speed,
+        }
+    }
+
+    /// The throughput of one lane, in items per second.
+    pub fn lane_rate<N: Number>(&self) -> N {
+        N::from_f64(self.speed) * N::from_u32(LANE_ITEMS_PER_TILE_TICK)
+    }
+
+    /// The throughput of both lanes, in items per second.
+    pub fn rate<N: Number>(&self) -> N {
+        self.lane_rate::<N>() * N::from_u32(2)
+    }
+}
+
+/// A quantity of fully saturated belts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Saturation<N = f64> {
+    Belts(N),
+    Lanes(N),
+}
+
+impl<N: Number> Saturation<N> {
+    /// The rate in items per second which saturates this many belts or lanes.
+    pub fn rate(&self, belt: &Belt) -> N {
+        match self {
+            Self::Belts(belts) => belts.clone() * belt.rate(),
+            Self::Lanes(lanes) => lanes.clone() * belt.lane_rate(),
+        }
+    }
+}
+
+/// A plan whose targets fill a number of belts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeltPlan<N = f64> {
+    pub belt: String,
+    /// The throughput of one full belt, in items per second.
+    pub belt_rate: N,
+    pub plan: ProductionPlan<N>,
+    /// The number of belts needed to supply each solid raw input.
+    pub input_belts: BTreeMap<String, N>,
+    /// Solid items which flow through the plan faster than one belt can
+    /// carry, with the number of belts their flow requires.
+    pub overflowing: BTreeMap<String, N>,
+}
+
+impl<N: Number> BeltPlan<N> {
+    /// Plan production to fill the given belts of each target item.
+    ///
+    /// Targets already present in `request` are replaced by those computed
+    /// from `targets`.
+    pub fn new(
+        database: &PrototypeDatabase,
+        request: &PlanRequest<N>,
+        belt: &str,
+        targets: &BTreeMap<String, Saturation<N>>,
+    ) -> Result<Self> {
+        let belt = database
+            .belt(belt)
+            .ok_or_else(|| Error::UnknownBelt(belt.into()))?;
+
+        let request = PlanRequest {
+            targets: targets
+                .iter()
+                .map(|(item, saturation)| (item.clone(), saturation.rate(belt)))
+                .collect(),
+            ..request.clone()
+        };
+        let plan = ProductionPlan::new(database, &request)?;
+
+        let belt_rate = belt.rate::<N>();
+        let input_belts = input_belts(&plan, belt);
+        let overflowing = plan
+            .flows
+            .iter()
+            .filter(|(item, flow)| {
+                flow.kind == ItemKind::Item && !plan.raw_inputs.contains_key(*item)
+            })
+            .filter_map(|(item, flow)| {
+                let rate = if flow.produced > flow.consumed {
+                    flow.produced.clone()
+                } else {
+                    flow.consumed.clone()
+                };
+                let belts = rate / belt_rate.clone();
+                (belts.clone() - N::one())
+                    .is_positive()
+                    .then(|| (item.clone(), belts))
+            })
+            .collect();
+
+        Ok(Self {
+            belt: belt.name.clone(),
+            belt_rate,
+            plan,
+            input_belts,
+            overflowing,
+        })
+    }
+}
+
+/// The number of `belt`s needed to supply each solid raw input of `plan`.
+///
+/// Inputs without a flow are assumed to be solid.
+pub(crate) fn input_belts<N: Number>(plan: &ProductionPlan<N>, belt: &Belt) -> BTreeMap<String, N> {
+    let belt_rate = belt.rate::<N>();
+    let is_solid = |item: &str| {
+        plan.flows
+            .get(item)
+            .is_none_or(|flow| flow.kind == ItemKind::Item)
+    };
+    plan.raw_inputs
+        .iter()
+        .filter(|(item, _)| is_solid(item))
+        .map(|(item, rate)| (item.clone(), rate.clone() / belt_rate.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        number::Rational,
+        plan::tests::{assert_close, database, request},
+    };
+
+    fn database_with_belts() -> PrototypeDatabase {
+        let mut database = database();
+        for (name, speed) in [
+            ("transport-belt", 0.03125),
+            ("fast-transport-belt", 0.0625),
+            ("express-transport-belt", 0.09375),
+        ] {
+            database.belts.insert(
+                name.into(),
+                Belt {
+                    name: name.into(),
+                    speed,
+                },
+            );
+        }
+        database
+    }
+
+    #[test]
+    fn belt_rates() {
+        let database = database_with_belts();
+        let rate = |name: &str| database.belt(name).unwrap().rate::<Rational>();
+        assert_eq!(rate("transport-belt"), Rational::new(15, 1));
+        assert_eq!(rate("fast-transport-belt"), Rational::new(30, 1));
+        assert_eq!(rate("express-transport-belt"), Rational::new(45, 1));
+    }
+
+    #[test]
+    fn fill_one_belt() {
+        let plan = BeltPlan::new(
+            &database_with_belts(),
+            &request(&[], &[]),
+            "express-transport-belt",
+            &BTreeMap::from([("electronic-circuit".into(), Saturation::Belts(1.0))]),
+        )
+        .unwrap();
+
+        assert_close(plan.belt_rate, 45.0);
+        assert_close(plan.plan.targets["electronic-circuit"], 45.0);
+        assert_close(plan.plan.steps["electronic-circuit"].machines, 30.0);
+        assert_close(plan.input_belts["iron-plate"], 1.0);
+        assert_close(plan.input_belts["copper-plate"], 1.5);
+        // 135 cables per second, between the cable and circuit assemblers
+        assert_close(plan.overflowing["copper-cable"], 3.0);
+        assert!(!plan.overflowing.contains_key("electronic-circuit"));
+    }
+
+    #[test]
+    fn fill_one_lane() {
+        let plan = BeltPlan::new(
+            &database_with_belts(),
+            &request(&[], &[]),
+            "transport-belt",
+            &BTreeMap::from([("iron-gear-wheel".into(), Saturation::Lanes(1.0))]),
+        )
+        .unwrap();
+
+        assert_close(plan.plan.targets["iron-gear-wheel"], 7.5);
+        assert_close(plan.input_belts["iron-plate"], 1.0);
+        assert!(plan.overflowing.is_empty());
+    }
+
+    #[test]
+    fn unknown_belt() {
+        let err = BeltPlan::<f64>::new(
+            &database_with_belts(),
+            &request(&[], &[]),
+            "hyper-belt",
+            &BTreeMap::new(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::UnknownBelt(_)));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::BTreeSet, io::Write, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    cost::{CostOptions, CostTable},
+    database::PrototypeDatabase,
+    number::{Number, Rational},
+    policy::RecipePolicy,
+    recipe::Difficulty,
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+/// Compute the raw-resource cost and crafting time of one unit of every item.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Only report the cost of this item
+    ///
+    /// May be specified several times. Reports every item if omitted.
+    #[arg(long = "item", value_name = "ITEM")]
+    items: Vec<String>,
+
+    /// Produce an item with the given recipe, as `ITEM=RECIPE`
+    ///
+    /// May be specified several times.
+    #[arg(long = "recipe", value_name = "ITEM=RECIPE", value_parser = parse_choice)]
+    recipes: Vec<(String, String)>,
+
+    /// Treat this item as raw: do not expand the recipes which produce it
+    ///
+    /// May be specified several times.
+    #[arg(long = "raw", value_name = "ITEM")]
+    raw: Vec<String>,
+
+    /// Never use this recipe
+    ///
+    /// May be specified several times.
+    #[arg(long = "exclude", value_name = "RECIPE")]
+    excluded_recipes: Vec<String>,
+
+    /// Where an item has several recipes, use only those enabled at the start
+    /// of the game, if there are any
+    #[arg(long)]
+    prefer_enabled: bool,
+
+    /// Report exact rational costs rather than floating-point ones
+    #[arg(long)]
+    exact: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Use expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn parse_choice(value: &str) -> Result<(String, String)> {
+    let Some((item, recipe)) = value.split_once('=') else {
+        bail!("expected ITEM=RECIPE");
+    };
+    Ok((item.into(), recipe.into()))
+}
+
+fn render<N: Number + Serialize>(
+    mut table: CostTable<N>,
+    items: &BTreeSet<String>,
+    format: Format,
+) -> Result<Vec<u8>> {
+    if !items.is_empty() {
+        if let Some(missing) = items.iter().find(|item| !table.items.contains_key(*item)) {
+            bail!("no cost for \"{missing}\"");
+        }
+        table.items.retain(|item, _| items.contains(item));
+        table.unresolved.clear();
+    }
+
+    match format {
+        Format::Json => {
+            let mut json = serde_json::to_vec_pretty(&table).context("serializing costs")?;
+            json.push(b'\n');
+            Ok(json)
+        }
+        Format::Csv => {
+            let resources = table.resources();
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let header = ["item", "recipe", "crafting_time", "total"]
+                .into_iter()
+                .chain(resources.iter().copied());
+            writer.write_record(header)?;
+            for (item, cost) in &table.items {
+                let mut record = vec![
+                    item.clone(),
+                    cost.recipe.clone().unwrap_or_default(),
+                    cost.crafting_time.to_string(),
+                    cost.total().to_string(),
+                ];
+                record.extend(resources.iter().map(|&resource| {
+                    cost.raw
+                        .get(resource)
+                        .cloned()
+                        .unwrap_or_default()
+                        .to_string()
+                }));
+                writer.write_record(record)?;
+            }
+            writer.into_inner().context("writing CSV")
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let difficulty = if args.expensive {
+        Difficulty::Expensive
+    } else {
+        Difficulty::Normal
+    };
+    let database = PrototypeDatabase::load(&args.data, difficulty).context("loading data")?;
+
+    let options = CostOptions {
+        policy: RecipePolicy {
+            recipes: args.recipes.into_iter().collect(),
+            excluded_recipes: args.excluded_recipes.into_iter().collect(),
+            prefer_enabled: args.prefer_enabled,
+            ..Default::default()
+        },
+        raw: args.raw.into_iter().collect(),
+    };
+    let items = args.items.into_iter().collect();
+    let rendered = if args.exact {
+        render(
+            CostTable::<Rational>::new(&database, &options)?,
+            &items,
+            args.format,
+        )?
+    } else {
+        render(
+            CostTable::<f64>::new(&database, &options)?,
+            &items,
+            args.format,
+        )?
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(&rendered)
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
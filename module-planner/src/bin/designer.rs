@@ -0,0 +1,559 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use module_planner::{
+    database::PrototypeDatabase,
+    design::{fuzzy_search, Design, TreeNode},
+    plan_file::PlanFile,
+    recipe::Difficulty,
+    units::{compact, format_energy},
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Clear, List, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+/// Interactively design a factory module in the terminal.
+///
+/// Pick target items, choose recipes, machines, modules and beacons, and see
+/// the production tree, machine counts, belts and power update as you go.
+/// The design can be saved as a plan file for the `plan` script.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`. May be omitted when `--plan` names
+    /// one.
+    data: Option<PathBuf>,
+
+    /// Plan file to start from
+    #[arg(short, long)]
+    plan: Option<PathBuf>,
+
+    /// Where to save the design
+    ///
+    /// The format is chosen by extension. Defaults to the file given with
+    /// `--plan`, or `module.toml`. The data dump path is written relative to
+    /// the saved file, so it can be loaded again from anywhere.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Use expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+}
+
+const HELP: &str = "a add target · e edit rate · d delete · r recipe · m machine · \
+    o module · b beacon · x clear modules · t belt · s save · tab switch · q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Targets,
+    Tree,
+}
+
+/// What a choice from a list applies to.
+#[derive(Debug, Clone)]
+enum Action {
+    /// Produce the item with the chosen recipe.
+    Recipe(String),
+    /// Craft the recipe's category in the chosen machine.
+    Machine(String),
+    /// Add the chosen module to the recipe's machines.
+    Module(String),
+    /// Add a beacon filled with the chosen module to the recipe's machines.
+    Beacon(String),
+}
+
+#[derive(Debug, Clone)]
+enum Popup {
+    /// Fuzzy search for a new target item.
+    Search { query: String, selected: usize },
+    /// Entry of a target rate, in items per second.
+    Rate { item: String, input: String },
+    Choose {
+        title: String,
+        options: Vec<String>,
+        selected: usize,
+        action: Action,
+    },
+}
+
+struct App<'a> {
+    design: Design<'a>,
+    output: PathBuf,
+    focus: Focus,
+    targets: ListState,
+    tree: ListState,
+    popup: Option<Popup>,
+    message: Option<String>,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(design: Design<'a>, output: PathBuf) -> Self {
+        Self {
+            design,
+            output,
+            focus: Focus::Targets,
+            targets: ListState::default().with_selected(Some(0)),
+            tree: ListState::default().with_selected(Some(0)),
+            popup: None,
+            message: None,
+            quit: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.message = None;
+                    match self.popup.take() {
+                        Some(popup) => self.popup_key(popup, key),
+                        None => self.key(key),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn selected_target(&self) -> Option<(String, f64)> {
+        let index = self.targets.selected()?;
+        let (item, rate) = self.design.file.request.targets.iter().nth(index)?;
+        Some((item.clone(), *rate))
+    }
+
+    fn selected_node(&self) -> Option<&TreeNode> {
+        let summary = self.design.summary().ok()?;
+        summary.tree.get(self.tree.selected()?)
+    }
+
+    fn selected_recipe(&self) -> Option<String> {
+        self.selected_node()?.recipe.clone()
+    }
+
+    fn choose(&mut self, title: String, options: Vec<&str>, action: Action) {
+        if options.is_empty() {
+            self.message = Some(format!("nothing to choose for {title}"));
+            return;
+        }
+        self.popup = Some(Popup::Choose {
+            title,
+            options: options.into_iter().map(Into::into).collect(),
+            selected: 0,
+            action,
+        });
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Targets => Focus::Tree,
+                    Focus::Tree => Focus::Targets,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => match self.focus {
+                Focus::Targets => self.targets.select_previous(),
+                Focus::Tree => self.tree.select_previous(),
+            },
+            KeyCode::Down | KeyCode::Char('j') => match self.focus {
+                Focus::Targets => self.targets.select_next(),
+                Focus::Tree => self.tree.select_next(),
+            },
+            KeyCode::Char('a') => {
+                self.popup = Some(Popup::Search {
+                    query: String::new(),
+                    selected: 0,
+                })
+            }
+            KeyCode::Char('e') => {
+                if let Some((item, rate)) = self.selected_target() {
+                    self.popup = Some(Popup::Rate {
+                        item,
+                        input: compact(rate, 6),
+                    });
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some((item, _)) = self.selected_target() {
+                    self.design.remove_target(&item);
+                }
+            }
+            KeyCode::Char('r') => {
+                let item = match self.design.pending_choice() {
+                    Some((item, _)) => Some(item.to_owned()),
+                    None => self.selected_node().map(|node| node.item.clone()),
+                };
+                if let Some(item) = item {
+                    let options = self.design.recipes_for(&item);
+                    self.choose(format!("recipe for {item}"), options, Action::Recipe(item));
+                }
+            }
+            KeyCode::Char('m') => {
+                if let Some(recipe) = self.selected_recipe() {
+                    let options = self.design.machines_for(&recipe);
+                    self.choose(
+                        format!("machine for {recipe}"),
+                        options,
+                        Action::Machine(recipe),
+                    );
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(recipe) = self.selected_recipe() {
+                    let options = self.design.modules_for(&recipe);
+                    self.choose(
+                        format!("module for {recipe}"),
+                        options,
+                        Action::Module(recipe),
+                    );
+                }
+            }
+            KeyCode::Char('b') => {
+                if let Some(recipe) = self.selected_recipe() {
+                    let options = self.design.modules_for(&recipe);
+                    self.choose(
+                        format!("beacon modules for {recipe}"),
+                        options,
+                        Action::Beacon(recipe),
+                    );
+                }
+            }
+            KeyCode::Char('x') => {
+                if let Some(recipe) = self.selected_recipe() {
+                    self.design.clear_modules(&recipe);
+                }
+            }
+            KeyCode::Char('t') => self.design.cycle_belt(),
+            KeyCode::Char('s') => {
+                self.message = Some(match self.design.file.save(&self.output) {
+                    Ok(()) => format!("saved {}", self.output.display()),
+                    Err(err) => format!("error: {err}"),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn popup_key(&mut self, popup: Popup, key: KeyEvent) {
+        if key.code == KeyCode::Esc {
+            return;
+        }
+        self.popup = match popup {
+            Popup::Search {
+                mut query,
+                mut selected,
+            } => {
+                let matches = fuzzy_search(&query, self.design.items());
+                match key.code {
+                    KeyCode::Enter => {
+                        return if let Some(item) = matches.get(selected) {
+                            self.popup = Some(Popup::Rate {
+                                item: (*item).into(),
+                                input: "1".into(),
+                            });
+                        };
+                    }
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+                    KeyCode::Backspace => {
+                        query.pop();
+                        selected = 0;
+                    }
+                    KeyCode::Char(c) => {
+                        query.push(c);
+                        selected = 0;
+                    }
+                    _ => {}
+                }
+                Some(Popup::Search { query, selected })
+            }
+            Popup::Rate { item, mut input } => {
+                match key.code {
+                    KeyCode::Enter => {
+                        match input.parse::<f64>() {
+                            Ok(rate) if rate > 0.0 => self.design.set_target(&item, rate),
+                            _ => self.message = Some(format!("invalid rate \"{input}\"")),
+                        }
+                        return;
+                    }
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => input.push(c),
+                    _ => {}
+                }
+                Some(Popup::Rate { item, input })
+            }
+            Popup::Choose {
+                title,
+                options,
+                mut selected,
+                action,
+            } => {
+                match key.code {
+                    KeyCode::Enter => {
+                        let choice = &options[selected];
+                        match &action {
+                            Action::Recipe(item) => self.design.choose_recipe(item, choice),
+                            Action::Machine(recipe) => self.design.choose_machine(recipe, choice),
+                            Action::Module(recipe) => self.design.add_module(recipe, choice),
+                            Action::Beacon(recipe) => self.design.add_beacon(recipe, choice),
+                        }
+                        return;
+                    }
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(options.len() - 1),
+                    _ => {}
+                }
+                Some(Popup::Choose {
+                    title,
+                    options,
+                    selected,
+                    action,
+                })
+            }
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let [targets, tree, summary] = Layout::horizontal([
+            Constraint::Percentage(25),
+            Constraint::Percentage(45),
+            Constraint::Percentage(30),
+        ])
+        .areas(main);
+
+        let block = |title: &'static str, focused: bool| {
+            let block = Block::bordered().title(title);
+            if focused {
+                block.border_style(Style::new().fg(Color::Yellow))
+            } else {
+                block
+            }
+        };
+
+        let items = self
+            .design
+            .file
+            .request
+            .targets
+            .iter()
+            .map(|(item, rate)| format!("{item}  {}/s", compact(*rate, 3)));
+        let list = List::new(items)
+            .block(block("Targets", self.focus == Focus::Targets))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, targets, &mut self.targets);
+
+        let nodes = match self.design.summary() {
+            Ok(summary) => summary.tree.iter().map(tree_line).collect(),
+            Err(_) => Vec::new(),
+        };
+        let list = List::new(nodes)
+            .block(block("Production tree", self.focus == Focus::Tree))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, tree, &mut self.tree);
+
+        let paragraph = Paragraph::new(self.summary_text())
+            .block(Block::bordered().title("Summary"))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, summary);
+
+        let status_line = match (&self.message, self.design.summary()) {
+            (Some(message), _) => Line::from(message.as_str()),
+            (None, Err(err)) => {
+                let hint = if self.design.pending_choice().is_some() {
+                    " (press r to choose)"
+                } else {
+                    ""
+                };
+                Line::from(format!("{err}{hint}")).fg(Color::Red)
+            }
+            (None, Ok(_)) => Line::from(HELP).dim(),
+        };
+        let paragraph = Paragraph::new(status_line)
+            .block(Block::bordered())
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, status);
+
+        if let Some(popup) = &self.popup {
+            self.draw_popup(frame, popup);
+        }
+    }
+
+    fn summary_text(&self) -> Text<'static> {
+        let Ok(summary) = self.design.summary() else {
+            return Text::default();
+        };
+        let mut lines = vec![Line::from("Machines").bold()];
+        for (recipe, step) in &summary.plan.steps {
+            lines.push(Line::from(format!(
+                "  {} × {}  {recipe}",
+                step.machines.ceil(),
+                step.machine
+            )));
+        }
+
+        let belt = self.design.belt.as_deref().unwrap_or("no belt");
+        lines.push(Line::default());
+        lines.push(Line::from(format!("Raw inputs ({belt})")).bold());
+        for (item, rate) in &summary.plan.raw_inputs {
+            let belts = summary
+                .input_belts
+                .get(item)
+                .map(|belts| format!("  {} belts", compact(*belts, 2)))
+                .unwrap_or_default();
+            lines.push(Line::from(format!(
+                "  {item}  {}/s{belts}",
+                compact(*rate, 3)
+            )));
+        }
+
+        let power = &summary.power;
+        lines.push(Line::default());
+        lines.push(Line::from("Power").bold());
+        lines.push(Line::from(format!(
+            "  electric  {}",
+            format_energy(power.electric, 'W')
+        )));
+        for (category, fuel) in &power.fuel {
+            lines.push(Line::from(format!(
+                "  {category} fuel  {}",
+                format_energy(fuel.power, 'W')
+            )));
+        }
+        for (generator, count) in &power.generators {
+            lines.push(Line::from(format!(
+                "  {} × {generator}",
+                compact(*count, 1)
+            )));
+        }
+        lines.push(Line::from(format!(
+            "  pollution  {}/min",
+            compact(power.pollution_per_minute, 1)
+        )));
+        Text::from(lines)
+    }
+
+    fn draw_popup(&self, frame: &mut Frame, popup: &Popup) {
+        let area = centered(frame.area(), 50, 60);
+        frame.render_widget(Clear, area);
+        match popup {
+            Popup::Search { query, selected } => {
+                let [input, results] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+                frame.render_widget(
+                    Paragraph::new(query.as_str()).block(Block::bordered().title("Add target")),
+                    input,
+                );
+                let matches = fuzzy_search(query, self.design.items());
+                let list = List::new(matches)
+                    .block(Block::bordered())
+                    .highlight_style(Style::new().reversed());
+                let mut state = ListState::default().with_selected(Some(*selected));
+                frame.render_stateful_widget(list, results, &mut state);
+            }
+            Popup::Rate { item, input } => {
+                let [area, _] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+                frame.render_widget(
+                    Paragraph::new(format!("{input}/s"))
+                        .block(Block::bordered().title(format!("Rate of {item}"))),
+                    area,
+                );
+            }
+            Popup::Choose {
+                title,
+                options,
+                selected,
+                ..
+            } => {
+                let list = List::new(options.iter().map(String::as_str))
+                    .block(Block::bordered().title(title.as_str()))
+                    .highlight_style(Style::new().reversed());
+                let mut state = ListState::default().with_selected(Some(*selected));
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+        }
+    }
+}
+
+fn tree_line(node: &TreeNode) -> String {
+    let indent = "  ".repeat(node.depth);
+    let rate = compact(node.rate, 3);
+    match &node.recipe {
+        Some(recipe) => format!(
+            "{indent}{}  {rate}/s  ← {recipe} × {}",
+            node.item,
+            compact(node.machines, 2)
+        ),
+        None => format!("{indent}{}  {rate}/s  (raw)", node.item),
+    }
+}
+
+/// A rectangle of the given percentage size, centered in `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage((100 - height) / 2),
+        Constraint::Percentage(height),
+        Constraint::Min(0),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage((100 - width) / 2),
+        Constraint::Percentage(width),
+        Constraint::Min(0),
+    ])
+    .areas(area);
+    area
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut file = match &args.plan {
+        Some(path) => PlanFile::load(path).context("loading plan file")?,
+        None => {
+            let Some(data) = &args.data else {
+                bail!("either a data dump or --plan is required");
+            };
+            PlanFile {
+                data: data.clone(),
+                difficulty: Difficulty::Normal,
+                solver: None,
+                request: Default::default(),
+            }
+        }
+    };
+    if let Some(data) = &args.data {
+        file.data = data.clone();
+    }
+    if args.expensive {
+        file.difficulty = Difficulty::Expensive;
+    }
+    let database = PrototypeDatabase::load(&file.data, file.difficulty).context("loading data")?;
+
+    let output = args
+        .output
+        .or(args.plan)
+        .unwrap_or_else(|| "module.toml".into());
+    let mut app = App::new(Design::new(&database, file), output);
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
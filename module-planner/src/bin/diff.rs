@@ -0,0 +1,154 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    database::PrototypeDatabase,
+    diff::{DumpDiff, PlanImpact},
+    plan_file::{PlanFile, PlanFormat},
+    recipe::Difficulty,
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Report what changed between two data dumps, such as before and after a mod
+/// update, and which saved plans the changes affect.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the old data dump
+    ///
+    /// Each dump may be a raw Lua dump, a single JSON file, or a directory
+    /// produced by `into-json --split-toplevel`.
+    old: PathBuf,
+
+    /// Path to the new data dump
+    new: PathBuf,
+
+    /// Compare expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+
+    /// Plan files, or directories of plan files, to check for changes
+    #[arg(short, long = "plans", num_args = 1..)]
+    plans: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    #[serde(flatten)]
+    diff: DumpDiff,
+    plans: BTreeMap<PathBuf, PlanImpact>,
+}
+
+/// Expand directories into the plan files directly within them.
+fn plan_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            found.push(path.clone());
+            continue;
+        }
+        let entries = std::fs::read_dir(path)
+            .with_context(|| format!("reading directory {}", path.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if PlanFormat::from_path(&path).is_some() {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn load(path: &Path, difficulty: Difficulty) -> Result<(serde_json::Value, PrototypeDatabase)> {
+    let context = || format!("loading {}", path.display());
+    let dump = data_parser::load(path).with_context(context)?;
+    let database = PrototypeDatabase::from_value(&dump, difficulty).with_context(context)?;
+    Ok((dump, database))
+}
+
+fn render_text(report: &Report) -> String {
+    let mut rendered = report.diff.to_string();
+    if report.diff.is_empty() {
+        rendered.push_str("no changes\n");
+    }
+    if report.plans.is_empty() {
+        return rendered;
+    }
+
+    rendered.push_str("\nplans\n");
+    for (path, impact) in &report.plans {
+        let mut uses = impact.recipes.clone();
+        uses.extend(impact.entities.iter().cloned());
+        let summary = match (&impact.error, uses.is_empty()) {
+            (Some(error), _) => format!("does not plan: {error}"),
+            (None, true) => "unaffected".into(),
+            (None, false) => format!("uses changed {}", uses.join(", ")),
+        };
+        writeln!(rendered, "  {}: {summary}", path.display()).unwrap();
+    }
+    rendered
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let difficulty = if args.expensive {
+        Difficulty::Expensive
+    } else {
+        Difficulty::Normal
+    };
+    let (old_dump, old) = load(&args.old, difficulty)?;
+    let (new_dump, new) = load(&args.new, difficulty)?;
+    let diff = DumpDiff::new(&old_dump, &new_dump, difficulty);
+
+    let mut plans = BTreeMap::new();
+    for path in plan_paths(&args.plans)? {
+        let file = PlanFile::load(&path)
+            .with_context(|| format!("loading plan file {}", path.display()))?;
+        plans.insert(path, diff.impact(&file, &old, &new));
+    }
+    let report = Report { diff, plans };
+
+    let rendered = match args.format {
+        Format::Text => render_text(&report),
+        Format::Json => {
+            let mut rendered =
+                serde_json::to_string_pretty(&report).context("serializing report")?;
+            rendered.push('\n');
+            rendered
+        }
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
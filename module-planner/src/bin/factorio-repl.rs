@@ -0,0 +1,163 @@
+use std::{path::PathBuf, time::Instant};
+
+use anyhow::{bail, Context as _, Result};
+use clap::Parser;
+use module_planner::{
+    database::PrototypeDatabase,
+    recipe::Difficulty,
+    repl::{complete, Command, Session},
+};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+
+/// Query a data dump interactively.
+///
+/// The dump is loaded once, then commands such as `recipe copper-cable` or
+/// `rate electronic-circuit 30/s` are answered as they are typed. Type `help`
+/// for a list of commands.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Use expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+
+    /// Run these commands and exit, rather than reading commands interactively.
+    /// Exits with an error if any command fails
+    #[arg(short, long = "command")]
+    commands: Vec<String>,
+
+    /// File in which to keep command history
+    ///
+    /// Defaults to `.factorio-repl-history` in the home directory.
+    #[arg(long)]
+    history: Option<PathBuf>,
+}
+
+/// Completes command and prototype names.
+struct ReplHelper<'a> {
+    database: &'a PrototypeDatabase,
+}
+
+impl Completer for ReplHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let (start, names) = complete(self.database, line, pos);
+        Ok((start, names.into_iter().map(Into::into).collect()))
+    }
+}
+
+impl Hinter for ReplHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper<'_> {}
+
+impl Validator for ReplHelper<'_> {}
+
+impl Helper for ReplHelper<'_> {}
+
+/// What became of one line of input.
+#[derive(PartialEq)]
+enum Outcome {
+    Succeeded,
+    Failed,
+    Quit,
+}
+
+/// Run one line of input, printing its output or error.
+fn run(session: &mut Session, line: &str) -> Outcome {
+    let result = line.parse::<Command>().and_then(|command| match command {
+        Command::Quit => Ok(None),
+        command => session.execute(&command).map(Some),
+    });
+    match result {
+        Ok(Some(output)) => {
+            println!("{output}");
+            Outcome::Succeeded
+        }
+        Ok(None) => Outcome::Quit,
+        Err(err) => {
+            eprintln!("error: {err}");
+            Outcome::Failed
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let difficulty = if args.expensive {
+        Difficulty::Expensive
+    } else {
+        Difficulty::Normal
+    };
+    let start = Instant::now();
+    let database = PrototypeDatabase::load(&args.data, difficulty).context("loading data")?;
+    let mut session = Session::new(&database);
+
+    if !args.commands.is_empty() {
+        let mut failed = 0;
+        for line in &args.commands {
+            match run(&mut session, line) {
+                Outcome::Succeeded => {}
+                Outcome::Failed => failed += 1,
+                Outcome::Quit => break,
+            }
+        }
+        if failed > 0 {
+            bail!("{failed} of {} commands failed", args.commands.len());
+        }
+        return Ok(());
+    }
+
+    eprintln!(
+        "loaded {} recipes in {:.1} s; type \"help\" for a list of commands",
+        database.recipes.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    let history = args.history.or_else(|| {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".factorio-repl-history"))
+    });
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper {
+        database: &database,
+    }));
+    if let Some(history) = &history {
+        // there is no history the first time round
+        let _ = editor.load_history(history);
+    }
+
+    loop {
+        match editor.readline("> ") {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                editor.add_history_entry(line.as_str())?;
+                if run(&mut session, &line) == Outcome::Quit {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(err) => return Err(err).context("reading input"),
+        }
+    }
+
+    if let Some(history) = &history {
+        editor.save_history(history).context("saving history")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::{collections::BTreeSet, io::Write, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    chain::{ChainOptions, ProductionChain},
+    database::PrototypeDatabase,
+    policy::RecipePolicy,
+    recipe::Difficulty,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Dot,
+    Mermaid,
+}
+
+/// Emit the production chain of an item as a Graphviz DOT or Mermaid graph.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Item or fluid whose production chain to emit
+    item: String,
+
+    /// Maximum number of recipe steps to expand from the target item
+    #[arg(short, long)]
+    depth: Option<usize>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Dot)]
+    format: Format,
+
+    /// Omit recipes which fill or empty fluid barrels
+    #[arg(long)]
+    collapse_barreling: bool,
+
+    /// Omit recipes marked `hidden`
+    #[arg(long)]
+    hide_hidden: bool,
+
+    /// Treat this item as raw: do not expand the recipes which produce it
+    ///
+    /// May be specified several times.
+    #[arg(long = "raw", value_name = "ITEM")]
+    raw: Vec<String>,
+
+    /// Include only this recipe for an item, as `ITEM=RECIPE`
+    ///
+    /// May be specified several times.
+    #[arg(long = "recipe", value_name = "ITEM=RECIPE", value_parser = parse_choice)]
+    recipes: Vec<(String, String)>,
+
+    /// Omit this recipe
+    ///
+    /// May be specified several times.
+    #[arg(long = "exclude", value_name = "RECIPE")]
+    excluded_recipes: Vec<String>,
+
+    /// Where an item has several recipes, include only those enabled at the
+    /// start of the game, if there are any
+    #[arg(long)]
+    prefer_enabled: bool,
+
+    /// Where an item has several recipes, include only the one with the least
+    /// raw-resource cost
+    #[arg(long)]
+    prefer_cheapest: bool,
+
+    /// Use expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn parse_choice(value: &str) -> Result<(String, String)> {
+    let Some((item, recipe)) = value.split_once('=') else {
+        bail!("expected ITEM=RECIPE");
+    };
+    Ok((item.into(), recipe.into()))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let difficulty = if args.expensive {
+        Difficulty::Expensive
+    } else {
+        Difficulty::Normal
+    };
+    let database = PrototypeDatabase::load(&args.data, difficulty).context("loading data")?;
+
+    let policy = RecipePolicy {
+        recipes: args.recipes.into_iter().collect(),
+        excluded_recipes: args.excluded_recipes.into_iter().collect(),
+        prefer_enabled: args.prefer_enabled,
+        prefer_cheapest: args.prefer_cheapest,
+        ..RecipePolicy::permissive()
+    };
+    let options = ChainOptions {
+        depth: args.depth,
+        collapse_barreling: args.collapse_barreling,
+        hide_hidden: args.hide_hidden,
+        raw: args.raw.into_iter().collect::<BTreeSet<_>>(),
+        // barreling and hidden recipes are omitted by the options above
+        policy: (policy != RecipePolicy::permissive()).then_some(policy),
+    };
+    let chain = ProductionChain::new(&database, &args.item, &options)?;
+    if chain.is_empty() {
+        if options.depth == Some(0) {
+            bail!("--depth 0 expands no recipes; use a depth of at least 1");
+        }
+        if options.raw.contains(&args.item) {
+            bail!(
+                "\"{}\" is listed in --raw, so none of its recipes are expanded",
+                args.item
+            );
+        }
+        if database.producers(&args.item).next().is_some() {
+            bail!(
+                "every recipe producing \"{}\" is omitted by the options",
+                args.item
+            );
+        }
+        bail!("no recipe produces \"{}\"", args.item);
+    }
+
+    let rendered = match args.format {
+        Format::Dot => chain.to_dot(),
+        Format::Mermaid => chain.to_mermaid(),
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
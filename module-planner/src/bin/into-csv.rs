@@ -0,0 +1,165 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    database::PrototypeDatabase,
+    export::{machine_table, plan_tables, recipe_table, spreadsheet, Table},
+    plan_file::PlanFile,
+    recipe::Difficulty,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TableKind {
+    /// Every ingredient and product of every recipe
+    Recipes,
+    /// Every crafting machine
+    Machines,
+    /// The recipe steps of the plan
+    Steps,
+    /// The items consumed and produced by each step of the plan
+    StepItems,
+    /// The flow of every item through the plan
+    Items,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    /// A SpreadsheetML workbook, with a worksheet per table, which Excel and
+    /// LibreOffice open
+    Spreadsheet,
+}
+
+/// Export recipes, machines and production plans as tables for spreadsheets.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(default_value = "-")]
+    output: String,
+
+    /// Tables to export
+    ///
+    /// Defaults to the plan's tables with `--plan`, and to the recipe and
+    /// machine tables otherwise. A single CSV file holds one table, so it
+    /// defaults to the `steps` table with `--plan`, and to the `recipes` table
+    /// otherwise.
+    #[arg(short = 't', long = "table", value_enum)]
+    tables: Vec<TableKind>,
+
+    /// Plan file whose production plan to export, against the data dump
+    #[arg(long)]
+    plan: Option<PathBuf>,
+
+    /// Use expensive-mode recipes, unless the plan file chooses
+    #[arg(long)]
+    expensive: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+
+    /// Split CSV output by table
+    ///
+    /// This is only meaningful when the output is a particular path, not
+    /// stdout. The output path becomes a directory, into which each table is
+    /// written as a file such as `recipes.csv`.
+    #[arg(short = 'S', long)]
+    split_toplevel: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let plan_file = args
+        .plan
+        .as_ref()
+        .map(|path| {
+            let mut file = PlanFile::<f64>::load(path).context("loading plan file")?;
+            file.data = args.data.clone();
+            anyhow::Ok(file)
+        })
+        .transpose()?;
+    let database = match &plan_file {
+        Some(file) => file.database(),
+        None if args.expensive => PrototypeDatabase::load(&args.data, Difficulty::Expensive),
+        None => PrototypeDatabase::load(&args.data, Difficulty::Normal),
+    }
+    .context("loading data")?;
+
+    let single_csv =
+        matches!(args.format, Format::Csv) && !(args.split_toplevel && args.output != "-");
+    let mut kinds = args.tables.clone();
+    if kinds.is_empty() {
+        kinds = match (&plan_file, single_csv) {
+            (Some(_), true) => vec![TableKind::Steps],
+            (Some(_), false) => vec![TableKind::Steps, TableKind::StepItems, TableKind::Items],
+            (None, true) => vec![TableKind::Recipes],
+            (None, false) => vec![TableKind::Recipes, TableKind::Machines],
+        };
+    }
+
+    let plan = match &plan_file {
+        Some(file) => Some(plan_tables(&file.plan(&database)?)),
+        None => None,
+    };
+    let mut tables = Vec::<Table>::new();
+    for kind in kinds {
+        let table = match (kind, &plan) {
+            (TableKind::Recipes, _) => recipe_table(&database),
+            (TableKind::Machines, _) => machine_table(&database),
+            (_, None) => {
+                let name = kind.to_possible_value().unwrap();
+                bail!(
+                    "the {} table needs a plan; pass one with --plan",
+                    name.get_name()
+                )
+            }
+            (TableKind::Steps, Some([steps, _, _])) => steps.clone(),
+            (TableKind::StepItems, Some([_, step_items, _])) => step_items.clone(),
+            (TableKind::Items, Some([_, _, items])) => items.clone(),
+        };
+        tables.push(table);
+    }
+
+    if let Format::Spreadsheet = args.format {
+        return write(&args.output, spreadsheet(&tables).as_bytes());
+    }
+
+    if args.split_toplevel && args.output != "-" {
+        let dir = PathBuf::from(&args.output);
+        std::fs::create_dir_all(&dir).context("creating output directory")?;
+        for table in &tables {
+            let path = dir.join(format!("{}.csv", table.name));
+            let csv = table.to_csv().context("writing csv")?;
+            std::fs::write(path, csv)
+                .with_context(|| format!("writing table \"{}\"", table.name))?;
+        }
+        return Ok(());
+    }
+
+    let [table] = tables.as_slice() else {
+        bail!("CSV holds one table; choose one with --table, or use --split-toplevel or --format spreadsheet");
+    };
+    write(&args.output, &table.to_csv().context("writing csv")?)
+}
+
+fn write(output: &str, rendered: &[u8]) -> Result<()> {
+    if output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered)
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(output, rendered).context("writing output file")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use std::{fmt::Write as _, io::Write, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    database::PrototypeDatabase,
+    inserter::HandSize,
+    modules::{BeaconLoadout, ModuleLoadout},
+    number::{Number, Rational},
+    recipe::Difficulty,
+    report::MachineReport,
+    units::{compact, format_energy},
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Report what a single machine does when crafting a recipe nonstop.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the data dump
+    ///
+    /// This may be a raw Lua dump, a single JSON file, or a directory produced
+    /// by `into-json --split-toplevel`.
+    data: PathBuf,
+
+    /// Recipe to craft
+    recipe: String,
+
+    /// Machine crafting the recipe
+    ///
+    /// May be omitted if exactly one machine can craft the recipe's category.
+    #[arg(short, long)]
+    machine: Option<String>,
+
+    /// Module in the machine
+    ///
+    /// May be specified once per module slot.
+    #[arg(long = "module", value_name = "MODULE")]
+    modules: Vec<String>,
+
+    /// Beacons affecting the machine, as `COUNT:BEACON[:MODULE,...]`
+    ///
+    /// For example, `8:beacon:speed-module,speed-module`. May be specified
+    /// several times.
+    #[arg(long = "beacon", value_name = "BEACONS", value_parser = parse_beacons)]
+    beacons: Vec<BeaconLoadout>,
+
+    /// Items per swing of inserters, after research
+    #[arg(
+        long,
+        default_value_t = HandSize::default().inserter,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    hand_size: u32,
+
+    /// Items per swing of stack inserters, after research
+    #[arg(
+        long,
+        default_value_t = HandSize::default().stack_inserter,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    stack_hand_size: u32,
+
+    /// Report exact rational rates rather than floating-point ones
+    #[arg(long)]
+    exact: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Use expensive-mode recipes
+    #[arg(long)]
+    expensive: bool,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn parse_beacons(value: &str) -> Result<BeaconLoadout> {
+    let mut parts = value.splitn(3, ':');
+    let (Some(count), Some(beacon)) = (parts.next(), parts.next()) else {
+        bail!("expected COUNT:BEACON[:MODULE,...]");
+    };
+    Ok(BeaconLoadout {
+        beacon: beacon.into(),
+        count: count.parse().context("invalid beacon count")?,
+        modules: parts
+            .next()
+            .map(|modules| modules.split(',').map(str::to_owned).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn render<N: Number + Serialize>(
+    report: &MachineReport<N>,
+    format: Format,
+    exact: bool,
+) -> Result<String> {
+    if let Format::Json = format {
+        let mut json = serde_json::to_string_pretty(report).context("serializing report")?;
+        json.push('\n');
+        return Ok(json);
+    }
+
+    // exact rates display as mixed numbers, inexact ones are rounded
+    let number = |value: &N| {
+        if exact {
+            value.to_string()
+        } else {
+            compact(value.to_f64(), 3)
+        }
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{} in {}", report.recipe, report.machine)?;
+    writeln!(out, "  {} crafts/s", number(&report.crafts_per_second))?;
+    writeln!(out, "products:")?;
+    for (item, rate) in &report.products {
+        write!(out, "  {item}: {}/s", number(&rate.per_second))?;
+        if rate.probability < 1.0 {
+            write!(
+                out,
+                " ({}% per craft)",
+                compact(rate.probability * 100.0, 3)
+            )?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "ingredients:")?;
+    for (item, rate) in &report.ingredients {
+        writeln!(out, "  {item}: {}/s", number(rate))?;
+    }
+    write!(
+        out,
+        "power: {} ({} drain)",
+        format_energy(report.power.to_f64(), 'W'),
+        format_energy(report.drain.to_f64(), 'W'),
+    )?;
+    if report.beacon_power.is_positive() {
+        write!(
+            out,
+            ", beacons {}",
+            format_energy(report.beacon_power.to_f64(), 'W')
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "pollution: {}/min",
+        number(&report.pollution_per_minute)
+    )?;
+    if !report.inserters.is_empty() {
+        writeln!(out, "inserters (input / output):")?;
+        for (inserter, count) in &report.inserters {
+            writeln!(out, "  {inserter}: {} / {}", count.input, count.output)?;
+        }
+    }
+    Ok(out)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let difficulty = if args.expensive {
+        Difficulty::Expensive
+    } else {
+        Difficulty::Normal
+    };
+    let database = PrototypeDatabase::load(&args.data, difficulty).context("loading data")?;
+
+    let loadout = ModuleLoadout {
+        modules: args.modules,
+        beacons: args.beacons,
+    };
+    let hand_size = HandSize {
+        inserter: args.hand_size,
+        stack_inserter: args.stack_hand_size,
+    };
+    let machine = args.machine.as_deref();
+    let rendered = if args.exact {
+        let report =
+            MachineReport::<Rational>::new(&database, &args.recipe, machine, &loadout, hand_size)?;
+        render(&report, args.format, args.exact)?
+    } else {
+        let report =
+            MachineReport::<f64>::new(&database, &args.recipe, machine, &loadout, hand_size)?;
+        render(&report, args.format, args.exact)?
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use module_planner::{
+    number::{Number, Rational},
+    plan_file::PlanFile,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// Plan a factory module described by a plan file.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the plan file
+    ///
+    /// The format is chosen by extension: `.toml`, `.yaml`, `.yml` or
+    /// `.json`.
+    file: PathBuf,
+
+    /// Path to the data dump, overriding the one named by the plan file
+    #[arg(long)]
+    data: Option<PathBuf>,
+
+    /// Only check the plan file against the data dump
+    #[arg(long)]
+    check: bool,
+
+    /// Report exact rational rates rather than floating-point ones
+    #[arg(long)]
+    exact: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Output path
+    ///
+    /// When `-`, writes to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+}
+
+fn run<N: Number + Default + Serialize + DeserializeOwned>(args: &Args) -> Result<Vec<u8>> {
+    let mut file = PlanFile::<N>::load(&args.file).context("loading plan file")?;
+    if let Some(data) = &args.data {
+        file.data = data.clone();
+    }
+    let database = file.database().context("loading data")?;
+
+    if args.check {
+        file.validate(&database)?;
+        return Ok(Vec::new());
+    }
+
+    let plan = file.plan(&database)?;
+    let mut rendered = match args.format {
+        Format::Json => serde_json::to_string_pretty(&plan).context("serializing plan")?,
+        Format::Yaml => serde_yaml::to_string(&plan).context("serializing plan")?,
+        Format::Toml => toml::to_string(&plan).context("serializing plan")?,
+    };
+    if !rendered.ends_with('\n') {
+        rendered.push('\n');
+    }
+    Ok(rendered.into_bytes())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let rendered = if args.exact {
+        run::<Rational>(&args)?
+    } else {
+        run::<f64>(&args)?
+    };
+
+    if args.output == "-" {
+        std::io::stdout()
+            .lock()
+            .write_all(&rendered)
+            .context("writing to stdout")?;
+    } else {
+        std::fs::write(&args.output, rendered).context("writing output file")?;
+    }
+
+    Ok(())
+}
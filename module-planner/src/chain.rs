@@ -0,0 +1,511 @@
+//! Production chain extraction and export.
+//!
+//! A [`ProductionChain`] is the subset of the recipe graph which contributes
+//! to a particular target item. It can be rendered as a Graphviz DOT graph or
+//! as a Mermaid flowchart, suitable for pasting into design documents.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write,
+};
+
+use crate::{
+    database::PrototypeDatabase,
+    policy::{RecipePolicy, RecipeSelector},
+    recipe::{ItemKind, NormalizedRecipe},
+    units::compact,
+    Result,
+};
+
+/// Options controlling which parts of a production chain are extracted.
+#[derive(Debug, Clone, Default)]
+pub struct ChainOptions {
+    /// Maximum number of recipe steps to expand from the target.
+    ///
+    /// `None` expands the chain until only raw inputs remain.
+    pub depth: Option<usize>,
+    /// Omit recipes which fill or empty fluid barrels.
+    pub collapse_barreling: bool,
+    /// Omit recipes marked `hidden`.
+    pub hide_hidden: bool,
+    /// Items which are treated as raw inputs: their recipes are not expanded.
+    pub raw: BTreeSet<String>,
+    /// Restrict each item to the recipes this policy allows.
+    ///
+    /// `None` includes every producing recipe, so that alternatives appear
+    /// side by side.
+    pub policy: Option<RecipePolicy>,
+}
+
+impl ChainOptions {
+    fn admits(&self, recipe: &NormalizedRecipe) -> bool {
+        !((self.collapse_barreling && recipe.is_barreling()) || (self.hide_hidden && recipe.hidden))
+    }
+}
+
+/// The recipes and items which contribute to a target item.
+#[derive(Debug, Clone)]
+pub struct ProductionChain<'a> {
+    pub target: String,
+    pub items: BTreeMap<&'a str, ItemKind>,
+    pub recipes: BTreeMap<&'a str, &'a NormalizedRecipe>,
+}
+
+impl<'a> ProductionChain<'a> {
+    /// Extract the production chain of `target` from the database.
+    ///
+    /// Every recipe which produces an expanded item is included, so alternate
+    /// recipes appear side by side, unless the options give a
+    /// [policy][ChainOptions::policy].
+    ///
+    /// Fails only if the policy names an unknown recipe, or one which does
+    /// not produce its item.
+    pub fn new(
+        database: &'a PrototypeDatabase,
+        target: &str,
+        options: &ChainOptions,
+    ) -> Result<Self> {
+        let selector = options
+            .policy
+            .as_ref()
+            .map(|policy| RecipeSelector::new(database, policy, &options.raw))
+            .transpose()?;
+        let mut items = BTreeMap::new();
+        let mut recipes = BTreeMap::new();
+        let mut expanded = BTreeSet::new();
+        let mut queue = VecDeque::from([(target.to_owned(), 0)]);
+
+        while let Some((item, depth)) = queue.pop_front() {
+            if !expanded.insert(item.clone())
+                || options.raw.contains(&item)
+                || options.depth.is_some_and(|max| depth >= max)
+            {
+                continue;
+            }
+
+            let producers = match &selector {
+                Some(selector) => selector.candidates(&item)?,
+                None => database.producers(&item).collect(),
+            };
+            for recipe in producers {
+                if !options.admits(recipe) || recipes.contains_key(recipe.name.as_str()) {
+                    continue;
+                }
+                recipes.insert(recipe.name.as_str(), recipe);
+                for ingredient in &recipe.ingredients {
+                    items.insert(ingredient.name.as_str(), ingredient.kind);
+                    queue.push_back((ingredient.name.clone(), depth + 1));
+                }
+                for product in &recipe.products {
+                    items.insert(product.name.as_str(), product.kind);
+                }
+            }
+        }
+
+        Ok(Self {
+            target: target.to_owned(),
+            items,
+            recipes,
+        })
+    }
+
+    /// `true` if no admitted recipe produces the target.
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    /// Render this chain as a Graphviz DOT digraph.
+    ///
+    /// Items are ellipses and recipes are boxes annotated with their duration
+    /// and category. Edges are labeled with amounts per craft; fluids and
+    /// their edges are drawn in blue, and fluid edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph {} {{", quote(&self.target)).unwrap();
+        writeln!(out, "    rankdir=LR;").unwrap();
+        writeln!(out, "    node [fontname=\"sans-serif\"];").unwrap();
+        writeln!(out, "    edge [fontname=\"sans-serif\"];").unwrap();
+
+        for (&name, &kind) in &self.items {
+            let mut attrs = format!("label={}, shape=ellipse", quote(name));
+            if kind == ItemKind::Fluid {
+                attrs.push_str(", style=filled, fillcolor=lightblue");
+            }
+            if name == self.target {
+                attrs.push_str(", peripheries=2");
+            }
+            writeln!(out, "    {} [{attrs}];", quote(&item_id(name))).unwrap();
+        }
+
+        for recipe in self.recipes.values() {
+            writeln!(
+                out,
+                "    {} [label={}, shape=box];",
+                quote(&recipe_id(&recipe.name)),
+                quote(&format!("{}\\n{}", recipe.name, annotation(recipe))),
+            )
+            .unwrap();
+        }
+
+        for recipe in self.recipes.values() {
+            for ingredient in &recipe.ingredients {
+                writeln!(
+                    out,
+                    "    {} -> {} [{}];",
+                    quote(&item_id(&ingredient.name)),
+                    quote(&recipe_id(&recipe.name)),
+                    dot_edge_attrs(&ingredient.amount.to_string(), ingredient.kind),
+                )
+                .unwrap();
+            }
+            for product in &recipe.products {
+                writeln!(
+                    out,
+                    "    {} -> {} [{}];",
+                    quote(&recipe_id(&recipe.name)),
+                    quote(&item_id(&product.name)),
+                    dot_edge_attrs(
+                        &product_label(product.amount, product.probability),
+                        product.kind
+                    ),
+                )
+                .unwrap();
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render this chain as a Mermaid flowchart.
+    ///
+    /// Items are stadiums and recipes are rectangles annotated with their
+    /// duration and category. Edges are labeled with amounts per craft; fluids
+    /// are drawn as cylinders with a `fluid` class, and fluid edges are dotted.
+    pub fn to_mermaid(&self) -> String {
+        // mermaid node ids must be plain identifiers, so number the nodes
+        let item_ids = self
+            .items
+            .keys()
+            .enumerate()
+            .map(|(idx, &name)| (name, format!("i{idx}")))
+            .collect::<BTreeMap<_, _>>();
+        let recipe_ids = self
+            .recipes
+            .keys()
+            .enumerate()
+            .map(|(idx, &name)| (name, format!("r{idx}")))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut out = String::new();
+        writeln!(out, "flowchart LR").unwrap();
+
+        for (&name, &kind) in &self.items {
+            let id = &item_ids[name];
+            match kind {
+                ItemKind::Item => writeln!(out, "    {id}([\"{name}\"])").unwrap(),
+                ItemKind::Fluid => writeln!(out, "    {id}[(\"{name}\")]").unwrap(),
+            }
+        }
+        for recipe in self.recipes.values() {
+            writeln!(
+                out,
+                "    {}[\"{}<br/>{}\"]",
+                recipe_ids[recipe.name.as_str()],
+                recipe.name,
+                annotation(recipe)
+            )
+            .unwrap();
+        }
+
+        for recipe in self.recipes.values() {
+            let recipe_id = &recipe_ids[recipe.name.as_str()];
+            for ingredient in &recipe.ingredients {
+                writeln!(
+                    out,
+                    "    {} {}",
+                    item_ids[ingredient.name.as_str()],
+                    mermaid_edge(&ingredient.amount.to_string(), ingredient.kind, recipe_id),
+                )
+                .unwrap();
+            }
+            for product in &recipe.products {
+                writeln!(
+                    out,
+                    "    {recipe_id} {}",
+                    mermaid_edge(
+                        &product_label(product.amount, product.probability),
+                        product.kind,
+                        &item_ids[product.name.as_str()]
+                    ),
+                )
+                .unwrap();
+            }
+        }
+
+        let fluids = self
+            .items
+            .iter()
+            .filter(|(_, &kind)| kind == ItemKind::Fluid)
+            .map(|(&name, _)| item_ids[name].as_str())
+            .collect::<Vec<_>>();
+        if !fluids.is_empty() {
+            writeln!(out, "    classDef fluid fill:#def,stroke:#36c").unwrap();
+            writeln!(out, "    class {} fluid", fluids.join(",")).unwrap();
+        }
+        if let Some(target) = item_ids.get(self.target.as_str()) {
+            writeln!(out, "    style {target} stroke-width:3px").unwrap();
+        }
+
+        out
+    }
+}
+
+fn item_id(name: &str) -> String {
+    format!("item:{name}")
+}
+
+fn recipe_id(name: &str) -> String {
+    format!("recipe:{name}")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\\\""))
+}
+
+fn annotation(recipe: &NormalizedRecipe) -> String {
+    format!(
+        "{} s · {}",
+        compact(recipe.duration.as_seconds_f64(), 3),
+        recipe.category
+    )
+}
+
+fn product_label(amount: u32, probability: f64) -> String {
+    if probability < 1.0 {
+        format!("{amount} @ {}%", compact(probability * 100.0, 2))
+    } else {
+        amount.to_string()
+    }
+}
+
+fn dot_edge_attrs(label: &str, kind: ItemKind) -> String {
+    match kind {
+        ItemKind::Item => format!("label={}", quote(label)),
+        ItemKind::Fluid => format!(
+            "label={}, style=dashed, color=blue, fontcolor=blue",
+            quote(label)
+        ),
+    }
+}
+
+fn mermaid_edge(label: &str, kind: ItemKind, to: &str) -> String {
+    match kind {
+        ItemKind::Item => format!("-- \"{label}\" --> {to}"),
+        ItemKind::Fluid => format!("-. \"{label}\" .-> {to}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+    use crate::recipe::{Ingredient, Product};
+
+    fn recipe(
+        name: &str,
+        category: &str,
+        subgroup: Option<&str>,
+        ingredients: &[(&str, ItemKind, u32)],
+        products: &[(&str, ItemKind, u32)],
+    ) -> NormalizedRecipe {
+        NormalizedRecipe {
+            name: name.into(),
+            category: category.into(),
+            subgroup: subgroup.map(Into::into),
+            enabled: true,
+            hidden: false,
+            duration: Duration::seconds(1),
+            emissions_multiplier: 1.0,
+            ingredients: ingredients
+                .iter()
+                .map(|&(name, kind, amount)| Ingredient {
+                    name: name.into(),
+                    kind,
+                    amount,
+                })
+                .collect(),
+            products: products
+                .iter()
+                .map(|&(name, kind, amount)| Product {
+                    name: name.into(),
+                    kind,
+                    amount,
+                    probability: 1.0,
+                })
+                .collect(),
+        }
+    }
+
+    fn database() -> PrototypeDatabase {
+        use ItemKind::{Fluid, Item};
+
+        let recipes = [
+            recipe(
+                "plastic-bar",
+                "chemistry",
+                None,
+                &[("petroleum-gas", Fluid, 20), ("coal", Item, 1)],
+                &[("plastic-bar", Item, 2)],
+            ),
+            recipe(
+                "basic-oil-processing",
+                "oil-processing",
+                None,
+                &[("crude-oil", Fluid, 100)],
+                &[("petroleum-gas", Fluid, 45)],
+            ),
+            recipe(
+                "empty-petroleum-gas-barrel",
+                "crafting-with-fluid",
+                Some("empty-barrel"),
+                &[("petroleum-gas-barrel", Item, 1)],
+                &[("empty-barrel", Item, 1), ("petroleum-gas", Fluid, 50)],
+            ),
+        ];
+
+        PrototypeDatabase {
+            recipes: recipes
+                .into_iter()
+                .map(|recipe| (recipe.name.clone(), recipe))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn full_chain() {
+        let database = database();
+        let chain =
+            ProductionChain::new(&database, "plastic-bar", &ChainOptions::default()).unwrap();
+        assert_eq!(
+            chain.recipes.keys().copied().collect::<Vec<_>>(),
+            [
+                "basic-oil-processing",
+                "empty-petroleum-gas-barrel",
+                "plastic-bar"
+            ]
+        );
+    }
+
+    #[test]
+    fn options_cut_chain() {
+        let database = database();
+
+        let collapsed = ChainOptions {
+            collapse_barreling: true,
+            ..Default::default()
+        };
+        let chain = ProductionChain::new(&database, "plastic-bar", &collapsed).unwrap();
+        assert!(!chain.recipes.contains_key("empty-petroleum-gas-barrel"));
+        assert!(chain.recipes.contains_key("basic-oil-processing"));
+
+        let raw_gas = ChainOptions {
+            raw: BTreeSet::from(["petroleum-gas".to_owned()]),
+            ..Default::default()
+        };
+        let chain = ProductionChain::new(&database, "plastic-bar", &raw_gas).unwrap();
+        assert_eq!(
+            chain.recipes.keys().copied().collect::<Vec<_>>(),
+            ["plastic-bar"]
+        );
+
+        let shallow = ChainOptions {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let chain = ProductionChain::new(&database, "plastic-bar", &shallow).unwrap();
+        assert_eq!(
+            chain.recipes.keys().copied().collect::<Vec<_>>(),
+            ["plastic-bar"]
+        );
+        assert!(chain.items.contains_key("petroleum-gas"));
+    }
+
+    #[test]
+    fn policy_cuts_chain() {
+        let database = database();
+        let options = ChainOptions {
+            policy: Some(RecipePolicy {
+                excluded_recipes: BTreeSet::from(["basic-oil-processing".to_owned()]),
+                ..RecipePolicy::permissive()
+            }),
+            ..Default::default()
+        };
+        let chain = ProductionChain::new(&database, "plastic-bar", &options).unwrap();
+        assert_eq!(
+            chain.recipes.keys().copied().collect::<Vec<_>>(),
+            ["empty-petroleum-gas-barrel", "plastic-bar"]
+        );
+
+        let options = ChainOptions {
+            policy: Some(RecipePolicy::default()),
+            ..Default::default()
+        };
+        let chain = ProductionChain::new(&database, "plastic-bar", &options).unwrap();
+        assert_eq!(
+            chain.recipes.keys().copied().collect::<Vec<_>>(),
+            ["basic-oil-processing", "plastic-bar"]
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let database = database();
+        let options = ChainOptions {
+            collapse_barreling: true,
+            ..Default::default()
+        };
+        let dot = ProductionChain::new(&database, "plastic-bar", &options)
+            .unwrap()
+            .to_dot();
+
+        assert!(dot.starts_with("digraph \"plastic-bar\" {"));
+        assert!(dot.contains(
+            r#""recipe:plastic-bar" [label="plastic-bar\n1 s · chemistry", shape=box];"#
+        ));
+        assert!(dot.contains(r#""item:coal" -> "recipe:plastic-bar" [label="1"];"#));
+        assert!(dot.contains(
+            r#""item:petroleum-gas" -> "recipe:plastic-bar" [label="20", style=dashed, color=blue, fontcolor=blue];"#
+        ));
+    }
+
+    #[test]
+    fn mermaid() {
+        let database = database();
+        let options = ChainOptions {
+            collapse_barreling: true,
+            ..Default::default()
+        };
+        let mermaid = ProductionChain::new(&database, "plastic-bar", &options)
+            .unwrap()
+            .to_mermaid();
+
+        // items: coal, crude-oil, petroleum-gas, plastic-bar
+        // recipes: basic-oil-processing, plastic-bar
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("    i0([\"coal\"])\n"));
+        assert!(mermaid.contains("    i2[(\"petroleum-gas\")]\n"));
+        assert!(mermaid.contains("    r1[\"plastic-bar<br/>1 s · chemistry\"]\n"));
+        assert!(mermaid.contains("    i0 -- \"1\" --> r1\n"));
+        assert!(mermaid.contains("    i2 -. \"20\" .-> r1\n"));
+        assert!(mermaid.contains("    class i1,i2 fluid\n"));
+    }
+
+    #[test]
+    fn probabilistic_label() {
+        assert_eq!(product_label(1, 0.007000000000000001), "1 @ 0.7%");
+        assert_eq!(product_label(2, 1.0), "2");
+    }
+}
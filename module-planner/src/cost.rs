@@ -0,0 +1,284 @@
+//! Raw-resource cost of every item.
+//!
+//! The cost of an item is the raw resources consumed, and the total crafting
+//! time spent, to produce one unit of it. Costs are found by relaxation, as in
+//! a shortest-path search: raw items cost themselves, and each recipe offers
+//! its products at the cost of its ingredients. Where several recipes produce
+//! an item, the one with the least total raw cost wins, so costs make
+//! alternative recipes directly comparable.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase, number::Number, policy::RecipePolicy, recipe::NormalizedRecipe,
+    Result,
+};
+
+/// Which recipes may produce each item when computing costs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostOptions {
+    /// The recipes which may produce each item. Of those, the cheapest is
+    /// used, whether or not the policy prefers it.
+    #[serde(flatten)]
+    pub policy: RecipePolicy,
+    /// Items treated as raw even if some recipe produces them.
+    #[serde(default)]
+    pub raw: BTreeSet<String>,
+}
+
+/// The cost of producing one unit of an item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemCost<N = f64> {
+    /// The recipe which produces the item, or `None` for raw items.
+    pub recipe: Option<String>,
+    /// Raw resources consumed, by item name.
+    pub raw: BTreeMap<String, N>,
+    /// Crafting time spent at crafting speed 1, in seconds, summed over every
+    /// recipe in the chain.
+    pub crafting_time: N,
+}
+
+impl<N: Number> ItemCost<N> {
+    /// The total amount of raw resources, regardless of kind.
+    pub fn total(&self) -> N {
+        self.raw.values().cloned().sum()
+    }
+
+    /// `true` if this cost is less than `other`: first by total raw
+    /// resources, then by crafting time.
+    pub(crate) fn is_cheaper_than(&self, other: &Self) -> bool {
+        let total = other.total() - self.total();
+        if total.is_zero() {
+            (other.crafting_time.clone() - self.crafting_time.clone()).is_positive()
+        } else {
+            total.is_positive()
+        }
+    }
+}
+
+/// The cost of every item which recipes consume or produce.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostTable<N = f64> {
+    pub items: BTreeMap<String, ItemCost<N>>,
+    /// Items which are produced only from one another, with no path from raw
+    /// resources, and so have no cost.
+    pub unresolved: BTreeSet<String>,
+}
+
+impl<N: Number> CostTable<N> {
+    /// Compute the cost of every item.
+    ///
+    /// Catalysts and loops within a recipe are netted out, so kovarex
+    /// enrichment costs uranium-235 as 3 uranium-238 per unit. Loops between
+    /// recipes which multiply an item would make it ever cheaper; relaxation
+    /// stops after one pass per item, so costs in such loops are
+    /// approximate. The whole cost of a multi-output recipe is charged to
+    /// each product in turn.
+    pub fn new(database: &PrototypeDatabase, options: &CostOptions) -> Result<Self> {
+        let items = database
+            .recipes
+            .values()
+            .flat_map(|recipe| {
+                let ingredients = recipe.ingredients.iter().map(|i| i.name.as_str());
+                let products = recipe.products.iter().map(|p| p.name.as_str());
+                ingredients.chain(products)
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut costs = BTreeMap::<String, ItemCost<N>>::new();
+        let mut producers = BTreeMap::<&str, Vec<&NormalizedRecipe>>::new();
+        for &item in &items {
+            let candidates = if options.raw.contains(item) {
+                Vec::new()
+            } else {
+                options.policy.producers(database, item)?
+            };
+
+            if candidates.is_empty() {
+                let cost = ItemCost {
+                    recipe: None,
+                    raw: BTreeMap::from([(item.to_owned(), N::one())]),
+                    crafting_time: N::zero(),
+                };
+                costs.insert(item.to_owned(), cost);
+            } else {
+                producers.insert(item, candidates);
+            }
+        }
+
+        for _ in 0..=producers.len() {
+            let mut changed = false;
+            for (&item, recipes) in &producers {
+                for recipe in recipes {
+                    let Some(candidate) = recipe_cost(recipe, item, &costs) else {
+                        continue;
+                    };
+                    let better = costs
+                        .get(item)
+                        .is_none_or(|current| candidate.is_cheaper_than(current));
+                    if better {
+                        costs.insert(item.to_owned(), candidate);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let unresolved = producers
+            .keys()
+            .filter(|&&item| !costs.contains_key(item))
+            .map(|&item| item.to_owned())
+            .collect();
+        Ok(Self {
+            items: costs,
+            unresolved,
+        })
+    }
+
+    /// The names of every raw resource which some item costs, in name order.
+    pub fn resources(&self) -> BTreeSet<&str> {
+        self.items
+            .values()
+            .flat_map(|cost| cost.raw.keys().map(String::as_str))
+            .collect()
+    }
+}
+
+/// The cost of one `item` made by `recipe`, if every ingredient has a cost.
+pub(crate) fn recipe_cost<N: Number>(
+    recipe: &NormalizedRecipe,
+    item: &str,
+    costs: &BTreeMap<String, ItemCost<N>>,
+) -> Option<ItemCost<N>> {
+    let net = recipe.net_amounts::<N>();
+    let output = net.get(item)?.clone();
+
+    let mut raw = BTreeMap::<String, N>::new();
+    let mut crafting_time = recipe.seconds::<N>();
+    for (ingredient, amount) in &net {
+        if !amount.is_negative() {
+            continue;
+        }
+        let cost = costs.get(*ingredient)?;
+        let amount = -amount.clone();
+        for (resource, per_unit) in &cost.raw {
+            *raw.entry(resource.clone()).or_default() += per_unit.clone() * amount.clone();
+        }
+        crafting_time += cost.crafting_time.clone() * amount;
+    }
+
+    Some(ItemCost {
+        recipe: Some(recipe.name.clone()),
+        raw: raw
+            .into_iter()
+            .map(|(resource, amount)| (resource, amount / output.clone()))
+            .collect(),
+        crafting_time: crafting_time / output,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        number::Rational,
+        plan::tests::{assert_close, database, recipe},
+        recipe::ItemKind,
+    };
+
+    #[test]
+    fn red_circuit() {
+        let costs = CostTable::<Rational>::new(&database(), &CostOptions::default()).unwrap();
+
+        let circuit = &costs.items["electronic-circuit"];
+        assert_eq!(circuit.recipe.as_deref(), Some("electronic-circuit"));
+        assert_eq!(circuit.raw["iron-plate"], Rational::new(1, 1));
+        assert_eq!(circuit.raw["copper-plate"], Rational::new(3, 2));
+        // one circuit craft and one and a half cable crafts
+        assert_eq!(circuit.crafting_time, Rational::new(5, 4));
+
+        let plate = &costs.items["iron-plate"];
+        assert_eq!(plate.recipe, None);
+        assert_eq!(plate.crafting_time, Rational::zero());
+    }
+
+    #[test]
+    fn cheapest_alternative() {
+        let costs = CostTable::<f64>::new(&database(), &CostOptions::default()).unwrap();
+
+        // basic processing: 100 crude oil per 45 gas, against 150 crude oil
+        // and water per 55 gas for advanced processing
+        let gas = &costs.items["petroleum-gas"];
+        assert_eq!(gas.recipe.as_deref(), Some("basic-oil-processing"));
+        assert_close(gas.raw["crude-oil"], 100.0 / 45.0);
+
+        let options = CostOptions {
+            policy: RecipePolicy {
+                recipes: BTreeMap::from([(
+                    "petroleum-gas".into(),
+                    "advanced-oil-processing".into(),
+                )]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let costs = CostTable::<f64>::new(&database(), &options).unwrap();
+        let gas = &costs.items["petroleum-gas"];
+        assert_close(gas.raw["crude-oil"], 100.0 / 55.0);
+        assert_close(gas.raw["water"], 50.0 / 55.0);
+    }
+
+    #[test]
+    fn catalyst_loop() {
+        let options = CostOptions {
+            policy: RecipePolicy {
+                recipes: BTreeMap::from([(
+                    "uranium-235".into(),
+                    "kovarex-enrichment-process".into(),
+                )]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let costs = CostTable::<Rational>::new(&database(), &options).unwrap();
+
+        // 3 uranium-238 per unit, each costing 10 ore per 0.993 units
+        let uranium = &costs.items["uranium-235"];
+        assert_eq!(uranium.raw["uranium-ore"], Rational::new(30_000, 993));
+    }
+
+    #[test]
+    fn unresolved_loop() {
+        let mut database = database();
+        for (name, from, to) in [("a-to-b", "a", "b"), ("b-to-a", "b", "a")] {
+            database.recipes.insert(
+                name.into(),
+                recipe(
+                    name,
+                    "crafting",
+                    1.0,
+                    &[(from, ItemKind::Item, 1)],
+                    &[(to, ItemKind::Item, 1, 1.0)],
+                ),
+            );
+        }
+        let costs = CostTable::<f64>::new(&database, &CostOptions::default()).unwrap();
+        assert_eq!(
+            costs.unresolved,
+            BTreeSet::from(["a".to_owned(), "b".to_owned()])
+        );
+
+        let options = CostOptions {
+            raw: BTreeSet::from(["a".into()]),
+            ..Default::default()
+        };
+        let costs = CostTable::<f64>::new(&database, &options).unwrap();
+        assert!(costs.unresolved.is_empty());
+        assert_close(costs.items["b"].raw["a"], 1.0);
+    }
+}
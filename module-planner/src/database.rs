@@ -0,0 +1,204 @@
+//! A queryable collection of normalized prototypes.
+
+use std::{collections::BTreeMap, path::Path};
+
+use data_parser::models::{self, CraftingMachine, Recipe, TransportBelt};
+
+use crate::{
+    belt::Belt,
+    graph::RecipeGraph,
+    inserter::Inserter,
+    machine::{Machine, MACHINE_TYPES},
+    modules::{Beacon, ModuleItem},
+    power::{Fuel, Generator},
+    recipe::{Difficulty, NormalizedRecipe},
+    technology::Technology,
+    Error, Result,
+};
+
+/// Normalized prototypes extracted from a data dump.
+///
+/// Prototypes are stored in maps ordered by name, so that iteration, and
+/// therefore all derived output, is deterministic.
+#[derive(Debug, Clone, Default)]
+pub struct PrototypeDatabase {
+    pub difficulty: Difficulty,
+    pub recipes: BTreeMap<String, NormalizedRecipe>,
+    pub machines: BTreeMap<String, Machine>,
+    pub modules: BTreeMap<String, ModuleItem>,
+    pub beacons: BTreeMap<String, Beacon>,
+    pub belts: BTreeMap<String, Belt>,
+    pub inserters: BTreeMap<String, Inserter>,
+    /// Burnable items, by item name.
+    pub fuels: BTreeMap<String, Fuel>,
+    /// Generators and solar panels.
+    pub generators: BTreeMap<String, Generator>,
+    pub technologies: BTreeMap<String, Technology>,
+}
+
+/// Deserialize every prototype under a top-level key of the dump.
+///
+/// Missing keys produce an empty map: not every dump defines every prototype
+/// type.
+fn prototypes<T>(data: &serde_json::Value, key: &str) -> Result<BTreeMap<String, T>>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let Some(object) = data.get(key).and_then(|value| value.as_object()) else {
+        return Ok(BTreeMap::new());
+    };
+
+    object
+        .iter()
+        .map(|(name, value)| {
+            let prototype = T::deserialize(value).map_err(|err| Error::Prototype {
+                kind: key.into(),
+                name: name.clone(),
+                inner: err,
+            })?;
+            Ok((name.clone(), prototype))
+        })
+        .collect()
+}
+
+impl PrototypeDatabase {
+    /// Build a database from a data dump as produced by
+    /// [`data_parser::parse_lua`] or [`data_parser::load`].
+    pub fn from_value(data: &serde_json::Value, difficulty: Difficulty) -> Result<Self> {
+        let recipes = prototypes::<Recipe>(data, "recipe")?
+            .into_iter()
+            .map(|(name, recipe)| (name, NormalizedRecipe::new(&recipe, difficulty)))
+            .collect();
+
+        let mut machines = BTreeMap::new();
+        for key in MACHINE_TYPES {
+            for (name, machine) in prototypes::<CraftingMachine>(data, key)? {
+                machines.insert(name, Machine::new(&machine)?);
+            }
+        }
+
+        let modules = prototypes::<models::Module>(data, "module")?
+            .into_iter()
+            .map(|(name, module)| (name, ModuleItem::new(&module)))
+            .collect();
+
+        let beacons = prototypes::<models::Beacon>(data, "beacon")?
+            .into_iter()
+            .map(|(name, beacon)| Ok((name, Beacon::new(&beacon)?)))
+            .collect::<Result<_>>()?;
+
+        let belts = prototypes::<TransportBelt>(data, "transport-belt")?
+            .into_iter()
+            .map(|(name, belt)| (name, Belt::new(&belt)))
+            .collect();
+
+        let inserters = prototypes::<models::Inserter>(data, "inserter")?
+            .into_iter()
+            .map(|(name, inserter)| (name, Inserter::new(&inserter)))
+            .collect();
+
+        let mut fuels = BTreeMap::new();
+        for (name, item) in prototypes::<models::Item>(data, "item")? {
+            if let Some(fuel) = Fuel::new(&item)? {
+                fuels.insert(name, fuel);
+            }
+        }
+
+        let mut generators = BTreeMap::new();
+        for (name, generator) in prototypes::<models::Generator>(data, "generator")? {
+            if let Some(generator) = Generator::new(&generator)? {
+                generators.insert(name, generator);
+            }
+        }
+        for (name, panel) in prototypes::<models::SolarPanel>(data, "solar-panel")? {
+            if let Some(panel) = Generator::solar(&panel)? {
+                generators.insert(name, panel);
+            }
+        }
+
+        let technologies = prototypes::<models::Technology>(data, "technology")?
+            .into_iter()
+            .map(|(name, technology)| (name, Technology::new(&technology)))
+            .collect();
+
+        Ok(Self {
+            difficulty,
+            recipes,
+            machines,
+            modules,
+            beacons,
+            belts,
+            inserters,
+            fuels,
+            generators,
+            technologies,
+        })
+    }
+
+    /// Load a database from a data dump on disk.
+    ///
+    /// See [`data_parser::load`] for the accepted formats.
+    pub fn load(path: impl AsRef<Path>, difficulty: Difficulty) -> Result<Self> {
+        let data = data_parser::load(path)?;
+        Self::from_value(&data, difficulty)
+    }
+
+    pub fn recipe(&self, name: &str) -> Option<&NormalizedRecipe> {
+        self.recipes.get(name)
+    }
+
+    pub fn machine(&self, name: &str) -> Option<&Machine> {
+        self.machines.get(name)
+    }
+
+    pub fn module(&self, name: &str) -> Option<&ModuleItem> {
+        self.modules.get(name)
+    }
+
+    pub fn beacon(&self, name: &str) -> Option<&Beacon> {
+        self.beacons.get(name)
+    }
+
+    pub fn belt(&self, name: &str) -> Option<&Belt> {
+        self.belts.get(name)
+    }
+
+    pub fn inserter(&self, name: &str) -> Option<&Inserter> {
+        self.inserters.get(name)
+    }
+
+    /// All recipes which produce the named item, in name order.
+    pub fn producers<'a: 'b, 'b>(
+        &'a self,
+        item: &'b str,
+    ) -> impl 'b + Iterator<Item = &'a NormalizedRecipe> {
+        self.recipes
+            .values()
+            .filter(move |recipe| recipe.product(item).is_some())
+    }
+
+    /// All recipes which consume the named item, in name order.
+    pub fn consumers<'a: 'b, 'b>(
+        &'a self,
+        item: &'b str,
+    ) -> impl 'b + Iterator<Item = &'a NormalizedRecipe> {
+        self.recipes
+            .values()
+            .filter(move |recipe| recipe.ingredient(item).is_some())
+    }
+
+    /// All technologies which unlock the named recipe, in name order.
+    pub fn unlocked_by<'a: 'b, 'b>(
+        &'a self,
+        recipe: &'b str,
+    ) -> impl 'b + Iterator<Item = &'a Technology> {
+        self.technologies
+            .values()
+            .filter(move |technology| technology.unlocks.iter().any(|name| name == recipe))
+    }
+
+    /// Build the dependency graph of every recipe in this database.
+    pub fn graph(&self) -> RecipeGraph {
+        RecipeGraph::new(self.recipes.values())
+    }
+}
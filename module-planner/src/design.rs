@@ -0,0 +1,443 @@
+//! Interactive design sessions.
+//!
+//! A [`Design`] is the state behind the `designer` terminal UI: a
+//! [`PlanFile`] being edited, and the plan, production tree, belt counts and
+//! power report recomputed after every change. It is kept apart from
+//! rendering and input handling so that it can be driven, and tested,
+//! without a terminal.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    belt,
+    database::PrototypeDatabase,
+    modules::BeaconLoadout,
+    plan::{ProductionPlan, RecipeStep},
+    plan_file::PlanFile,
+    power::PowerReport,
+    Error, Result,
+};
+
+/// Score how well `query` matches `candidate`, higher being better.
+///
+/// Every character of the query must appear in the candidate, in order and
+/// ignoring case. Consecutive matches and matches at the start of a word
+/// score more; skipped characters score less.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut previous = None;
+    let mut chars = candidate.char_indices().peekable();
+    for wanted in query.chars().map(|c| c.to_ascii_lowercase()) {
+        let (index, _) = chars
+            .by_ref()
+            .find(|(_, c)| c.to_ascii_lowercase() == wanted)?;
+        let at_word_start = index == 0
+            || candidate[..index]
+                .chars()
+                .next_back()
+                .is_some_and(|c| !c.is_alphanumeric());
+        score += match previous {
+            Some(previous) if previous + 1 == index => 8,
+            _ if at_word_start => 6,
+            Some(previous) => -((index - previous) as i64).min(5),
+            None => -(index as i64).min(5),
+        };
+        previous = Some(index);
+    }
+    Some(score)
+}
+
+/// The candidates matching `query`, best first; ties go to the shorter
+/// candidate, then to the first by name.
+pub fn fuzzy_search<'a>(
+    query: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let mut matches = candidates
+        .into_iter()
+        .filter_map(|candidate| Some((fuzzy_score(query, candidate)?, candidate)))
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then(a.len().cmp(&b.len()))
+            .then(a.cmp(b))
+    });
+    matches
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// One line of the production tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    /// The nesting depth, 0 for targets.
+    pub depth: usize,
+    pub item: String,
+    /// Items per second flowing to the parent, or produced for a target.
+    pub rate: f64,
+    /// The recipe producing the item, or `None` for raw inputs.
+    pub recipe: Option<String>,
+    /// The share of the producing step's machines working for this node.
+    pub machines: f64,
+}
+
+/// The results of planning a design.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub plan: ProductionPlan,
+    pub power: PowerReport,
+    pub tree: Vec<TreeNode>,
+    /// The number of belts needed to supply each solid raw input.
+    pub input_belts: BTreeMap<String, f64>,
+}
+
+impl Summary {
+    fn new(database: &PrototypeDatabase, plan: ProductionPlan, belt: Option<&str>) -> Result<Self> {
+        let power = PowerReport::new(database, &plan)?;
+
+        let mut tree = Vec::new();
+        for (item, rate) in &plan.targets {
+            add_node(&plan, &mut tree, &mut Vec::new(), item, *rate);
+        }
+
+        let input_belts = match belt.and_then(|belt| database.belt(belt)) {
+            Some(belt) => belt::input_belts(&plan, belt),
+            None => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            plan,
+            power,
+            tree,
+            input_belts,
+        })
+    }
+}
+
+/// Add `item`, and the items it is made from, to the production tree.
+fn add_node(
+    plan: &ProductionPlan,
+    tree: &mut Vec<TreeNode>,
+    path: &mut Vec<String>,
+    item: &str,
+    rate: f64,
+) {
+    // catalysts such as kovarex enrichment are net producers of one item only
+    let net = |step: &RecipeStep| {
+        step.outputs.get(item).copied().unwrap_or(0.0)
+            - step.inputs.get(item).copied().unwrap_or(0.0)
+    };
+    let step = plan.steps.values().find(|step| net(step) > 0.0);
+    let share = step.map_or(0.0, |step| rate / net(step));
+    tree.push(TreeNode {
+        depth: path.len(),
+        item: item.into(),
+        rate,
+        recipe: step.map(|step| step.recipe.clone()),
+        machines: step.map_or(0.0, |step| step.machines * share),
+    });
+
+    // stop at cycles such as kovarex enrichment
+    let Some(step) = step else { return };
+    if path.iter().any(|seen| seen == item) {
+        return;
+    }
+    path.push(item.into());
+    for (input, input_rate) in &step.inputs {
+        let consumed = input_rate - step.outputs.get(input).copied().unwrap_or(0.0);
+        if consumed > 0.0 {
+            add_node(plan, tree, path, input, consumed * share);
+        }
+    }
+    path.pop();
+}
+
+/// A plan file being designed interactively.
+#[derive(Debug)]
+pub struct Design<'a> {
+    pub database: &'a PrototypeDatabase,
+    pub file: PlanFile,
+    /// The belt tier in which raw inputs are counted.
+    pub belt: Option<String>,
+    summary: Result<Summary>,
+}
+
+impl<'a> Design<'a> {
+    pub fn new(database: &'a PrototypeDatabase, file: PlanFile) -> Self {
+        // the slowest belt, which is the one available from the start
+        let belt = database
+            .belts
+            .values()
+            .min_by(|a, b| a.speed.total_cmp(&b.speed))
+            .map(|belt| belt.name.clone());
+        let mut design = Self {
+            database,
+            file,
+            belt,
+            summary: Ok(Summary {
+                plan: ProductionPlan::default(),
+                power: PowerReport::default(),
+                tree: Vec::new(),
+                input_belts: BTreeMap::new(),
+            }),
+        };
+        design.recompute();
+        design
+    }
+
+    /// The results of planning, or the reason planning failed.
+    pub fn summary(&self) -> std::result::Result<&Summary, &Error> {
+        self.summary.as_ref()
+    }
+
+    /// The item whose recipe must be chosen before the design can be
+    /// planned, with the candidate recipes.
+    pub fn pending_choice(&self) -> Option<(&str, &[String])> {
+        match &self.summary {
+            Err(Error::AmbiguousRecipe { item, candidates }) => Some((item, candidates)),
+            _ => None,
+        }
+    }
+
+    /// Every item produced or consumed by some recipe, in name order.
+    pub fn items(&self) -> Vec<&'a str> {
+        self.database
+            .recipes
+            .values()
+            .flat_map(|recipe| {
+                let ingredients = recipe.ingredients.iter().map(|i| i.name.as_str());
+                let products = recipe.products.iter().map(|p| p.name.as_str());
+                ingredients.chain(products)
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The recipes which produce `item`.
+    pub fn recipes_for(&self, item: &str) -> Vec<&'a str> {
+        self.database
+            .producers(item)
+            .map(|recipe| recipe.name.as_str())
+            .collect()
+    }
+
+    /// The machines which can craft `recipe`.
+    pub fn machines_for(&self, recipe: &str) -> Vec<&'a str> {
+        let Some(recipe) = self.database.recipe(recipe) else {
+            return Vec::new();
+        };
+        self.database
+            .machines
+            .values()
+            .filter(|machine| machine.can_craft(&recipe.category))
+            .map(|machine| machine.name.as_str())
+            .collect()
+    }
+
+    /// The modules which may be used in `recipe`.
+    pub fn modules_for(&self, recipe: &str) -> Vec<&'a str> {
+        self.database
+            .modules
+            .values()
+            .filter(|module| module.allows_recipe(recipe))
+            .map(|module| module.name.as_str())
+            .collect()
+    }
+
+    pub fn set_target(&mut self, item: &str, rate: f64) {
+        self.file.request.targets.insert(item.into(), rate);
+        self.recompute();
+    }
+
+    pub fn remove_target(&mut self, item: &str) {
+        self.file.request.targets.remove(item);
+        self.recompute();
+    }
+
+    pub fn choose_recipe(&mut self, item: &str, recipe: &str) {
+        self.file
+            .request
+            .policy
+            .recipes
+            .insert(item.into(), recipe.into());
+        self.recompute();
+    }
+
+    /// Craft the category of `recipe`, and every other recipe of that
+    /// category, in `machine`.
+    pub fn choose_machine(&mut self, recipe: &str, machine: &str) {
+        let Some(recipe) = self.database.recipe(recipe) else {
+            return;
+        };
+        self.file
+            .request
+            .machines
+            .insert(recipe.category.clone(), machine.into());
+        self.recompute();
+    }
+
+    pub fn add_module(&mut self, recipe: &str, module: &str) {
+        let loadout = self.file.request.modules.entry(recipe.into()).or_default();
+        loadout.modules.push(module.into());
+        self.recompute();
+    }
+
+    /// Add a beacon of the first kind in the database, filled with `module`,
+    /// in range of every machine crafting `recipe`.
+    pub fn add_beacon(&mut self, recipe: &str, module: &str) {
+        let Some(beacon) = self.database.beacons.values().next() else {
+            return;
+        };
+        let modules = vec![module.to_owned(); beacon.module_slots as usize];
+        let loadout = self.file.request.modules.entry(recipe.into()).or_default();
+        match loadout
+            .beacons
+            .iter_mut()
+            .find(|loadout| loadout.beacon == beacon.name && loadout.modules == modules)
+        {
+            Some(loadout) => loadout.count += 1,
+            None => loadout.beacons.push(BeaconLoadout {
+                beacon: beacon.name.clone(),
+                count: 1,
+                modules,
+            }),
+        }
+        self.recompute();
+    }
+
+    /// Remove every module and beacon from `recipe`.
+    pub fn clear_modules(&mut self, recipe: &str) {
+        self.file.request.modules.remove(recipe);
+        self.recompute();
+    }
+
+    /// Count raw input belts in the next belt tier, by speed.
+    pub fn cycle_belt(&mut self) {
+        let mut belts = self.database.belts.values().collect::<Vec<_>>();
+        belts.sort_by(|a, b| a.speed.total_cmp(&b.speed));
+        let current = belts
+            .iter()
+            .position(|belt| Some(&belt.name) == self.belt.as_ref());
+        let next = current.map_or(0, |index| (index + 1) % belts.len());
+        self.belt = belts.get(next).map(|belt| belt.name.clone());
+        self.recompute();
+    }
+
+    fn recompute(&mut self) {
+        self.summary = self
+            .file
+            .plan(self.database)
+            .and_then(|plan| Summary::new(self.database, plan, self.belt.as_deref()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        belt::Belt,
+        plan::tests::{assert_close, database},
+        plan::PlanRequest,
+        recipe::Difficulty,
+    };
+
+    fn file() -> PlanFile {
+        PlanFile {
+            data: "prototype-data".into(),
+            difficulty: Difficulty::Normal,
+            solver: None,
+            request: PlanRequest {
+                machines: BTreeMap::from([("crafting".into(), "assembling-machine-2".into())]),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn fuzzy() {
+        let items = [
+            "copper-cable",
+            "copper-plate",
+            "electronic-circuit",
+            "iron-plate",
+        ];
+        assert_eq!(fuzzy_search("ec", items)[0], "electronic-circuit");
+        assert_eq!(fuzzy_search("plate", items), ["iron-plate", "copper-plate"]);
+        assert_eq!(fuzzy_search("cc", items)[0], "copper-cable");
+        assert!(fuzzy_search("xyz", items).is_empty());
+        assert_eq!(fuzzy_search("", items).len(), items.len());
+    }
+
+    #[test]
+    fn editing() {
+        let mut database = database();
+        database.belts.insert(
+            "transport-belt".into(),
+            Belt {
+                name: "transport-belt".into(),
+                speed: 0.03125,
+            },
+        );
+        let mut design = Design::new(&database, file());
+        assert!(design.summary().unwrap().tree.is_empty());
+
+        design.set_target("electronic-circuit", 15.0);
+        let summary = design.summary().unwrap();
+        let tree = summary
+            .tree
+            .iter()
+            .map(|node| (node.depth, node.item.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tree,
+            [
+                (0, "electronic-circuit"),
+                (1, "copper-cable"),
+                (2, "copper-plate"),
+                (1, "iron-plate"),
+            ]
+        );
+        assert_close(summary.tree[1].rate, 45.0);
+        assert_close(summary.tree[1].machines, 15.0);
+        assert_eq!(summary.tree[2].recipe, None);
+        assert_close(summary.input_belts["copper-plate"], 1.5);
+        assert!(summary.power.electric > 0.0);
+
+        design.add_module("copper-cable", "speed-module");
+        let machines = design.summary().unwrap().plan.steps["copper-cable"].machines;
+        assert!(machines < 15.0);
+        design.clear_modules("copper-cable");
+        assert!(design.file.request.modules.is_empty());
+    }
+
+    #[test]
+    fn pending_choice() {
+        let database = database();
+        let mut design = Design::new(&database, file());
+        design.set_target("uranium-235", 1.0);
+        let (item, candidates) = design.pending_choice().unwrap();
+        assert_eq!(item, "uranium-235");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(design.recipes_for("uranium-235").len(), 2);
+
+        design.choose_recipe("uranium-235", "kovarex-enrichment-process");
+        design.choose_recipe("uranium-238", "uranium-processing");
+        assert!(design.pending_choice().is_none());
+        let tree = design
+            .summary()
+            .unwrap()
+            .tree
+            .iter()
+            .map(|node| (node.depth, node.item.as_str()))
+            .collect::<Vec<_>>();
+        // uranium-235 is a catalyst of kovarex enrichment, so only its net
+        // production appears
+        assert_eq!(
+            tree,
+            [(0, "uranium-235"), (1, "uranium-238"), (2, "uranium-ore"),]
+        );
+    }
+}
@@ -0,0 +1,533 @@
+//! Differences between two data dumps.
+//!
+//! When a mod updates, a [`DumpDiff`] reports which prototypes were added or
+//! removed, and which fields of the others changed. Fields are compared by
+//! value rather than as text, so `8` and `8.0` are equal, and everything is
+//! reported in name order. Recipes are also compared after normalization, so
+//! that a change reads as "advanced-circuit: energy_required 6 → 8,
+//! plastic-bar 2 → 3" whichever of Factorio's recipe forms each dump uses.
+//!
+//! [`DumpDiff::impact`] then tells whether a saved [`PlanFile`] uses anything
+//! which changed.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use data_parser::models::Recipe;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    database::PrototypeDatabase,
+    machine::MACHINE_TYPES,
+    plan_file::PlanFile,
+    recipe::{Difficulty, NormalizedRecipe},
+    units::compact,
+};
+
+/// A change to one field of a prototype.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// Where the field is within the prototype, such as `ingredients[1].amount`.
+    pub path: String,
+    /// `None` where the field was added.
+    pub old: Option<Value>,
+    /// `None` where the field was removed.
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "(none)".into(),
+        };
+        write!(f, "{} {} → {}", self.path, show(&self.old), show(&self.new))
+    }
+}
+
+/// Collect the differences between two values, descending into objects and
+/// into arrays of equal length.
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_values(&path, old, new, changes),
+                    (old, new) => changes.push(FieldChange {
+                        path,
+                        old: old.cloned(),
+                        new: new.cloned(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (index, (old, new)) in old.iter().zip(new).enumerate() {
+                diff_values(&format!("{path}[{index}]"), old, new, changes);
+            }
+        }
+        (Value::Number(a), Value::Number(b)) if a.as_f64() == b.as_f64() => {}
+        _ if old == new => {}
+        _ => changes.push(FieldChange {
+            path: path.into(),
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+    }
+}
+
+/// The differences between two versions of a recipe, after normalization.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecipeDiff {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<(String, String)>,
+    /// The crafting time, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_required: Option<(f64, f64)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<(bool, bool)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<(bool, bool)>,
+    /// Ingredient amounts which changed, by item. An amount is zero where the
+    /// item is not an ingredient.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ingredients: BTreeMap<String, (u32, u32)>,
+    /// Expected product amounts which changed, by item. An amount is zero
+    /// where the item is not a product.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub products: BTreeMap<String, (f64, f64)>,
+}
+
+/// The pairs of values which differ between two maps, with absent keys
+/// taken as `zero`.
+fn changed_amounts<T: Copy + PartialEq>(
+    old: BTreeMap<&str, T>,
+    new: BTreeMap<&str, T>,
+    zero: T,
+) -> BTreeMap<String, (T, T)> {
+    let names = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+    names
+        .into_iter()
+        .filter_map(|&name| {
+            let old = old.get(name).copied().unwrap_or(zero);
+            let new = new.get(name).copied().unwrap_or(zero);
+            (old != new).then(|| (name.to_owned(), (old, new)))
+        })
+        .collect()
+}
+
+impl RecipeDiff {
+    pub fn new(old: &NormalizedRecipe, new: &NormalizedRecipe) -> Self {
+        fn changed<T: PartialEq>(old: T, new: T) -> Option<(T, T)> {
+            (old != new).then_some((old, new))
+        }
+
+        fn ingredients(recipe: &NormalizedRecipe) -> BTreeMap<&str, u32> {
+            let mut amounts = BTreeMap::<&str, u32>::new();
+            for ingredient in &recipe.ingredients {
+                *amounts.entry(&ingredient.name).or_default() += ingredient.amount;
+            }
+            amounts
+        }
+        fn products(recipe: &NormalizedRecipe) -> BTreeMap<&str, f64> {
+            let mut amounts = BTreeMap::<&str, f64>::new();
+            for product in &recipe.products {
+                *amounts.entry(&product.name).or_default() += product.expected_amount::<f64>();
+            }
+            amounts
+        }
+
+        Self {
+            name: new.name.clone(),
+            category: changed(old.category.clone(), new.category.clone()),
+            energy_required: changed(old.seconds(), new.seconds()),
+            enabled: changed(old.enabled, new.enabled),
+            hidden: changed(old.hidden, new.hidden),
+            ingredients: changed_amounts(ingredients(old), ingredients(new), 0),
+            products: changed_amounts(products(old), products(new), 0.0),
+        }
+    }
+
+    /// `true` if the recipe is unchanged in every respect the planner uses.
+    pub fn is_empty(&self) -> bool {
+        self.category.is_none()
+            && self.energy_required.is_none()
+            && self.enabled.is_none()
+            && self.hidden.is_none()
+            && self.ingredients.is_empty()
+            && self.products.is_empty()
+    }
+}
+
+impl fmt::Display for RecipeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some((old, new)) = &self.category {
+            parts.push(format!("category {old} → {new}"));
+        }
+        if let Some((old, new)) = self.energy_required {
+            parts.push(format!(
+                "energy_required {} → {}",
+                compact(old, 3),
+                compact(new, 3)
+            ));
+        }
+        if let Some((old, new)) = self.enabled {
+            parts.push(format!("enabled {old} → {new}"));
+        }
+        if let Some((old, new)) = self.hidden {
+            parts.push(format!("hidden {old} → {new}"));
+        }
+        for (item, (old, new)) in &self.ingredients {
+            parts.push(format!("{item} {old} → {new}"));
+        }
+        for (item, (old, new)) in &self.products {
+            parts.push(format!(
+                "makes {item} {} → {}",
+                compact(*old, 3),
+                compact(*new, 3)
+            ));
+        }
+        write!(f, "{}: {}", self.name, parts.join(", "))
+    }
+}
+
+/// How a prototype differs between two dumps.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum PrototypeChange {
+    Added,
+    Removed,
+    Changed {
+        fields: Vec<FieldChange>,
+        /// For recipes which parse in both dumps, the normalized comparison.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        recipe: Option<RecipeDiff>,
+    },
+}
+
+impl PrototypeChange {
+    /// `false` for changes which cannot affect planning, such as a recipe
+    /// whose icon alone changed.
+    pub fn is_significant(&self) -> bool {
+        match self {
+            Self::Changed {
+                recipe: Some(recipe),
+                ..
+            } => !recipe.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+/// The differences between two data dumps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DumpDiff {
+    /// Changes by prototype type, then by prototype name.
+    pub prototypes: BTreeMap<String, BTreeMap<String, PrototypeChange>>,
+}
+
+impl DumpDiff {
+    /// Compare two dumps, as produced by [`data_parser::load`].
+    ///
+    /// Recipes are normalized for `difficulty`.
+    pub fn new(old: &Value, new: &Value, difficulty: Difficulty) -> Self {
+        /// The prototypes of each type in a dump.
+        fn types(dump: &Value) -> BTreeMap<&str, &serde_json::Map<String, Value>> {
+            let Some(types) = dump.as_object() else {
+                return BTreeMap::new();
+            };
+            (types.iter())
+                .filter_map(|(kind, prototypes)| Some((kind.as_str(), prototypes.as_object()?)))
+                .collect()
+        }
+
+        let empty = serde_json::Map::new();
+        let (old, new) = (types(old), types(new));
+
+        let mut prototypes = BTreeMap::new();
+        for kind in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+            let old = old.get(kind).copied().unwrap_or(&empty);
+            let new = new.get(kind).copied().unwrap_or(&empty);
+
+            let mut changes = BTreeMap::new();
+            for name in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+                let change = match (old.get(name), new.get(name)) {
+                    (None, _) => PrototypeChange::Added,
+                    (_, None) => PrototypeChange::Removed,
+                    (Some(old), Some(new)) => {
+                        let mut fields = Vec::new();
+                        diff_values("", old, new, &mut fields);
+                        if fields.is_empty() {
+                            continue;
+                        }
+                        let recipe = (*kind == "recipe")
+                            .then(|| recipe_diff(old, new, difficulty))
+                            .flatten();
+                        PrototypeChange::Changed { fields, recipe }
+                    }
+                };
+                changes.insert(name.clone(), change);
+            }
+            if !changes.is_empty() {
+                prototypes.insert((*kind).to_owned(), changes);
+            }
+        }
+        Self { prototypes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prototypes.is_empty()
+    }
+
+    /// `true` if a prototype of one of `kinds` named `name` was removed, or
+    /// changed significantly.
+    fn touches(&self, kinds: &[&str], name: &str) -> bool {
+        kinds.iter().any(|kind| {
+            self.prototypes
+                .get(*kind)
+                .and_then(|changes| changes.get(name))
+                .is_some_and(PrototypeChange::is_significant)
+        })
+    }
+
+    /// How the changes affect a saved plan.
+    ///
+    /// `old` and `new` are the databases built from the two dumps.
+    pub fn impact(
+        &self,
+        file: &PlanFile,
+        old: &PrototypeDatabase,
+        new: &PrototypeDatabase,
+    ) -> PlanImpact {
+        let request = &file.request;
+        let planned = file.plan(old);
+
+        // recipes and machines as planned, or as named if planning fails
+        let (recipes, mut entities): (BTreeSet<&str>, BTreeSet<&str>) = match &planned {
+            Ok(plan) => (
+                plan.steps.keys().map(String::as_str).collect(),
+                plan.steps.values().map(|s| s.machine.as_str()).collect(),
+            ),
+            Err(_) => (
+                request
+                    .policy
+                    .recipes
+                    .values()
+                    .map(String::as_str)
+                    .collect(),
+                request.machines.values().map(String::as_str).collect(),
+            ),
+        };
+        for loadout in request.modules.values() {
+            entities.extend(loadout.modules.iter().map(String::as_str));
+            for beacon in &loadout.beacons {
+                entities.insert(&beacon.beacon);
+                entities.extend(beacon.modules.iter().map(String::as_str));
+            }
+        }
+
+        let entity_kinds = MACHINE_TYPES
+            .iter()
+            .copied()
+            .chain(["module", "beacon"])
+            .collect::<Vec<_>>();
+        PlanImpact {
+            recipes: (recipes.into_iter())
+                .filter(|recipe| self.touches(&["recipe"], recipe))
+                .map(Into::into)
+                .collect(),
+            entities: (entities.into_iter())
+                .filter(|entity| self.touches(&entity_kinds, entity))
+                .map(Into::into)
+                .collect(),
+            error: file.plan(new).err().map(|err| err.to_string()),
+        }
+    }
+}
+
+fn recipe_diff(old: &Value, new: &Value, difficulty: Difficulty) -> Option<RecipeDiff> {
+    let old = Recipe::deserialize(old).ok()?;
+    let new = Recipe::deserialize(new).ok()?;
+    Some(RecipeDiff::new(
+        &NormalizedRecipe::new(&old, difficulty),
+        &NormalizedRecipe::new(&new, difficulty),
+    ))
+}
+
+impl fmt::Display for DumpDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (kind, changes) in &self.prototypes {
+            writeln!(f, "{kind}")?;
+            for (name, change) in changes {
+                match change {
+                    PrototypeChange::Added => writeln!(f, "  + {name}")?,
+                    PrototypeChange::Removed => writeln!(f, "  - {name}")?,
+                    PrototypeChange::Changed {
+                        recipe: Some(recipe),
+                        ..
+                    } if !recipe.is_empty() => writeln!(f, "  ~ {recipe}")?,
+                    PrototypeChange::Changed { fields, .. } => {
+                        let fields = fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+                        writeln!(f, "  ~ {name}: {}", fields.join(", "))?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the differences between two dumps affect a saved plan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanImpact {
+    /// Recipes the plan uses which were removed or changed.
+    pub recipes: Vec<String>,
+    /// Machines, modules and beacons the plan uses which were removed or
+    /// changed.
+    pub entities: Vec<String>,
+    /// Why the plan cannot be planned against the new dump, if it cannot.
+    pub error: Option<String>,
+}
+
+impl PlanImpact {
+    /// `true` if nothing the plan uses changed.
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty() && self.entities.is_empty() && self.error.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::plan::PlanRequest;
+
+    fn old() -> Value {
+        json!({
+            "recipe": {
+                "advanced-circuit": {
+                    "name": "advanced-circuit",
+                    "type": "recipe",
+                    "enabled": false,
+                    "energy_required": 6,
+                    "ingredients": [
+                        ["electronic-circuit", 2],
+                        ["plastic-bar", 2],
+                        ["copper-cable", 4]
+                    ],
+                    "result": "advanced-circuit"
+                },
+                "copper-cable": {
+                    "name": "copper-cable",
+                    "type": "recipe",
+                    "ingredients": [["copper-plate", 1]],
+                    "result": "copper-cable",
+                    "result_count": 2
+                },
+                "wooden-chest": {
+                    "name": "wooden-chest",
+                    "type": "recipe",
+                    "ingredients": [["wood", 2]],
+                    "result": "wooden-chest"
+                }
+            },
+            "assembling-machine": {
+                "assembling-machine-2": {
+                    "name": "assembling-machine-2",
+                    "type": "assembling-machine",
+                    "crafting_speed": 0.75,
+                    "crafting_categories": ["crafting"],
+                    "energy_usage": "150kW",
+                    "energy_source": {"type": "electric", "usage_priority": "secondary-input"}
+                }
+            }
+        })
+    }
+
+    fn new() -> Value {
+        let mut new = old();
+        let recipes = &mut new["recipe"];
+        recipes["advanced-circuit"]["energy_required"] = json!(8.0);
+        recipes["advanced-circuit"]["ingredients"][1] = json!(["plastic-bar", 3]);
+        recipes["copper-cable"]["icon"] = json!("__base__/copper-cable.png");
+        recipes.as_object_mut().unwrap().remove("wooden-chest");
+        recipes["iron-chest"] = json!({
+            "name": "iron-chest",
+            "type": "recipe",
+            "ingredients": [["iron-plate", 8]],
+            "result": "iron-chest"
+        });
+        new["assembling-machine"]["assembling-machine-2"]["crafting_speed"] = json!(1);
+        new
+    }
+
+    #[test]
+    fn reports_changes() {
+        let diff = DumpDiff::new(&old(), &new(), Difficulty::Normal);
+        assert_eq!(
+            diff.to_string(),
+            "\
+assembling-machine
+  ~ assembling-machine-2: crafting_speed 0.75 → 1
+recipe
+  ~ advanced-circuit: energy_required 6 → 8, plastic-bar 2 → 3
+  ~ copper-cable: icon (none) → \"__base__/copper-cable.png\"
+  + iron-chest
+  - wooden-chest
+"
+        );
+
+        let recipes = &diff.prototypes["recipe"];
+        assert!(!recipes["copper-cable"].is_significant());
+        assert!(recipes["advanced-circuit"].is_significant());
+        assert!(DumpDiff::new(&old(), &old(), Difficulty::Normal).is_empty());
+
+        // integral floats are equal to integers
+        let mut changes = Vec::new();
+        diff_values(
+            "",
+            &json!({"amount": 2}),
+            &json!({"amount": 2.0}),
+            &mut changes,
+        );
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn finds_affected_plans() {
+        let diff = DumpDiff::new(&old(), &new(), Difficulty::Normal);
+        let old = PrototypeDatabase::from_value(&old(), Difficulty::Normal).unwrap();
+        let new = PrototypeDatabase::from_value(&new(), Difficulty::Normal).unwrap();
+        let file = |item: &str| PlanFile {
+            data: "prototype-data".into(),
+            difficulty: Difficulty::Normal,
+            solver: None,
+            request: PlanRequest {
+                targets: BTreeMap::from([(item.into(), 1.0)]),
+                machines: BTreeMap::from([("crafting".into(), "assembling-machine-2".into())]),
+                ..Default::default()
+            },
+        };
+
+        let impact = diff.impact(&file("advanced-circuit"), &old, &new);
+        assert_eq!(impact.recipes, ["advanced-circuit"]);
+        assert_eq!(impact.entities, ["assembling-machine-2"]);
+        assert_eq!(impact.error, None);
+
+        let impact = diff.impact(&file("wooden-chest"), &old, &new);
+        assert_eq!(impact.recipes, ["wooden-chest"]);
+        assert!(impact.error.is_some());
+    }
+}
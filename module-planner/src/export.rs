@@ -0,0 +1,410 @@
+//! Tabular exports for spreadsheets.
+//!
+//! Recipes, machines and [`ProductionPlan`]s are flattened into [`Table`]s of
+//! one row per fact, such as one row per ingredient or product of a recipe,
+//! so that they can be filtered and pivoted. Each table can be written as
+//! CSV, and any number of tables as the worksheets of one
+//! [SpreadsheetML](https://learn.microsoft.com/en-us/previous-versions/office/developer/office-xp/aa140066(v=office.10))
+//! workbook, a flat XML format which both Excel and LibreOffice open.
+//!
+//! Rates are per second, and power is in watts.
+
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use crate::{
+    database::PrototypeDatabase, machine::EnergySource, number::Number, plan::ProductionPlan,
+    recipe::ItemKind,
+};
+
+/// One cell of a [`Table`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<u32> for Cell {
+    fn from(n: u32) -> Self {
+        Self::Number(n.into())
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<ItemKind> for Cell {
+    fn from(kind: ItemKind) -> Self {
+        Self::Text(match kind {
+            ItemKind::Item => "item".into(),
+            ItemKind::Fluid => "fluid".into(),
+        })
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Empty, Into::into)
+    }
+}
+
+impl Cell {
+    /// The cell as CSV text.
+    fn text(&self) -> String {
+        match self {
+            Self::Empty => String::new(),
+            Self::Text(text) => text.clone(),
+            Self::Number(n) => n.to_string(),
+            Self::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+/// A named table with a header row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// The name of the table, used for its worksheet or file.
+    pub name: &'static str,
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    fn new(name: &'static str, header: &[&'static str]) -> Self {
+        Self {
+            name,
+            header: header.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Write the table as CSV.
+    pub fn to_csv(&self) -> csv::Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.header)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(Cell::text))?;
+        }
+        writer.into_inner().map_err(|err| err.into_error().into())
+    }
+}
+
+/// Every ingredient and product of every recipe, one per row, with the rate
+/// at which a machine of crafting speed 1 consumes or produces it.
+pub fn recipe_table(database: &PrototypeDatabase) -> Table {
+    let mut table = Table::new(
+        "recipes",
+        &[
+            "recipe",
+            "category",
+            "enabled",
+            "hidden",
+            "seconds",
+            "role",
+            "item",
+            "kind",
+            "amount",
+            "probability",
+            "per_second",
+        ],
+    );
+    for recipe in database.recipes.values() {
+        let seconds = recipe.seconds::<f64>();
+        let per_second = |amount: f64| (seconds > 0.0).then(|| amount / seconds);
+        let row = |role: &str, item: &str, kind: ItemKind, amount: u32, probability: f64| {
+            vec![
+                recipe.name.as_str().into(),
+                recipe.category.as_str().into(),
+                recipe.enabled.into(),
+                recipe.hidden.into(),
+                seconds.into(),
+                role.into(),
+                item.into(),
+                kind.into(),
+                amount.into(),
+                probability.into(),
+                per_second(f64::from(amount) * probability).into(),
+            ]
+        };
+        for ingredient in &recipe.ingredients {
+            table.rows.push(row(
+                "ingredient",
+                &ingredient.name,
+                ingredient.kind,
+                ingredient.amount,
+                1.0,
+            ));
+        }
+        for product in &recipe.products {
+            table.rows.push(row(
+                "product",
+                &product.name,
+                product.kind,
+                product.amount,
+                product.probability,
+            ));
+        }
+    }
+    table
+}
+
+/// Every crafting machine, one per row.
+pub fn machine_table(database: &PrototypeDatabase) -> Table {
+    let mut table = Table::new(
+        "machines",
+        &[
+            "machine",
+            "type",
+            "crafting_speed",
+            "crafting_categories",
+            "energy_usage",
+            "energy_source",
+            "drain",
+            "module_slots",
+            "base_productivity",
+            "emissions_per_minute",
+        ],
+    );
+    for machine in database.machines.values() {
+        let (source, drain) = match &machine.energy_source {
+            EnergySource::Electric { drain } => ("electric", Some(*drain)),
+            EnergySource::Burner { .. } => ("burner", None),
+            EnergySource::Other(kind) => (kind.as_str(), None),
+        };
+        let categories = machine
+            .crafting_categories
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        table.rows.push(vec![
+            machine.name.as_str().into(),
+            machine.kind.as_str().into(),
+            machine.crafting_speed.into(),
+            categories.join(", ").into(),
+            machine.energy_usage.into(),
+            source.into(),
+            drain.into(),
+            machine.module_slots.into(),
+            machine.base_productivity.into(),
+            machine.emissions_per_minute.into(),
+        ]);
+    }
+    table
+}
+
+/// The tables of a plan: its recipe steps, the items each step consumes and
+/// produces, and the flow of every item.
+pub fn plan_tables<N: Number>(plan: &ProductionPlan<N>) -> [Table; 3] {
+    let mut steps = Table::new(
+        "steps",
+        &[
+            "recipe",
+            "machine",
+            "machines",
+            "crafts_per_second",
+            "speed",
+            "productivity",
+            "consumption",
+            "pollution",
+            "power",
+        ],
+    );
+    let mut step_items = Table::new(
+        "step-items",
+        &["recipe", "machine", "role", "item", "per_second"],
+    );
+    for step in plan.steps.values() {
+        let effects = &step.effects;
+        steps.rows.push(vec![
+            step.recipe.as_str().into(),
+            step.machine.as_str().into(),
+            step.machines.to_f64().into(),
+            step.crafts_per_second.to_f64().into(),
+            effects.speed.to_f64().into(),
+            effects.productivity.to_f64().into(),
+            effects.consumption.to_f64().into(),
+            effects.pollution.to_f64().into(),
+            step.power.to_f64().into(),
+        ]);
+
+        let inputs = step.inputs.iter().map(|flow| ("input", flow));
+        let outputs = step.outputs.iter().map(|flow| ("output", flow));
+        for (role, (item, rate)) in inputs.chain(outputs) {
+            step_items.rows.push(vec![
+                step.recipe.as_str().into(),
+                step.machine.as_str().into(),
+                role.into(),
+                item.as_str().into(),
+                rate.to_f64().into(),
+            ]);
+        }
+    }
+
+    let mut items = Table::new(
+        "items",
+        &[
+            "item",
+            "kind",
+            "produced",
+            "consumed",
+            "target",
+            "raw_input",
+            "surplus",
+        ],
+    );
+    for (item, flow) in &plan.flows {
+        let rate = |rates: &BTreeMap<String, N>| Cell::from(rates.get(item).map(Number::to_f64));
+        items.rows.push(vec![
+            item.as_str().into(),
+            flow.kind.into(),
+            flow.produced.to_f64().into(),
+            flow.consumed.to_f64().into(),
+            rate(&plan.targets),
+            rate(&plan.raw_inputs),
+            rate(&plan.surplus),
+        ]);
+    }
+
+    [steps, step_items, items]
+}
+
+/// Escape text for XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write tables as the worksheets of a SpreadsheetML workbook.
+pub fn spreadsheet(tables: &[Table]) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<?mso-application progid=\"Excel.Sheet\"?>\n",
+        "<Workbook xmlns=\"urn:schemas-microsoft-com:office:spreadsheet\"",
+        " xmlns:ss=\"urn:schemas-microsoft-com:office:spreadsheet\">\n",
+    ));
+    for table in tables {
+        writeln!(xml, " <Worksheet ss:Name=\"{}\">", escape(table.name)).unwrap();
+        xml.push_str("  <Table>\n");
+        let header = table.header.iter().map(|&cell| Cell::from(cell)).collect();
+        for row in std::iter::once(&header).chain(&table.rows) {
+            xml.push_str("   <Row>");
+            for cell in row {
+                match cell {
+                    Cell::Empty => xml.push_str("<Cell/>"),
+                    Cell::Number(n) if !n.is_finite() => xml.push_str("<Cell/>"),
+                    Cell::Number(n) => {
+                        write!(xml, "<Cell><Data ss:Type=\"Number\">{n}</Data></Cell>").unwrap()
+                    }
+                    Cell::Bool(b) => write!(
+                        xml,
+                        "<Cell><Data ss:Type=\"Boolean\">{}</Data></Cell>",
+                        u8::from(*b)
+                    )
+                    .unwrap(),
+                    Cell::Text(text) => write!(
+                        xml,
+                        "<Cell><Data ss:Type=\"String\">{}</Data></Cell>",
+                        escape(text)
+                    )
+                    .unwrap(),
+                }
+            }
+            xml.push_str("</Row>\n");
+        }
+        xml.push_str("  </Table>\n </Worksheet>\n");
+    }
+    xml.push_str("</Workbook>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::{tests::database, PlanRequest};
+
+    #[test]
+    fn exports_recipes_and_machines() {
+        let database = database();
+
+        let recipes = recipe_table(&database);
+        let csv = String::from_utf8(recipes.to_csv().unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("recipe,category,enabled,hidden,seconds,role,item,kind,amount,probability,per_second")
+        );
+        assert!(csv.contains(
+            "\nelectronic-circuit,crafting,true,false,0.5,ingredient,copper-cable,item,3,1,6\n"
+        ));
+        assert!(csv.contains(
+            "\nadvanced-oil-processing,oil-processing,true,false,5,product,heavy-oil,fluid,25,1,5\n"
+        ));
+
+        let machines = machine_table(&database);
+        let row = machines
+            .rows
+            .iter()
+            .find(|row| row[0] == Cell::from("assembling-machine-2"))
+            .unwrap();
+        assert_eq!(row[2], Cell::Number(0.75));
+        assert_eq!(row[5], Cell::from("electric"));
+    }
+
+    #[test]
+    fn exports_plans() {
+        let request = PlanRequest {
+            targets: BTreeMap::from([("electronic-circuit".into(), 45.0)]),
+            machines: BTreeMap::from([("crafting".into(), "assembling-machine-2".into())]),
+            ..Default::default()
+        };
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        let [steps, step_items, items] = plan_tables(&plan);
+        assert_eq!(steps.rows.len(), 2);
+        assert_eq!(steps.rows[1][0], Cell::from("electronic-circuit"));
+        assert_eq!(step_items.rows.len(), 5);
+
+        let row = |table: &Table, item: &str| {
+            table
+                .rows
+                .iter()
+                .find(|row| row[0] == Cell::from(item))
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(row(&items, "electronic-circuit")[4], Cell::Number(45.0));
+        assert_eq!(row(&items, "iron-plate")[4], Cell::Empty);
+        assert_eq!(row(&items, "iron-plate")[5], Cell::Number(45.0));
+
+        let xml = spreadsheet(&[steps, items]);
+        assert!(xml.contains(" <Worksheet ss:Name=\"steps\">\n"));
+        assert!(xml.contains(
+            "<Row><Cell><Data ss:Type=\"String\">iron-plate</Data></Cell><Cell><Data ss:Type=\"String\">item</Data></Cell>"
+        ));
+        assert!(xml.ends_with("</Workbook>\n"));
+    }
+}
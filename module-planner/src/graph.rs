@@ -0,0 +1,609 @@
+//! Recipe dependency graph.
+//!
+//! A [`RecipeGraph`] is a bipartite graph: item nodes link to the recipes
+//! which consume them, and recipe nodes link to the items they produce. Edges
+//! are weighted by the amount consumed or (expected) amount produced per craft.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::recipe::{ItemKind, NormalizedRecipe};
+
+/// Opaque handle to a node in a [`RecipeGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Item { name: String, kind: ItemKind },
+    Recipe { name: String },
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Item { name, .. } | Node::Recipe { name } => name,
+        }
+    }
+
+    pub fn is_item(&self) -> bool {
+        matches!(self, Node::Item { .. })
+    }
+
+    pub fn is_recipe(&self) -> bool {
+        matches!(self, Node::Recipe { .. })
+    }
+}
+
+/// A weighted, directed edge.
+///
+/// Item to recipe edges carry the amount of the item consumed per craft.
+/// Recipe to item edges carry the expected amount of the item produced per
+/// craft.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edge {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RecipeGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+    items: HashMap<String, NodeId>,
+    recipes: HashMap<String, NodeId>,
+    barreling: BTreeSet<NodeId>,
+}
+
+impl RecipeGraph {
+    /// Build a graph from a collection of recipes.
+    ///
+    /// Node ids are assigned in iteration order, so a deterministic input
+    /// order produces a deterministic graph.
+    pub fn new<'a>(recipes: impl IntoIterator<Item = &'a NormalizedRecipe>) -> Self {
+        let mut graph = Self::default();
+
+        for recipe in recipes {
+            let recipe_id = graph.add_node(Node::Recipe {
+                name: recipe.name.clone(),
+            });
+            graph.recipes.insert(recipe.name.clone(), recipe_id);
+            if recipe.is_barreling() {
+                graph.barreling.insert(recipe_id);
+            }
+
+            for ingredient in &recipe.ingredients {
+                let item_id = graph.item_node(&ingredient.name, ingredient.kind);
+                graph.add_edge(item_id, recipe_id, ingredient.amount as f64);
+            }
+            for product in &recipe.products {
+                let item_id = graph.item_node(&product.name, product.kind);
+                graph.add_edge(recipe_id, item_id, product.expected_amount());
+            }
+        }
+
+        graph
+    }
+
+    fn add_node(&mut self, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(node);
+        self.outgoing.push(Vec::new());
+        self.incoming.push(Vec::new());
+        id
+    }
+
+    fn item_node(&mut self, name: &str, kind: ItemKind) -> NodeId {
+        if let Some(id) = self.items.get(name) {
+            return *id;
+        }
+        let id = self.add_node(Node::Item {
+            name: name.into(),
+            kind,
+        });
+        self.items.insert(name.into(), id);
+        id
+    }
+
+    fn add_edge(&mut self, from: NodeId, to: NodeId, amount: f64) {
+        let idx = self.edges.len();
+        self.edges.push(Edge { from, to, amount });
+        self.outgoing[from.0].push(idx);
+        self.incoming[to.0].push(idx);
+    }
+
+    /// Find the node for an item or fluid by name.
+    pub fn item(&self, name: &str) -> Option<NodeId> {
+        self.items.get(name).copied()
+    }
+
+    /// Find the node for a recipe by name.
+    pub fn recipe(&self, name: &str) -> Option<NodeId> {
+        self.recipes.get(name).copied()
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// Iterate over all nodes in id order.
+    pub fn nodes(&self) -> impl '_ + Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| (NodeId(idx), node))
+    }
+
+    /// Edges leaving this node.
+    pub fn outgoing(&self, id: NodeId) -> impl '_ + Iterator<Item = &Edge> {
+        self.outgoing[id.0].iter().map(|&idx| &self.edges[idx])
+    }
+
+    /// Edges entering this node.
+    pub fn incoming(&self, id: NodeId) -> impl '_ + Iterator<Item = &Edge> {
+        self.incoming[id.0].iter().map(|&idx| &self.edges[idx])
+    }
+
+    /// Names of all recipes which produce the named item.
+    pub fn producers(&self, item: &str) -> impl '_ + Iterator<Item = &str> {
+        self.item(item)
+            .into_iter()
+            .flat_map(|id| self.incoming(id))
+            .map(|edge| self.node(edge.from).name())
+    }
+
+    /// Names of all recipes which consume the named item.
+    pub fn consumers(&self, item: &str) -> impl '_ + Iterator<Item = &str> {
+        self.item(item)
+            .into_iter()
+            .flat_map(|id| self.outgoing(id))
+            .map(|edge| self.node(edge.to).name())
+    }
+
+    fn reachable(&self, start: NodeId, forward: bool) -> BTreeSet<NodeId> {
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            let neighbors: Box<dyn Iterator<Item = NodeId>> = if forward {
+                Box::new(self.outgoing(id).map(|edge| edge.to))
+            } else {
+                Box::new(self.incoming(id).map(|edge| edge.from))
+            };
+            for neighbor in neighbors {
+                if seen.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        seen.remove(&start);
+        seen
+    }
+
+    /// All nodes from which this node can be reached: every recipe and item
+    /// which could contribute to it.
+    pub fn upstream(&self, id: NodeId) -> BTreeSet<NodeId> {
+        self.reachable(id, false)
+    }
+
+    /// All nodes reachable from this node: every recipe and item to which it
+    /// could contribute.
+    pub fn downstream(&self, id: NodeId) -> BTreeSet<NodeId> {
+        self.reachable(id, true)
+    }
+
+    /// Compute the strongly connected components of this graph.
+    ///
+    /// Components are emitted in reverse topological order: if any edge leads
+    /// from component `a` to component `b`, then `b` precedes `a`. Members of
+    /// each component are sorted by id.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        // iterative implementation of Tarjan's algorithm; production chains can
+        // be deep enough that recursion is unpleasant
+        const UNVISITED: usize = usize::MAX;
+
+        let node_count = self.nodes.len();
+        let mut index = vec![UNVISITED; node_count];
+        let mut lowlink = vec![0; node_count];
+        let mut on_stack = vec![false; node_count];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut components = Vec::new();
+
+        for root in 0..node_count {
+            if index[root] != UNVISITED {
+                continue;
+            }
+
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut call_stack = vec![(root, 0)];
+
+            while let Some(frame) = call_stack.last_mut() {
+                let node = frame.0;
+                if let Some(&edge) = self.outgoing[node].get(frame.1) {
+                    frame.1 += 1;
+                    let successor = self.edges[edge].to.0;
+                    if index[successor] == UNVISITED {
+                        index[successor] = next_index;
+                        lowlink[successor] = next_index;
+                        next_index += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        call_stack.push((successor, 0));
+                    } else if on_stack[successor] {
+                        lowlink[node] = lowlink[node].min(index[successor]);
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut component = Vec::new();
+                    loop {
+                        let member = stack.pop().expect("node is on the stack");
+                        on_stack[member] = false;
+                        component.push(NodeId(member));
+                        if member == node {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Find all cycles in this graph.
+    ///
+    /// Each cycle is a strongly connected component of more than one node:
+    /// for example, kovarex enrichment together with `uranium-235`, or a
+    /// barrel filling and emptying loop. Cycles are sorted by their first
+    /// member.
+    pub fn cycles(&self) -> Vec<Vec<NodeId>> {
+        let mut cycles = self
+            .strongly_connected_components()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect::<Vec<_>>();
+        cycles.sort();
+        cycles
+    }
+
+    /// Partition the graph into topological layers.
+    ///
+    /// Layer 0 contains the nodes with no inputs; every other node is placed
+    /// one layer after its deepest input. Nodes which participate in a cycle
+    /// share a layer with the whole cycle, so the layering is a topological
+    /// order of the acyclic condensation of the graph.
+    pub fn layers(&self) -> Vec<Vec<NodeId>> {
+        let components = self.strongly_connected_components();
+        let component_of = self.component_of(&components);
+
+        // reverse tarjan order is a topological order, so every predecessor
+        // of a component is finalized before the component itself
+        let mut depth = vec![0; components.len()];
+        for component_idx in (0..components.len()).rev() {
+            for member in &components[component_idx] {
+                for edge in self.outgoing(*member) {
+                    let successor = component_of[edge.to.0];
+                    if successor != component_idx {
+                        depth[successor] = depth[successor].max(depth[component_idx] + 1);
+                    }
+                }
+            }
+        }
+
+        let layer_count = depth.iter().max().map_or(0, |max| max + 1);
+        let mut layers = vec![Vec::new(); layer_count];
+        for (idx, component_idx) in component_of.into_iter().enumerate() {
+            layers[depth[component_idx]].push(NodeId(idx));
+        }
+        layers
+    }
+
+    /// The index of the component containing each node.
+    fn component_of(&self, components: &[Vec<NodeId>]) -> Vec<usize> {
+        let mut component_of = vec![0; self.nodes.len()];
+        for (component_idx, members) in components.iter().enumerate() {
+            for member in members {
+                component_of[member.0] = component_idx;
+            }
+        }
+        component_of
+    }
+
+    /// Names of all raw inputs for the named item.
+    ///
+    /// Raw inputs are items upstream of the requested item which no recipe
+    /// usefully produces. A producer does not count when it sits in the same
+    /// cycle as the recipe consuming the item, as emptying a water barrel does
+    /// for the water which fills it, nor when it empties a fluid from a barrel:
+    /// such fluids must still come from outside. If the requested item is
+    /// itself raw, it is its own sole raw input. Unknown items have no raw
+    /// inputs.
+    pub fn raw_inputs(&self, item: &str) -> BTreeSet<&str> {
+        let Some(id) = self.item(item) else {
+            return BTreeSet::new();
+        };
+
+        let component_of = self.component_of(&self.strongly_connected_components());
+        let mut raw = BTreeSet::new();
+        // whether an item is raw depends on the cycle of the recipe consuming
+        // it, so an item is visited once per consuming cycle
+        let mut seen = BTreeSet::from([(id, None)]);
+        let mut queue = VecDeque::from([(id, None)]);
+        while let Some((item, consumer_component)) = queue.pop_front() {
+            let is_fluid = matches!(
+                self.node(item),
+                Node::Item {
+                    kind: ItemKind::Fluid,
+                    ..
+                }
+            );
+            let producers = self
+                .incoming(item)
+                .map(|edge| edge.from)
+                .filter(|producer| !(is_fluid && self.barreling.contains(producer)))
+                .filter(|producer| Some(component_of[producer.0]) != consumer_component)
+                .collect::<Vec<_>>();
+            if producers.is_empty() {
+                raw.insert(self.node(item).name());
+            }
+            for producer in producers {
+                let consumer_component = Some(component_of[producer.0]);
+                for edge in self.incoming(producer) {
+                    if seen.insert((edge.from, consumer_component)) {
+                        queue.push_back((edge.from, consumer_component));
+                    }
+                }
+            }
+        }
+        raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+    use crate::recipe::{Ingredient, Product};
+
+    fn recipe(
+        name: &str,
+        ingredients: &[(&str, ItemKind, u32)],
+        products: &[(&str, ItemKind, u32)],
+    ) -> NormalizedRecipe {
+        NormalizedRecipe {
+            name: name.into(),
+            category: "crafting".into(),
+            subgroup: None,
+            enabled: true,
+            hidden: false,
+            duration: Duration::milliseconds(500),
+            emissions_multiplier: 1.0,
+            ingredients: ingredients
+                .iter()
+                .map(|&(name, kind, amount)| Ingredient {
+                    name: name.into(),
+                    kind,
+                    amount,
+                })
+                .collect(),
+            products: products
+                .iter()
+                .map(|&(name, kind, amount)| Product {
+                    name: name.into(),
+                    kind,
+                    amount,
+                    probability: 1.0,
+                })
+                .collect(),
+        }
+    }
+
+    fn graph() -> RecipeGraph {
+        use ItemKind::{Fluid, Item};
+
+        let recipes = [
+            recipe(
+                "iron-gear-wheel",
+                &[("iron-plate", Item, 2)],
+                &[("iron-gear-wheel", Item, 1)],
+            ),
+            recipe(
+                "copper-cable",
+                &[("copper-plate", Item, 1)],
+                &[("copper-cable", Item, 2)],
+            ),
+            recipe(
+                "electronic-circuit",
+                &[("iron-plate", Item, 1), ("copper-cable", Item, 3)],
+                &[("electronic-circuit", Item, 1)],
+            ),
+            recipe(
+                "kovarex-enrichment-process",
+                &[("uranium-235", Item, 40), ("uranium-238", Item, 5)],
+                &[("uranium-235", Item, 41), ("uranium-238", Item, 2)],
+            ),
+            recipe(
+                "uranium-processing",
+                &[("uranium-ore", Item, 10)],
+                &[("uranium-235", Item, 1), ("uranium-238", Item, 1)],
+            ),
+            recipe(
+                "empty-barrel",
+                &[("steel-plate", Item, 1)],
+                &[("empty-barrel", Item, 1)],
+            ),
+            recipe(
+                "fill-water-barrel",
+                &[("empty-barrel", Item, 1), ("water", Fluid, 50)],
+                &[("water-barrel", Item, 1)],
+            ),
+            recipe(
+                "empty-water-barrel",
+                &[("water-barrel", Item, 1)],
+                &[("empty-barrel", Item, 1), ("water", Fluid, 50)],
+            ),
+        ];
+        RecipeGraph::new(&recipes)
+    }
+
+    fn names<'a>(graph: &'a RecipeGraph, ids: &[NodeId]) -> BTreeSet<&'a str> {
+        ids.iter().map(|&id| graph.node(id).name()).collect()
+    }
+
+    #[test]
+    fn raw_inputs() {
+        let graph = graph();
+        assert_eq!(
+            graph.raw_inputs("electronic-circuit"),
+            BTreeSet::from(["copper-plate", "iron-plate"])
+        );
+        assert_eq!(
+            graph.raw_inputs("iron-plate"),
+            BTreeSet::from(["iron-plate"])
+        );
+        assert_eq!(
+            graph.raw_inputs("water-barrel"),
+            BTreeSet::from(["steel-plate", "water"])
+        );
+        assert_eq!(graph.raw_inputs("water"), BTreeSet::from(["water"]));
+        assert_eq!(
+            graph.raw_inputs("uranium-235"),
+            BTreeSet::from(["uranium-ore"])
+        );
+        assert!(graph.raw_inputs("unobtainium").is_empty());
+    }
+
+    #[test]
+    fn raw_inputs_of_self_sustaining_cycles() {
+        use ItemKind::Item;
+
+        // seeds grow only from seeds, so the first must come from outside,
+        // however the search first reaches them
+        let recipes = [
+            recipe("plant", &[("seed", Item, 1)], &[("tree", Item, 1)]),
+            recipe("grow", &[("seed", Item, 1)], &[("seed", Item, 2)]),
+        ];
+        let graph = RecipeGraph::new(&recipes);
+        assert_eq!(graph.raw_inputs("tree"), BTreeSet::from(["seed"]));
+    }
+
+    #[test]
+    fn producers_and_consumers() {
+        let graph = graph();
+        assert_eq!(
+            graph.producers("uranium-235").collect::<BTreeSet<_>>(),
+            BTreeSet::from(["kovarex-enrichment-process", "uranium-processing"])
+        );
+        assert_eq!(
+            graph.consumers("iron-plate").collect::<BTreeSet<_>>(),
+            BTreeSet::from(["electronic-circuit", "iron-gear-wheel"])
+        );
+    }
+
+    #[test]
+    fn edge_weights() {
+        let graph = graph();
+        let circuit = graph.recipe("electronic-circuit").unwrap();
+        let cable = graph.item("copper-cable").unwrap();
+        let edge = graph
+            .incoming(circuit)
+            .find(|edge| edge.from == cable)
+            .unwrap();
+        assert_eq!(edge.amount, 3.0);
+    }
+
+    #[test]
+    fn traversal() {
+        let graph = graph();
+        let circuit = graph.item("electronic-circuit").unwrap();
+        let upstream = graph.upstream(circuit).into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            names(&graph, &upstream),
+            BTreeSet::from([
+                "copper-cable",
+                "copper-plate",
+                "electronic-circuit",
+                "iron-plate"
+            ])
+        );
+
+        let iron = graph.item("iron-plate").unwrap();
+        let downstream = graph.downstream(iron).into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            names(&graph, &downstream),
+            BTreeSet::from(["electronic-circuit", "iron-gear-wheel"])
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let graph = graph();
+        let cycles = graph
+            .cycles()
+            .iter()
+            .map(|cycle| names(&graph, cycle))
+            .collect::<Vec<_>>();
+
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles.contains(&BTreeSet::from([
+            "kovarex-enrichment-process",
+            "uranium-235",
+            "uranium-238"
+        ])));
+        assert!(cycles.contains(&BTreeSet::from([
+            "empty-barrel",
+            "empty-water-barrel",
+            "fill-water-barrel",
+            "water",
+            "water-barrel"
+        ])));
+    }
+
+    #[test]
+    fn layers() {
+        let graph = graph();
+        let layers = graph.layers();
+        let layer_of = |id: NodeId| layers.iter().position(|layer| layer.contains(&id)).unwrap();
+        let item_layer = |name: &str| layer_of(graph.item(name).unwrap());
+        let recipe_layer = |name: &str| layer_of(graph.recipe(name).unwrap());
+
+        assert_eq!(item_layer("iron-plate"), 0);
+        assert_eq!(item_layer("copper-plate"), 0);
+        assert_eq!(recipe_layer("copper-cable"), 1);
+        assert_eq!(item_layer("copper-cable"), 2);
+        assert_eq!(item_layer("electronic-circuit"), 4);
+        assert_eq!(
+            item_layer("uranium-235"),
+            recipe_layer("kovarex-enrichment-process")
+        );
+        assert!(recipe_layer("uranium-processing") < item_layer("uranium-235"));
+
+        // every edge between distinct components points to a later layer
+        let cycles = graph.cycles();
+        for (id, _) in graph.nodes() {
+            for edge in graph.outgoing(id) {
+                let same_cycle = cycles
+                    .iter()
+                    .any(|cycle| cycle.contains(&edge.from) && cycle.contains(&edge.to));
+                if !same_cycle {
+                    assert!(layer_of(edge.from) < layer_of(edge.to));
+                }
+            }
+        }
+    }
+}
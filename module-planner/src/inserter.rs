@@ -0,0 +1,91 @@
+//! Inserter throughput.
+
+use data_parser::models;
+use serde::{Deserialize, Serialize};
+
+use crate::number::Number;
+
+/// The number of items each inserter picks up per swing, which research
+/// increases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandSize {
+    pub inserter: u32,
+    /// The hand size of inserters with the `stack` flag.
+    pub stack_inserter: u32,
+}
+
+impl Default for HandSize {
+    /// Hand sizes before any research.
+    fn default() -> Self {
+        Self {
+            inserter: 1,
+            stack_inserter: 2,
+        }
+    }
+}
+
+/// An inserter tier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inserter {
+    pub name: String,
+    /// Rotation speed in revolutions per tick.
+    pub rotation_speed: f64,
+    /// Extension speed in tiles per tick.
+    pub extension_speed: f64,
+    /// Whether this inserter benefits from stack inserter capacity bonuses.
+    pub stack: bool,
+}
+
+impl Inserter {
+    pub fn new(inserter: &models::Inserter) -> Self {
+        Self {
+            name: inserter.name.clone(),
+            rotation_speed: inserter.rotation_speed,
+            extension_speed: inserter.extension_speed,
+            stack: inserter.stack,
+        }
+    }
+
+    /// The number of items this inserter picks up per swing.
+    pub fn hand_size(&self, hand_size: HandSize) -> u32 {
+        if self.stack {
+            hand_size.stack_inserter
+        } else {
+            hand_size.inserter
+        }
+    }
+
+    /// The number of items moved per second, carrying `stack_size` items per
+    /// swing.
+    ///
+    /// This is the chest-to-chest rate, limited by rotation alone: a swing is
+    /// half a revolution each way. Picking up from a belt, or waiting on a
+    /// machine's output, is slower.
+    pub fn items_per_second<N: Number>(&self, stack_size: u32) -> N {
+        N::from_u32(stack_size) * N::from_f64(self.rotation_speed) * N::from_u32(60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::Rational;
+
+    #[test]
+    fn chest_to_chest() {
+        let inserter = Inserter {
+            name: "fast-inserter".into(),
+            rotation_speed: 0.04,
+            extension_speed: 0.07,
+            stack: false,
+        };
+        assert_eq!(
+            inserter.items_per_second::<Rational>(1),
+            Rational::new(12, 5)
+        );
+        assert_eq!(
+            inserter.items_per_second::<Rational>(12),
+            Rational::new(144, 5)
+        );
+    }
+}
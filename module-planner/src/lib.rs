@@ -0,0 +1,430 @@
+//! # Factorio Recipe Planner: Module Planner
+//!
+//! This crate builds higher-level models on top of the low-level prototype
+//! models in `data_parser`, and uses them to reason about factory modules.
+//!
+//! ## [`recipe`] module
+//!
+//! The [`NormalizedRecipe`][recipe::NormalizedRecipe] struct resolves the
+//! representational variance of Factorio recipes: `result` versus `results`,
+//! simple versus typed ingredients, and normal versus expensive difficulty.
+//!
+//! ## [`graph`] module
+//!
+//! The [`RecipeGraph`][graph::RecipeGraph] struct is a bipartite graph of items
+//! and recipes, weighted by the amounts consumed and produced. It supports
+//! upstream and downstream traversal, cycle detection (kovarex enrichment,
+//! coal liquefaction, barrel fill/empty loops), topological layering, and
+//! finding the raw inputs of any item.
+//!
+//! ## [`database`] module
+//!
+//! The [`PrototypeDatabase`][database::PrototypeDatabase] struct loads a data
+//! dump and holds the normalized prototypes extracted from it.
+//!
+//! ## [`plan`] module
+//!
+//! The [`ProductionPlan`][plan::ProductionPlan] struct is the core of the
+//! planner. Given target rates (e.g. 45 electronic circuits per second), a
+//! recipe choice per item and a machine per recipe category, it computes the
+//! craft rate and machine count of every recipe, the flow of every
+//! intermediate, and the rate at which raw inputs are required. Items may be
+//! marked as supplied from outside the plan, such as circuits from a main
+//! bus: the chain stops there, and the required supply rate is reported,
+//! optionally bounded by a limit. Plans are serializable, so they can be fed
+//! into other tools.
+//!
+//! ## [`policy`] module
+//!
+//! Many items have more than one producing recipe: solid fuel from light oil,
+//! heavy oil or petroleum gas, or the alternatives added by mods. A
+//! [`RecipePolicy`][policy::RecipePolicy] makes the choice deterministic:
+//! explicit per-item recipe choices, blacklists of individual recipes and of
+//! barreling, hidden and voiding recipes, and preferences for recipes enabled
+//! at the start of the game or with the least raw-resource cost. Plans, the
+//! solver, cost tables and production chains all consume it.
+//!
+//! ## [`plan_file`] module
+//!
+//! A [`PlanFile`][plan_file::PlanFile] stores a factory module design in
+//! TOML, YAML or JSON, so that it can live in a repository, be reviewed, and
+//! be re-planned when mod versions change. It names the data dump and
+//! difficulty it was designed against alongside the request: targets,
+//! machines per category, module and beacon loadouts, supplied inputs with
+//! optional limits, the recipe policy, and optionally solver options. Every
+//! name it uses is validated against the database before planning.
+//!
+//! ## [`modules`] module
+//!
+//! A [`PlanRequest`][plan::PlanRequest] may give each recipe a
+//! [`ModuleLoadout`][modules::ModuleLoadout]: the modules in each machine, and
+//! the beacons affecting it. Their speed, productivity, consumption and
+//! pollution effects are applied with Factorio's clamps, after checking module
+//! slots, `allowed_effects` and module `limitation`s.
+//! [`ModuleImpact`][modules::ModuleImpact] reports how modules change machine
+//! counts, power and raw inputs compared with the same plan without them.
+//!
+//! ## [`power`] module
+//!
+//! A [`PowerReport`][power::PowerReport] totals what a plan costs to run, per
+//! recipe and overall: electric power including each machine's drain and its
+//! beacons, the fuel burned by burner machines in each fuel category, and
+//! pollution per minute after module effects and each recipe's
+//! `emissions_multiplier`. It also counts the steam engines or solar panels
+//! needed to supply the electric load.
+//!
+//! ## [`belt`] module
+//!
+//! A [`BeltPlan`][belt::BeltPlan] expresses targets as full belts or lanes of
+//! a given `transport-belt` tier rather than items per second: what you'll
+//! need to fill one belt. Alongside the plan, it reports how many belts of
+//! each raw input are needed, and which intermediates flow faster than one
+//! belt can carry.
+//!
+//! ## [`solver`] module
+//!
+//! Multi-output recipes such as oil and uranium processing cannot be balanced
+//! by walking the recipe tree. [`ProductionPlan::solve`][plan::ProductionPlan::solve]
+//! instead formulates the plan as a linear program, solved by the pure-Rust
+//! simplex implementation in [`simplex`]. It chooses among alternative
+//! recipes to minimize raw resources, machines, or power; treats byproducts
+//! either as sinks or as items which must be consumed; and, when no plan is
+//! feasible, explains which item balances could not be met and which supply
+//! limits bind.
+//!
+//! ## [`cost`] module
+//!
+//! A [`CostTable`][cost::CostTable] values every item in the dump by the raw
+//! resources and total crafting time needed to produce one unit, choosing the
+//! cheapest recipe wherever there is a choice. Catalyst loops such as kovarex
+//! enrichment are netted out. Costs compare alternative recipes, and show
+//! what is cheapest to ship by train.
+//!
+//! ## [`number`] module
+//!
+//! Planning and solving are generic over a [`Number`][number::Number] type.
+//! `f64` is the fast default; [`Rational`][number::Rational] is an
+//! arbitrary-precision alternative which yields exact ratios, displayed as
+//! mixed numbers such as `7 1/2` machines. Recipe durations, probabilities and
+//! crafting speeds are converted from the data dump exactly.
+//!
+//! ```ignore
+//! let plan = ProductionPlan::<Rational>::new(&database, &request)?;
+//! ```
+//!
+//! ## [`ratio`] module
+//!
+//! The [`BalancedBuild`][ratio::BalancedBuild] struct finds the smallest
+//! whole machine counts at which a chain is exactly balanced, with every
+//! machine running at 100%, and the output rate which results. This is the
+//! building block of tileable designs; it may be bounded by a total machine
+//! count or by the number of belts any item may occupy.
+//!
+//! ## [`report`] module
+//!
+//! A [`MachineReport`][report::MachineReport] describes one machine crafting a
+//! recipe with a given module loadout: crafts per second, the expected rate of
+//! each product, ingredient consumption, power draw including drain,
+//! pollution per minute, and how many inserters of each type keep it fed.
+//!
+//! ## [`statistics`] module
+//!
+//! Plan rates of probabilistic products are expected values, but uranium-235
+//! at 0.7% per craft arrives in bursts. [`OutputStatistics`][statistics::OutputStatistics]
+//! treats each product's rolls over a time window as a binomial distribution,
+//! and reports its standard deviation, the counts reached with a given
+//! confidence (at least 1 uranium-235 in 10 minutes from 10 centrifuges, 95%
+//! of the time), and the buffer which smooths the output for a consumer
+//! drawing the expected rate.
+//!
+//! ## [`simulation`] module
+//!
+//! Steady-state rates hide startup time, inserter batching and starved
+//! machines. A [`Simulation`][simulation::Simulation] runs a plan tick by
+//! tick, with whole machines, input and output buffers, belts of limited
+//! capacity and throughput, and probabilistic products rolled by a seeded
+//! random number generator. Its [`SimulationReport`][simulation::SimulationReport]
+//! gives the time taken to reach steady state, the utilization of every
+//! machine, and how long each spent starved of an ingredient or blocked by a
+//! product, so a block can be checked before it is built.
+//!
+//! ## [`design`] module
+//!
+//! A [`Design`][design::Design] is a plan file being edited interactively:
+//! each change to its targets, recipes, machines or modules re-plans it and
+//! refreshes a [`Summary`][design::Summary] of the production tree, machine
+//! counts, input belts and power. When the plan stops at an ambiguous recipe,
+//! the design exposes the choice to be made rather than just the error.
+//!
+//! ## [`repl`] module
+//!
+//! A [`Session`][repl::Session] answers one-line questions about a loaded
+//! database: what a recipe needs and where it is made, which recipes make or
+//! use an item, what a technology unlocks, and what it takes to make an item
+//! at a given rate. Machine and recipe choices made with `use` persist for the
+//! rest of the session.
+//!
+//! ## [`diff`] module
+//!
+//! A [`DumpDiff`][diff::DumpDiff] lists the prototypes added, removed and
+//! changed between two data dumps. Recipe changes are also compared in their
+//! normalized form, so a recipe that moves from `result` to `results` without
+//! changing what it makes does not affect any plan. The diff can say which
+//! changed recipes and machines a plan file uses, and whether it still plans.
+//!
+//! ## [`export`] module
+//!
+//! Recipes, machines and production plans can be flattened into
+//! [`Table`][export::Table]s of one row per fact, such as one row per
+//! ingredient or product of a recipe. Tables can be written as CSV, or together
+//! as the worksheets of a SpreadsheetML workbook which Excel and LibreOffice
+//! open.
+//!
+//! ## `graph` script
+//!
+//! This script emits the production chain of an item as a Graphviz DOT or
+//! Mermaid graph.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin graph -- prototype-data advanced-circuit --depth 3 --format mermaid
+//! ```
+//!
+//! Recipe nodes are annotated with their duration and category, and edges with
+//! the amounts consumed or produced per craft. Fluids are styled distinctly.
+//! Barreling recipes can be collapsed with `--collapse-barreling`, hidden
+//! recipes omitted with `--hide-hidden`, and the chain cut at arbitrary items
+//! with `--raw`. Where an item has several recipes, the chain may be narrowed
+//! with `--recipe ITEM=RECIPE`, `--exclude`, `--prefer-enabled` and
+//! `--prefer-cheapest`.
+//!
+//! ## `plan` script
+//!
+//! This script plans the module described by a plan file, and writes the
+//! resulting [`ProductionPlan`][plan::ProductionPlan] as JSON, YAML or TOML.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin plan -- modules/red-circuits.toml --exact --format yaml
+//! ```
+//!
+//! The data dump named by the file may be overridden with `--data`, and
+//! `--check` only validates the file against it.
+//!
+//! ## `designer` script
+//!
+//! This script is a terminal interface for designing a module from scratch or
+//! from an existing plan file, and saving it as a plan file.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin designer -- prototype-data --output modules/red-circuits.toml
+//! ```
+//!
+//! Targets are found by fuzzy search; recipes, machines, modules and beacons
+//! are chosen from lists for the selected step of the production tree. The
+//! help line at the bottom of the screen lists the keys.
+//!
+//! ## `factorio-repl` script
+//!
+//! This script loads a data dump once, then answers [`repl`] commands as they
+//! are typed, with tab completion of prototype names and persistent history.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin factorio-repl -- prototype-data
+//! > recipe copper-cable
+//! > use assembling-machine-2
+//! > rate electronic-circuit 30/s
+//! ```
+//!
+//! Commands may also be given with `-c` to run them without a prompt.
+//!
+//! ## `diff` script
+//!
+//! This script reports what changed between two data dumps, such as before and
+//! after a mod update, and which plan files the changes affect.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin diff -- old-prototype-data new-prototype-data --plans modules
+//! ```
+//!
+//! ## `into-csv` script
+//!
+//! This script exports the recipes and machines of a data dump, or the
+//! production plan of a plan file, as CSV or as a spreadsheet. On its own, it
+//! writes the recipe table as CSV to stdout.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin into-csv -- prototype-data > recipes.csv
+//! cargo run --bin into-csv -- prototype-data --plan modules/red-circuits.toml plan.xml --format spreadsheet
+//! ```
+//!
+//! ## `cost` script
+//!
+//! This script prints a [`CostTable`][cost::CostTable] as JSON or CSV.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin cost -- prototype-data --item advanced-circuit --format csv
+//! ```
+//!
+//! Recipes may be pinned with `--recipe ITEM=RECIPE`, excluded with
+//! `--exclude` or narrowed with `--prefer-enabled`, and items treated as raw
+//! with `--raw`.
+//!
+//! ## `machine-report` script
+//!
+//! This script prints a [`MachineReport`][report::MachineReport] as text or
+//! JSON.
+//!
+//! Example usage:
+//!
+//! ```sh
+//! cargo run --bin machine-report -- prototype-data electronic-circuit \
+//!     --machine assembling-machine-3 --module productivity-module \
+//!     --beacon 8:beacon:speed-module,speed-module --exact
+//! ```
+
+pub mod belt;
+pub mod chain;
+pub mod cost;
+pub mod database;
+pub mod design;
+pub mod diff;
+pub mod export;
+pub mod graph;
+pub mod inserter;
+pub mod machine;
+pub mod modules;
+pub mod number;
+pub mod plan;
+pub mod plan_file;
+pub mod policy;
+pub mod power;
+pub mod ratio;
+pub mod recipe;
+pub mod repl;
+pub mod report;
+pub mod simplex;
+pub mod simulation;
+pub mod solver;
+pub mod statistics;
+pub mod technology;
+pub mod units;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("loading data dump")]
+    DataParser(#[from] data_parser::Error),
+    #[error("parsing {kind} prototype \"{name}\"")]
+    Prototype {
+        kind: String,
+        name: String,
+        #[source]
+        inner: serde_json::Error,
+    },
+    #[error("reading {}", .path.display())]
+    ReadFile {
+        path: std::path::PathBuf,
+        #[source]
+        inner: std::io::Error,
+    },
+    #[error("writing {}", .path.display())]
+    WriteFile {
+        path: std::path::PathBuf,
+        #[source]
+        inner: std::io::Error,
+    },
+    #[error("unknown plan file format for {}; expected .toml, .yaml or .json", .0.display())]
+    UnknownPlanFormat(std::path::PathBuf),
+    #[error("parsing plan file")]
+    ParsePlanFile(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("serializing plan file")]
+    SerializePlanFile(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("invalid plan file: {}", plan_file::explain(.0))]
+    InvalidPlanFile(Vec<plan_file::Issue>),
+    #[error("unknown recipe \"{0}\"")]
+    UnknownRecipe(String),
+    #[error("unknown machine \"{0}\"")]
+    UnknownMachine(String),
+    #[error("recipe \"{0}\" takes no time to craft")]
+    InstantRecipe(String),
+    #[error("recipe \"{recipe}\" does not produce \"{item}\"")]
+    RecipeDoesNotProduce { recipe: String, item: String },
+    #[error("several recipes produce \"{item}\"; choose one of {candidates:?}")]
+    AmbiguousRecipe {
+        item: String,
+        candidates: Vec<String>,
+    },
+    #[error("no machine chosen for recipe category \"{category}\"; candidates: {candidates:?}")]
+    MissingMachine {
+        category: String,
+        candidates: Vec<String>,
+    },
+    #[error("machine \"{machine}\" cannot craft recipe category \"{category}\"")]
+    UnsupportedCategory { machine: String, category: String },
+    #[error("chosen recipes form a cycle: {0:?}")]
+    RecipeCycle(Vec<String>),
+    #[error("invalid energy quantity \"{0}\"")]
+    InvalidEnergy(String),
+    #[error("invalid rate \"{0}\"")]
+    InvalidRate(String),
+    #[error("unknown technology \"{0}\"")]
+    UnknownTechnology(String),
+    #[error("{0}; type \"help\" for a list of commands")]
+    InvalidCommand(String),
+    #[error("no feasible plan: {}", solver::explain(.0))]
+    Infeasible(Vec<solver::InfeasibleItem>),
+    #[error("unknown module \"{0}\"")]
+    UnknownModule(String),
+    #[error("unknown beacon \"{0}\"")]
+    UnknownBeacon(String),
+    #[error("unknown belt \"{0}\"")]
+    UnknownBelt(String),
+    #[error("unknown inserter \"{0}\"")]
+    UnknownInserter(String),
+    #[error("\"{entity}\" has {slots} module slots, but {count} modules were given")]
+    TooManyModules {
+        entity: String,
+        slots: u32,
+        count: usize,
+    },
+    #[error("\"{entity}\" does not allow the {effect} effect of module \"{module}\"")]
+    EffectNotAllowed {
+        entity: String,
+        module: String,
+        effect: modules::EffectKind,
+    },
+    #[error("module \"{module}\" cannot be used in recipe \"{recipe}\"")]
+    ModuleNotAllowed { module: String, recipe: String },
+    #[error("the smallest balanced build needs {required} machines, more than {limit}")]
+    TooManyMachines {
+        required: num_bigint::BigInt,
+        limit: u64,
+    },
+    #[error("the smallest balanced build needs {belts} belts of \"{item}\", more than allowed")]
+    TooManyBelts {
+        item: String,
+        belts: number::Rational,
+    },
+    #[error("\"{item}\" is needed at {required}/s, more than its supply limit of {limit}/s")]
+    SupplyLimitExceeded {
+        item: String,
+        required: f64,
+        limit: f64,
+    },
+    #[error("invalid simulation options: {0}")]
+    InvalidSimulation(&'static str),
+}
@@ -0,0 +1,102 @@
+//! Higher-level crafting machine models.
+
+use std::collections::BTreeSet;
+
+use data_parser::models::{self, CraftingMachine};
+
+use crate::{modules::EffectKind, units::parse_energy, Result};
+
+/// The top-level dump keys under which crafting machines are defined.
+pub const MACHINE_TYPES: &[&str] = &["assembling-machine", "furnace", "rocket-silo"];
+
+/// A machine which crafts recipes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub name: String,
+    /// The prototype type: `assembling-machine`, `furnace`, etc.
+    pub kind: String,
+    pub crafting_speed: f64,
+    pub crafting_categories: BTreeSet<String>,
+    /// Power drawn while crafting, in watts.
+    pub energy_usage: f64,
+    /// Productivity bonus which applies without modules.
+    pub base_productivity: f64,
+    pub module_slots: u32,
+    /// The module and beacon effects which apply to this machine.
+    pub allowed_effects: BTreeSet<EffectKind>,
+    pub energy_source: EnergySource,
+    /// Pollution emitted per minute while crafting, before module effects.
+    pub emissions_per_minute: f64,
+}
+
+/// How a machine is powered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnergySource {
+    Electric {
+        /// Power drawn even while idle, in watts.
+        drain: f64,
+    },
+    Burner {
+        fuel_category: String,
+        /// The fraction of fuel energy converted to work.
+        effectivity: f64,
+    },
+    /// A heat, fluid or void energy source, by prototype type.
+    Other(String),
+}
+
+impl EnergySource {
+    /// Normalize an energy source, given the owner's energy usage in watts.
+    pub fn new(source: &models::EnergySource, energy_usage: f64) -> Result<Self> {
+        Ok(match source.r#type.as_str() {
+            "electric" => Self::Electric {
+                drain: match &source.drain {
+                    Some(drain) => parse_energy(drain, 'W')?,
+                    None => energy_usage / 30.0,
+                },
+            },
+            "burner" => Self::Burner {
+                fuel_category: source
+                    .fuel_category
+                    .clone()
+                    .unwrap_or_else(|| "chemical".into()),
+                effectivity: source.effectivity.unwrap_or(1.0),
+            },
+            other => Self::Other(other.into()),
+        })
+    }
+
+    /// Power drawn while idle, in watts. Only electric sources have a drain.
+    pub fn drain(&self) -> f64 {
+        match self {
+            Self::Electric { drain } => *drain,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Machine {
+    pub fn new(machine: &CraftingMachine) -> Result<Self> {
+        let energy_usage = parse_energy(&machine.energy_usage, 'W')?;
+        Ok(Self {
+            name: machine.name.clone(),
+            kind: machine.r#type.clone(),
+            crafting_speed: machine.crafting_speed,
+            crafting_categories: machine.crafting_categories.iter().cloned().collect(),
+            energy_usage,
+            base_productivity: machine.base_productivity,
+            module_slots: machine
+                .module_specification
+                .as_ref()
+                .map_or(0, |spec| spec.module_slots),
+            allowed_effects: EffectKind::parse_all(&machine.allowed_effects),
+            energy_source: EnergySource::new(&machine.energy_source, energy_usage)?,
+            emissions_per_minute: machine.energy_source.emissions_per_minute,
+        })
+    }
+
+    /// `true` if this machine can craft recipes of the given category.
+    pub fn can_craft(&self, category: &str) -> bool {
+        self.crafting_categories.contains(category)
+    }
+}
@@ -0,0 +1,510 @@
+//! Module and beacon effects.
+//!
+//! Modules inserted into a machine, and modules inserted into beacons which
+//! affect it, change its speed, productivity, energy consumption and
+//! pollution. Effects are summed as fractional bonuses, with beacon effects
+//! scaled by the beacon's distribution effectivity, then clamped as Factorio
+//! does.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use data_parser::models::{self, module::Effect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase, machine::Machine, number::Number, plan::ProductionPlan,
+    recipe::NormalizedRecipe, units::parse_energy, Error, Result,
+};
+
+/// The lowest multiplier which negative speed, consumption and pollution
+/// bonuses can reduce a machine to.
+const MINIMUM_MULTIPLIER: f64 = 0.2;
+
+/// The kinds of effect a module can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EffectKind {
+    Consumption,
+    Speed,
+    Productivity,
+    Pollution,
+}
+
+impl EffectKind {
+    /// Parse an effect name as used by `allowed_effects`, ignoring unknown
+    /// names.
+    pub(crate) fn parse_all(names: &[String]) -> BTreeSet<Self> {
+        names
+            .iter()
+            .filter_map(|name| match name.as_str() {
+                "consumption" => Some(Self::Consumption),
+                "speed" => Some(Self::Speed),
+                "productivity" => Some(Self::Productivity),
+                "pollution" => Some(Self::Pollution),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Consumption => "consumption",
+            Self::Speed => "speed",
+            Self::Productivity => "productivity",
+            Self::Pollution => "pollution",
+        })
+    }
+}
+
+/// Fractional effect bonuses: a speed of `0.5` means 150% crafting speed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Effects<N = f64> {
+    pub consumption: N,
+    pub speed: N,
+    pub productivity: N,
+    pub pollution: N,
+}
+
+impl Effects {
+    fn new(effect: &Effect) -> Self {
+        let bonus = |value: Option<models::module::EffectValue>| value.map_or(0.0, |v| v.bonus);
+        Self {
+            consumption: bonus(effect.consumption),
+            speed: bonus(effect.speed),
+            productivity: bonus(effect.productivity),
+            pollution: bonus(effect.pollution),
+        }
+    }
+
+    /// The kinds of effect which are nonzero.
+    pub fn kinds(&self) -> impl '_ + Iterator<Item = EffectKind> {
+        [
+            (EffectKind::Consumption, self.consumption),
+            (EffectKind::Speed, self.speed),
+            (EffectKind::Productivity, self.productivity),
+            (EffectKind::Pollution, self.pollution),
+        ]
+        .into_iter()
+        .filter(|&(_, bonus)| bonus != 0.0)
+        .map(|(kind, _)| kind)
+    }
+}
+
+impl<N: Number> Effects<N> {
+    /// Add `times` copies of `effects`, each scaled by `effectivity`.
+    fn add(&mut self, effects: &Effects, times: u32, effectivity: f64) {
+        let scale = N::from_u32(times) * N::from_f64(effectivity);
+        self.consumption += scale.clone() * N::from_f64(effects.consumption);
+        self.speed += scale.clone() * N::from_f64(effects.speed);
+        self.productivity += scale.clone() * N::from_f64(effects.productivity);
+        self.pollution += scale * N::from_f64(effects.pollution);
+    }
+
+    /// Apply Factorio's limits: speed, consumption and pollution cannot fall
+    /// below 20%, and productivity cannot be negative.
+    fn clamped(self) -> Self {
+        let floor = N::from_f64(MINIMUM_MULTIPLIER) - N::one();
+        let at_least = |value: N, min: &N| if value < *min { min.clone() } else { value };
+        Self {
+            consumption: at_least(self.consumption, &floor),
+            speed: at_least(self.speed, &floor),
+            productivity: at_least(self.productivity, &N::zero()),
+            pollution: at_least(self.pollution, &floor),
+        }
+    }
+
+    /// The factor by which crafting speed is multiplied.
+    pub fn speed_multiplier(&self) -> N {
+        N::one() + self.speed.clone()
+    }
+
+    /// The factor by which energy consumption is multiplied.
+    pub fn consumption_multiplier(&self) -> N {
+        N::one() + self.consumption.clone()
+    }
+
+    /// The factor by which pollution is multiplied, on top of consumption.
+    pub fn pollution_multiplier(&self) -> N {
+        N::one() + self.pollution.clone()
+    }
+}
+
+/// A module item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleItem {
+    pub name: String,
+    pub category: String,
+    pub tier: u32,
+    pub effects: Effects,
+    /// The recipes in which this module may be used. Empty if unrestricted.
+    pub limitation: BTreeSet<String>,
+}
+
+impl ModuleItem {
+    pub fn new(module: &models::Module) -> Self {
+        Self {
+            name: module.name.clone(),
+            category: module.category.clone(),
+            tier: module.tier,
+            effects: Effects::new(&module.effect),
+            limitation: module.limitation.iter().cloned().collect(),
+        }
+    }
+
+    /// `true` if this module may be used to craft the given recipe.
+    pub fn allows_recipe(&self, recipe: &str) -> bool {
+        self.limitation.is_empty() || self.limitation.contains(recipe)
+    }
+}
+
+/// A beacon, which transmits the effects of its modules to nearby machines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beacon {
+    pub name: String,
+    /// Power drawn, in watts.
+    pub energy_usage: f64,
+    pub distribution_effectivity: f64,
+    pub module_slots: u32,
+    pub allowed_effects: BTreeSet<EffectKind>,
+}
+
+impl Beacon {
+    pub fn new(beacon: &models::Beacon) -> Result<Self> {
+        Ok(Self {
+            name: beacon.name.clone(),
+            energy_usage: parse_energy(&beacon.energy_usage, 'W')?,
+            distribution_effectivity: beacon.distribution_effectivity,
+            module_slots: beacon.module_specification.module_slots,
+            allowed_effects: EffectKind::parse_all(&beacon.allowed_effects),
+        })
+    }
+}
+
+/// Beacons of one type affecting every machine of a recipe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconLoadout {
+    pub beacon: String,
+    /// The number of beacons in range of each machine.
+    pub count: u32,
+    /// The modules in each beacon.
+    #[serde(default)]
+    pub modules: Vec<String>,
+}
+
+/// The modules in every machine of a recipe, and the beacons affecting them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleLoadout {
+    /// The modules in each machine.
+    #[serde(default)]
+    pub modules: Vec<String>,
+    #[serde(default)]
+    pub beacons: Vec<BeaconLoadout>,
+}
+
+impl ModuleLoadout {
+    /// `true` if the loadout has no modules and no beacons.
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty() && self.beacons.is_empty()
+    }
+
+    /// Compute the effects of this loadout on a machine crafting `recipe`.
+    ///
+    /// Fails if a module or beacon is unknown, if there are more modules than
+    /// slots, if a machine or beacon does not allow one of a module's
+    /// effects, or if a module's `limitation` excludes the recipe.
+    pub fn effects<N: Number>(
+        &self,
+        database: &PrototypeDatabase,
+        recipe: &NormalizedRecipe,
+        machine: &Machine,
+    ) -> Result<Effects<N>> {
+        let mut effects = Effects {
+            productivity: N::from_f64(machine.base_productivity),
+            ..Effects::default()
+        };
+
+        let modules = resolve_modules(
+            database,
+            recipe,
+            &machine.name,
+            machine.module_slots,
+            &machine.allowed_effects,
+            &self.modules,
+        )?;
+        for module in modules {
+            effects.add(&module.effects, 1, 1.0);
+        }
+
+        for loadout in &self.beacons {
+            let beacon = database
+                .beacon(&loadout.beacon)
+                .ok_or_else(|| Error::UnknownBeacon(loadout.beacon.clone()))?;
+            let modules = resolve_modules(
+                database,
+                recipe,
+                &beacon.name,
+                beacon.module_slots,
+                &beacon.allowed_effects,
+                &loadout.modules,
+            )?;
+            for module in modules {
+                // the machine must also accept what the beacon transmits
+                if let Some(effect) = module
+                    .effects
+                    .kinds()
+                    .find(|kind| !machine.allowed_effects.contains(kind))
+                {
+                    return Err(Error::EffectNotAllowed {
+                        entity: machine.name.clone(),
+                        module: module.name.clone(),
+                        effect,
+                    });
+                }
+                effects.add(
+                    &module.effects,
+                    loadout.count,
+                    beacon.distribution_effectivity,
+                );
+            }
+        }
+
+        Ok(effects.clamped())
+    }
+
+    /// The power drawn by the beacons affecting each machine, in watts.
+    ///
+    /// Beacons are usually shared between several machines; this counts
+    /// every beacon in full, so is an upper bound.
+    pub fn beacon_power<N: Number>(&self, database: &PrototypeDatabase) -> Result<N> {
+        self.beacons
+            .iter()
+            .map(|loadout| {
+                let beacon = database
+                    .beacon(&loadout.beacon)
+                    .ok_or_else(|| Error::UnknownBeacon(loadout.beacon.clone()))?;
+                Ok(N::from_u32(loadout.count) * N::from_f64(beacon.energy_usage))
+            })
+            .sum()
+    }
+}
+
+fn resolve_modules<'a>(
+    database: &'a PrototypeDatabase,
+    recipe: &NormalizedRecipe,
+    entity: &str,
+    slots: u32,
+    allowed_effects: &BTreeSet<EffectKind>,
+    names: &[String],
+) -> Result<Vec<&'a ModuleItem>> {
+    if names.len() > slots as usize {
+        return Err(Error::TooManyModules {
+            entity: entity.into(),
+            slots,
+            count: names.len(),
+        });
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            let module = database
+                .module(name)
+                .ok_or_else(|| Error::UnknownModule(name.clone()))?;
+            if let Some(effect) = module
+                .effects
+                .kinds()
+                .find(|kind| !allowed_effects.contains(kind))
+            {
+                return Err(Error::EffectNotAllowed {
+                    entity: entity.into(),
+                    module: name.clone(),
+                    effect,
+                });
+            }
+            if !module.allows_recipe(&recipe.name) {
+                return Err(Error::ModuleNotAllowed {
+                    module: name.clone(),
+                    recipe: recipe.name.clone(),
+                });
+            }
+            Ok(module)
+        })
+        .collect()
+}
+
+/// How modules change a plan, compared with the same plan without them.
+///
+/// Every value is the plan with modules minus the plan without; negative
+/// values are savings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleImpact<N = f64> {
+    /// The change in machine count, by recipe.
+    pub machines: BTreeMap<String, N>,
+    /// The change in total power, in watts.
+    pub power: N,
+    /// The change in raw input rates, by item.
+    pub raw_inputs: BTreeMap<String, N>,
+}
+
+impl<N: Number> ModuleImpact<N> {
+    pub fn new(with_modules: &ProductionPlan<N>, without_modules: &ProductionPlan<N>) -> Self {
+        fn difference<N: Number>(
+            with: impl IntoIterator<Item = (String, N)>,
+            without: impl IntoIterator<Item = (String, N)>,
+        ) -> BTreeMap<String, N> {
+            let mut delta = BTreeMap::<String, N>::new();
+            for (key, value) in with {
+                *delta.entry(key).or_default() += value;
+            }
+            for (key, value) in without {
+                *delta.entry(key).or_default() -= value;
+            }
+            delta
+        }
+
+        let machines = |plan: &ProductionPlan<N>| {
+            plan.steps
+                .iter()
+                .map(|(recipe, step)| (recipe.clone(), step.machines.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            machines: difference(machines(with_modules), machines(without_modules)),
+            power: with_modules.total_power() - without_modules.total_power(),
+            raw_inputs: difference(
+                with_modules.raw_inputs.clone(),
+                without_modules.raw_inputs.clone(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::tests::{assert_close, database, request};
+
+    fn loadout(modules: &[&str], beacons: &[(u32, &[&str])]) -> ModuleLoadout {
+        ModuleLoadout {
+            modules: modules.iter().map(|&m| m.to_owned()).collect(),
+            beacons: beacons
+                .iter()
+                .map(|&(count, modules)| BeaconLoadout {
+                    beacon: "beacon".into(),
+                    count,
+                    modules: modules.iter().map(|&m| m.to_owned()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn speed_and_beacons() {
+        let speed = ["speed-module", "speed-module"];
+        let request = request(
+            &[("electronic-circuit", 45.0)],
+            &[("electronic-circuit", loadout(&speed, &[(2, &speed)]))],
+        );
+        let database = database();
+        let with = ProductionPlan::new(&database, &request).unwrap();
+        let without = ProductionPlan::new(&database, &request.without_modules()).unwrap();
+
+        // two speed modules, plus two beacons at half effectivity
+        let step = &with.steps["electronic-circuit"];
+        assert_close(step.effects.speed, 0.8);
+        assert_close(step.effects.consumption, 2.0);
+        assert_close(step.machines, 30.0 / 1.8);
+        assert_close(step.power, 30.0 / 1.8 * (150e3 * 3.0 + 2.0 * 480e3));
+
+        let impact = ModuleImpact::new(&with, &without);
+        assert_close(impact.machines["electronic-circuit"], 30.0 / 1.8 - 30.0);
+        assert_close(impact.machines["copper-cable"], 0.0);
+        assert_close(
+            impact.power,
+            with.steps["electronic-circuit"].power - 30.0 * 150e3,
+        );
+        assert!(impact.raw_inputs.values().all(|&delta| delta.abs() < 1e-9));
+    }
+
+    #[test]
+    fn productivity_saves_inputs() {
+        let productivity = ["productivity-module", "productivity-module"];
+        let request = request(
+            &[("electronic-circuit", 45.0)],
+            &[("copper-cable", loadout(&productivity, &[]))],
+        );
+        let database = database();
+        let with = ProductionPlan::new(&database, &request).unwrap();
+        let without = ProductionPlan::new(&database, &request.without_modules()).unwrap();
+
+        let step = &with.steps["copper-cable"];
+        assert_close(step.crafts_per_second, 135.0 / 2.16);
+        assert_close(step.machines, 62.5 * 0.5 / (0.75 * 0.9));
+        assert_close(step.outputs["copper-cable"], 135.0);
+
+        let impact = ModuleImpact::new(&with, &without);
+        assert_close(impact.raw_inputs["copper-plate"], 62.5 - 67.5);
+        assert_close(impact.raw_inputs["iron-plate"], 0.0);
+    }
+
+    #[test]
+    fn effects_are_clamped() {
+        let efficiency = ["efficiency-module", "efficiency-module"];
+        let request = request(
+            &[("iron-gear-wheel", 1.5)],
+            &[("iron-gear-wheel", loadout(&efficiency, &[(2, &efficiency)]))],
+        );
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        let step = &plan.steps["iron-gear-wheel"];
+        assert_close(step.effects.consumption, -0.8);
+        assert_close(step.power, 150e3 * 0.2 + 2.0 * 480e3);
+    }
+
+    #[test]
+    fn invalid_loadouts() {
+        let database = database();
+        let plan = |recipe: &str, loadout: ModuleLoadout| {
+            let item = database.recipe(recipe).unwrap().products[0].name.clone();
+            ProductionPlan::new(&database, &request(&[(&item, 1.0)], &[(recipe, loadout)]))
+                .unwrap_err()
+        };
+
+        let err = plan(
+            "copper-cable",
+            loadout(&["speed-module", "speed-module", "speed-module"], &[]),
+        );
+        assert!(matches!(
+            err,
+            Error::TooManyModules {
+                slots: 2,
+                count: 3,
+                ..
+            }
+        ));
+
+        let err = plan(
+            "copper-cable",
+            loadout(&[], &[(1, &["productivity-module"])]),
+        );
+        assert!(matches!(
+            err,
+            Error::EffectNotAllowed { entity, effect: EffectKind::Productivity, .. }
+                if entity == "beacon"
+        ));
+
+        let err = plan("iron-gear-wheel", loadout(&["productivity-module"], &[]));
+        assert!(
+            matches!(err, Error::ModuleNotAllowed { recipe, .. } if recipe == "iron-gear-wheel")
+        );
+
+        let err = plan("iron-gear-wheel", loadout(&["warp-module"], &[]));
+        assert!(matches!(err, Error::UnknownModule(_)));
+    }
+}
@@ -0,0 +1,410 @@
+//! Numeric backends for planning.
+//!
+//! Factorio ratios are exact rationals: 3 cable assemblers feed 2 circuit
+//! assemblers, kovarex enrichment nets 1 uranium-235 per 60 second craft, and
+//! so on. Floating-point arithmetic turns these into `2.9999999999999996`
+//! machines. The planner is therefore generic over a [`Number`], which is
+//! either [`f64`] for speed or [`Rational`] for exact results.
+
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use time::Duration;
+
+/// Floating-point values smaller than this are considered to be zero.
+pub const EPSILON: f64 = 1e-9;
+
+/// Arithmetic used by the planner and solver.
+pub trait Number:
+    Clone
+    + Default
+    + fmt::Debug
+    + fmt::Display
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+{
+    fn from_u32(value: u32) -> Self;
+
+    /// Convert `numer / denom`, exactly if possible.
+    fn from_ratio(numer: i128, denom: i128) -> Self;
+
+    /// Convert a value read from prototype data.
+    ///
+    /// Prototype data is written in decimal, so exact backends convert the
+    /// shortest decimal representation of `value` rather than its binary
+    /// expansion: `0.007` becomes `7/1000`.
+    ///
+    /// `value` must be finite, which prototype data always is: JSON cannot
+    /// encode NaN or infinities, and energy strings which overflow are
+    /// rejected by [`parse_energy`](crate::units::parse_energy). Exact
+    /// backends panic otherwise; use [`Rational::try_from`] for values from
+    /// elsewhere.
+    fn from_f64(value: f64) -> Self;
+
+    fn to_f64(&self) -> f64;
+
+    /// `true` if the value is zero, or within [`EPSILON`] of it for inexact
+    /// backends.
+    fn is_zero(&self) -> bool;
+
+    /// The least integer not less than the value. Inexact backends round
+    /// values within [`EPSILON`] of an integer to that integer, so that
+    /// `3.0000000000000004` machines are 3 machines.
+    fn ceil(&self) -> Self;
+
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    fn one() -> Self {
+        Self::from_u32(1)
+    }
+
+    /// Convert a duration to seconds.
+    fn from_duration(duration: Duration) -> Self {
+        Self::from_ratio(duration.whole_nanoseconds(), 1_000_000_000)
+    }
+
+    /// `true` if the value is positive and not [zero][Self::is_zero].
+    fn is_positive(&self) -> bool {
+        !self.is_zero() && *self > Self::zero()
+    }
+
+    /// `true` if the value is negative and not [zero][Self::is_zero].
+    fn is_negative(&self) -> bool {
+        !self.is_zero() && *self < Self::zero()
+    }
+
+    fn abs(&self) -> Self {
+        if *self < Self::zero() {
+            -self.clone()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+impl Number for f64 {
+    fn from_u32(value: u32) -> Self {
+        value.into()
+    }
+
+    fn from_ratio(numer: i128, denom: i128) -> Self {
+        numer as f64 / denom as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn is_zero(&self) -> bool {
+        f64::abs(*self) <= EPSILON
+    }
+
+    fn ceil(&self) -> Self {
+        let rounded = self.round();
+        if f64::abs(self - rounded) <= EPSILON {
+            rounded
+        } else {
+            f64::ceil(*self)
+        }
+    }
+}
+
+/// An arbitrary-precision rational number.
+///
+/// Displays as a mixed number, such as `7 1/2`, and serializes likewise. Parses
+/// from mixed numbers, fractions (`15/2`), and decimals (`7.5`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rational(pub BigRational);
+
+impl Rational {
+    pub fn new(numer: i64, denom: i64) -> Self {
+        Self(BigRational::new(numer.into(), denom.into()))
+    }
+
+    /// The integer part, rounded towards zero.
+    pub fn trunc(&self) -> BigInt {
+        self.0.trunc().to_integer()
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self(BigRational::zero())
+    }
+}
+
+impl Number for Rational {
+    fn from_u32(value: u32) -> Self {
+        Self(BigInt::from(value).into())
+    }
+
+    fn from_ratio(numer: i128, denom: i128) -> Self {
+        Self(BigRational::new(numer.into(), denom.into()))
+    }
+
+    fn from_f64(value: f64) -> Self {
+        Self::try_from(value).expect("prototype values are finite")
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    fn ceil(&self) -> Self {
+        Self(self.0.ceil())
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.trunc();
+        let fraction = (&self.0 - BigRational::from(whole.clone())).abs();
+        match (whole.is_zero(), fraction.is_zero()) {
+            (_, true) => write!(f, "{whole}"),
+            (true, false) if self.0.is_negative() => write!(f, "-{fraction}"),
+            (true, false) => write!(f, "{fraction}"),
+            (false, false) => write!(f, "{whole} {fraction}"),
+        }
+    }
+}
+
+impl TryFrom<f64> for Rational {
+    type Error = NotFiniteError;
+
+    /// Convert a finite value exactly, as [`Number::from_f64`] does.
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if !value.is_finite() {
+            return Err(NotFiniteError(value));
+        }
+        // the `Display` impl of `f64` prints the shortest decimal which
+        // round-trips, and never uses exponent notation
+        Ok(value
+            .to_string()
+            .parse()
+            .expect("finite values are decimals"))
+    }
+}
+
+/// An error converting NaN or an infinity to a [`Rational`].
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[error("{0} is not a finite number")]
+pub struct NotFiniteError(pub f64);
+
+/// An error parsing a [`Rational`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid rational number \"{0}\"")]
+pub struct ParseRationalError(String);
+
+impl FromStr for Rational {
+    type Err = ParseRationalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseRationalError(s.to_owned());
+        let integer = |s: &str| {
+            s.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| s.parse::<BigInt>().ok())
+                .flatten()
+                .ok_or_else(invalid)
+        };
+
+        let fraction = |numer: &str, denom: &str| {
+            let denom = integer(denom)?;
+            if denom.is_zero() {
+                return Err(invalid());
+            }
+            Ok(BigRational::new(integer(numer)?, denom))
+        };
+
+        let trimmed = s.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+
+        let value = if let Some((whole, rest)) = unsigned.split_once(' ') {
+            let (numer, denom) = rest.trim().split_once('/').ok_or_else(invalid)?;
+            BigRational::from(integer(whole)?) + fraction(numer, denom)?
+        } else if let Some((numer, denom)) = unsigned.split_once('/') {
+            fraction(numer, denom)?
+        } else if let Some((whole, decimals)) = unsigned.split_once('.') {
+            let whole = if whole.is_empty() {
+                BigInt::zero()
+            } else {
+                integer(whole)?
+            };
+            let scale = BigInt::from(10).pow(decimals.len() as u32);
+            BigRational::from(whole) + BigRational::new(integer(decimals)?, scale)
+        } else {
+            integer(unsigned)?.into()
+        };
+
+        Ok(Self(if negative { -value } else { value }))
+    }
+}
+
+impl Serialize for Rational {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rational {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Rational;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number, or a string such as \"7 1/2\" or \"15/2\"")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Rational, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Rational, E> {
+                Ok(Rational(BigInt::from(v).into()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Rational, E> {
+                Ok(Rational(BigInt::from(v).into()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Rational, E> {
+                Rational::try_from(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+macro_rules! forward_binop {
+    ($($trait:ident::$method:ident),*) => {$(
+        impl $trait for Rational {
+            type Output = Self;
+
+            fn $method(self, rhs: Self) -> Self {
+                Self(self.0.$method(rhs.0))
+            }
+        }
+    )*};
+}
+
+forward_binop!(Add::add, Sub::sub, Mul::mul, Div::div);
+
+impl AddAssign for Rational {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Rational {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Sum for Rational {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|value| value.0).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_mixed_numbers() {
+        assert_eq!(Rational::new(15, 2).to_string(), "7 1/2");
+        assert_eq!(Rational::new(-15, 2).to_string(), "-7 1/2");
+        assert_eq!(Rational::new(1, 3).to_string(), "1/3");
+        assert_eq!(Rational::new(-1, 3).to_string(), "-1/3");
+        assert_eq!(Rational::new(6, 2).to_string(), "3");
+        assert_eq!(Rational::zero().to_string(), "0");
+    }
+
+    #[test]
+    fn parses() {
+        let parse = |s: &str| s.parse::<Rational>().unwrap();
+        assert_eq!(parse("7 1/2"), Rational::new(15, 2));
+        assert_eq!(parse("-7 1/2"), Rational::new(-15, 2));
+        assert_eq!(parse("15/2"), Rational::new(15, 2));
+        assert_eq!(parse("7.5"), Rational::new(15, 2));
+        assert_eq!(parse(".25"), Rational::new(1, 4));
+        assert_eq!(parse("42"), Rational::new(42, 1));
+        for invalid in ["", "1/0", "1 1/0", "1/-2", "seven", "1.5/2", "+3"] {
+            assert!(invalid.parse::<Rational>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn converts_prototype_values_exactly() {
+        assert_eq!(Rational::from_f64(0.007), Rational::new(7, 1000));
+        assert_eq!(Rational::from_f64(0.75), Rational::new(3, 4));
+        assert_eq!(Rational::from_f64(1e-7), Rational::new(1, 10_000_000));
+        assert!(Rational::try_from(f64::NAN).is_err());
+        assert!(Rational::try_from(f64::INFINITY).is_err());
+        assert_eq!(
+            Rational::from_duration(Duration::seconds_f64(0.1)),
+            Rational::new(1, 10)
+        );
+    }
+
+    #[test]
+    fn ceil() {
+        assert_eq!(Number::ceil(&((0.1 + 0.2) * 10.0)), 3.0);
+        assert_eq!(Number::ceil(&2.5), 3.0);
+        assert_eq!(Rational::new(7, 3).ceil(), Rational::new(3, 1));
+        assert_eq!(Rational::new(-7, 3).ceil(), Rational::new(-2, 1));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let value = Rational::new(-22, 3);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"-7 1/3\"");
+        assert_eq!(serde_json::from_str::<Rational>(&json).unwrap(), value);
+        assert_eq!(
+            serde_json::from_str::<Rational>("2.5").unwrap(),
+            Rational::new(5, 2)
+        );
+    }
+}
@@ -0,0 +1,868 @@
+//! Steady-state production planning.
+//!
+//! Given target output rates, a recipe for each item and a machine for each
+//! recipe category, a [`ProductionPlan`] computes how fast each recipe must run,
+//! how many machines that takes, and how much of each item flows through the
+//! factory per second.
+//!
+//! Plans are generic over their [`Number`] type: `f64` by default, or
+//! [`Rational`][crate::number::Rational] for exact ratios.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    machine::Machine,
+    modules::{Effects, ModuleLoadout},
+    number::Number,
+    policy::{RecipePolicy, RecipeSelector},
+    recipe::ItemKind,
+    recipe::NormalizedRecipe,
+    Error, Result,
+};
+
+/// The inputs to the planner.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlanRequest<N = f64> {
+    /// Desired output rates, in items (or units of fluid) per second.
+    pub targets: BTreeMap<String, N>,
+    /// Which recipe produces each item.
+    ///
+    /// Each item must be left with a single producing recipe, or none, in
+    /// which case it is a raw input.
+    #[serde(flatten)]
+    pub policy: RecipePolicy,
+    /// The machine used to craft each recipe category.
+    ///
+    /// Categories without an explicit choice use the sole machine which can
+    /// craft them.
+    #[serde(default)]
+    pub machines: BTreeMap<String, String>,
+    /// The modules and beacons used by each recipe.
+    #[serde(default)]
+    pub modules: BTreeMap<String, ModuleLoadout>,
+    /// Items supplied from outside the plan, such as circuits taken from a
+    /// main bus.
+    ///
+    /// The chain is not expanded past these items, even if recipes produce
+    /// them; the rate at which each must be supplied is reported among the
+    /// [raw inputs][ProductionPlan::raw_inputs].
+    #[serde(default)]
+    pub supplied: BTreeMap<String, Supply<N>>,
+}
+
+/// An item supplied from outside the plan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Supply<N = f64> {
+    /// The greatest rate at which the item can be supplied, per second.
+    #[serde(default)]
+    pub limit: Option<N>,
+}
+
+impl<N: Clone> PlanRequest<N> {
+    /// This request with every module and beacon removed.
+    pub fn without_modules(&self) -> Self {
+        Self {
+            modules: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// A single recipe running in the plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecipeStep<N = f64> {
+    pub recipe: String,
+    pub machine: String,
+    pub crafts_per_second: N,
+    /// The number of machines required to sustain the craft rate.
+    ///
+    /// This is fractional: the final machine may not run at full speed.
+    pub machines: N,
+    /// Items consumed per second.
+    pub inputs: BTreeMap<String, N>,
+    /// Items produced per second.
+    pub outputs: BTreeMap<String, N>,
+    /// The combined effects of modules and beacons on each machine.
+    pub effects: Effects<N>,
+    /// Power drawn while crafting by the machines and their beacons, in watts.
+    pub power: N,
+}
+
+/// The total flow of an item through the plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemFlow<N = f64> {
+    pub kind: ItemKind,
+    /// Items produced per second by recipes in the plan.
+    pub produced: N,
+    /// Items consumed per second by recipes in the plan.
+    pub consumed: N,
+}
+
+/// The result of planning: every recipe step and item flow required to meet
+/// the requested targets.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProductionPlan<N = f64> {
+    /// Requested output rates per second.
+    pub targets: BTreeMap<String, N>,
+    /// Recipe steps, by recipe name.
+    pub steps: BTreeMap<String, RecipeStep<N>>,
+    /// Item flows, by item name.
+    pub flows: BTreeMap<String, ItemFlow<N>>,
+    /// Items which must be supplied from outside the plan, per second.
+    pub raw_inputs: BTreeMap<String, N>,
+    /// Items produced beyond the targets and internal demand, per second.
+    ///
+    /// These are byproducts of multi-output recipes.
+    pub surplus: BTreeMap<String, N>,
+}
+
+/// Choose the machine which crafts `category`.
+pub(crate) fn choose_machine<'a>(
+    database: &'a PrototypeDatabase,
+    machines: &BTreeMap<String, String>,
+    category: &str,
+) -> Result<&'a Machine> {
+    if let Some(name) = machines.get(category) {
+        let machine = database
+            .machine(name)
+            .ok_or_else(|| Error::UnknownMachine(name.clone()))?;
+        if !machine.can_craft(category) {
+            return Err(Error::UnsupportedCategory {
+                machine: name.clone(),
+                category: category.into(),
+            });
+        }
+        return Ok(machine);
+    }
+
+    let candidates = database
+        .machines
+        .values()
+        .filter(|machine| machine.can_craft(category))
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [machine] => Ok(machine),
+        _ => Err(Error::MissingMachine {
+            category: category.into(),
+            candidates: candidates
+                .iter()
+                .map(|machine| machine.name.clone())
+                .collect(),
+        }),
+    }
+}
+
+/// How a recipe is crafted: by which machine, and with which effects.
+pub(crate) struct Configuration<'a, N> {
+    pub(crate) machine: &'a Machine,
+    pub(crate) effects: Effects<N>,
+    /// The power drawn by each machine and its beacons, in watts.
+    pub(crate) power: N,
+}
+
+impl<'a, N: Number> Configuration<'a, N> {
+    pub(crate) fn new(
+        database: &'a PrototypeDatabase,
+        request: &PlanRequest<N>,
+        recipe: &NormalizedRecipe,
+    ) -> Result<Self> {
+        let machine = choose_machine(database, &request.machines, &recipe.category)?;
+        let loadout = request.modules.get(&recipe.name);
+        let effects = match loadout {
+            Some(loadout) => loadout.effects(database, recipe, machine)?,
+            None => ModuleLoadout::default().effects(database, recipe, machine)?,
+        };
+        let beacon_power = match loadout {
+            Some(loadout) => loadout.beacon_power(database)?,
+            None => N::zero(),
+        };
+        let power =
+            N::from_f64(machine.energy_usage) * effects.consumption_multiplier() + beacon_power;
+        Ok(Self {
+            machine,
+            effects,
+            power,
+        })
+    }
+
+    /// The number of machines needed per craft per second.
+    pub(crate) fn machines_per_craft(&self, recipe: &NormalizedRecipe) -> N {
+        recipe.seconds::<N>()
+            / (N::from_f64(self.machine.crafting_speed) * self.effects.speed_multiplier())
+    }
+
+    /// The net amount of each item produced per craft.
+    pub(crate) fn net_amounts<'r>(&self, recipe: &'r NormalizedRecipe) -> BTreeMap<&'r str, N> {
+        recipe.net_amounts_with_productivity(&self.effects.productivity)
+    }
+}
+
+impl<N: Number> ProductionPlan<N> {
+    /// Plan production to meet the requested targets.
+    ///
+    /// Recipes are resolved by walking the production tree from the targets.
+    /// Items which appear as both ingredient and product of the same recipe
+    /// are netted out, so catalyst loops such as kovarex enrichment are
+    /// supported. Byproducts of multi-output recipes are not balanced against
+    /// other demand; they are reported as [`surplus`][Self::surplus].
+    ///
+    /// Fails if the chosen recipes form a cycle between distinct recipes, or
+    /// if a [supplied][PlanRequest::supplied] item is needed faster than its
+    /// limit.
+    pub fn new(database: &PrototypeDatabase, request: &PlanRequest<N>) -> Result<Self> {
+        // resolve the producing recipe for each item reachable from the targets
+        let supplied = request.supplied.keys().cloned().collect();
+        let selector = RecipeSelector::new(database, &request.policy, &supplied)?;
+        let mut producer = BTreeMap::<String, &NormalizedRecipe>::new();
+        let mut raw = BTreeSet::new();
+        let mut queue = request.targets.keys().cloned().collect::<Vec<_>>();
+        while let Some(item) = queue.pop() {
+            if producer.contains_key(&item) || raw.contains(&item) {
+                continue;
+            }
+            if request.supplied.contains_key(&item) {
+                raw.insert(item);
+                continue;
+            }
+            match selector.choose(&item)? {
+                None => {
+                    raw.insert(item);
+                }
+                Some(recipe) => {
+                    queue.extend(
+                        recipe
+                            .net_amounts::<f64>()
+                            .into_iter()
+                            .filter(|&(_, amount)| amount < 0.0)
+                            .map(|(name, _)| name.to_owned()),
+                    );
+                    producer.insert(item, recipe);
+                }
+            }
+        }
+
+        let recipes = producer
+            .values()
+            .map(|recipe| (recipe.name.as_str(), *recipe))
+            .collect::<BTreeMap<_, _>>();
+
+        // a recipe's rate is known once every recipe consuming its outputs has
+        // been computed; order the recipes accordingly
+        let mut waiting_on = recipes
+            .keys()
+            .map(|&name| (name, BTreeSet::new()))
+            .collect::<BTreeMap<_, _>>();
+        for recipe in recipes.values() {
+            for (item, amount) in recipe.net_amounts::<f64>() {
+                if amount >= 0.0 {
+                    continue;
+                }
+                if let Some(producer) = producer.get(item) {
+                    if producer.name != recipe.name {
+                        waiting_on
+                            .get_mut(producer.name.as_str())
+                            .expect("every producer is a planned recipe")
+                            .insert(recipe.name.as_str());
+                    }
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(recipes.len());
+        while !waiting_on.is_empty() {
+            let ready = waiting_on
+                .iter()
+                .filter(|(_, consumers)| consumers.is_empty())
+                .map(|(&name, _)| name)
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                return Err(Error::RecipeCycle(
+                    waiting_on.keys().map(|&name| name.to_owned()).collect(),
+                ));
+            }
+            for name in ready {
+                waiting_on.remove(name);
+                for consumers in waiting_on.values_mut() {
+                    consumers.remove(name);
+                }
+                order.push(recipes[name]);
+            }
+        }
+
+        // propagate demand from the targets towards the raw inputs
+        let mut demand = request.targets.clone();
+        let mut plan = Self {
+            targets: request.targets.clone(),
+            ..Self::default()
+        };
+        for recipe in order {
+            let configuration = Configuration::new(database, request, recipe)?;
+            let net = configuration.net_amounts(recipe);
+            let crafts_per_second = producer
+                .iter()
+                .filter(|(_, producer)| producer.name == recipe.name)
+                .map(|(item, _)| {
+                    demand.get(item).cloned().unwrap_or_default() / net[item.as_str()].clone()
+                })
+                .fold(N::zero(), |max, rate| if rate > max { rate } else { max });
+
+            for (item, amount) in net {
+                if amount < N::zero() {
+                    *demand.entry(item.to_owned()).or_default() -=
+                        crafts_per_second.clone() * amount;
+                }
+            }
+
+            plan.add_step(recipe, &configuration, crafts_per_second);
+        }
+
+        for item in raw {
+            let rate = demand.get(&item).cloned().unwrap_or_default();
+            let limit = request
+                .supplied
+                .get(&item)
+                .and_then(|supply| supply.limit.as_ref());
+            if let Some(limit) = limit {
+                if (rate.clone() - limit.clone()).is_positive() {
+                    return Err(Error::SupplyLimitExceeded {
+                        item,
+                        required: rate.to_f64(),
+                        limit: limit.to_f64(),
+                    });
+                }
+            }
+            plan.raw_inputs.insert(item, rate);
+        }
+        plan.compute_surplus();
+
+        Ok(plan)
+    }
+
+    /// Add a recipe step running at the given rate, updating item flows.
+    pub(crate) fn add_step(
+        &mut self,
+        recipe: &NormalizedRecipe,
+        configuration: &Configuration<N>,
+        crafts_per_second: N,
+    ) {
+        let machines = crafts_per_second.clone() * configuration.machines_per_craft(recipe);
+        let mut step = RecipeStep {
+            recipe: recipe.name.clone(),
+            machine: configuration.machine.name.clone(),
+            crafts_per_second: crafts_per_second.clone(),
+            machines: machines.clone(),
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+            effects: configuration.effects.clone(),
+            power: machines * configuration.power.clone(),
+        };
+        let productivity = N::one() + configuration.effects.productivity.clone();
+
+        for ingredient in &recipe.ingredients {
+            let rate = crafts_per_second.clone() * N::from_u32(ingredient.amount);
+            *step.inputs.entry(ingredient.name.clone()).or_default() += rate.clone();
+            self.flow(&ingredient.name, ingredient.kind).consumed += rate;
+        }
+        for product in &recipe.products {
+            let rate = crafts_per_second.clone() * product.expected_amount() * productivity.clone();
+            *step.outputs.entry(product.name.clone()).or_default() += rate.clone();
+            self.flow(&product.name, product.kind).produced += rate;
+        }
+
+        self.steps.insert(recipe.name.clone(), step);
+    }
+
+    fn flow(&mut self, item: &str, kind: ItemKind) -> &mut ItemFlow<N> {
+        self.flows.entry(item.to_owned()).or_insert(ItemFlow {
+            kind,
+            produced: N::zero(),
+            consumed: N::zero(),
+        })
+    }
+
+    /// Recompute [`surplus`][Self::surplus] from the item flows and targets.
+    pub(crate) fn compute_surplus(&mut self) {
+        self.surplus = self
+            .flows
+            .iter()
+            .filter_map(|(item, flow)| {
+                let target = self.targets.get(item).cloned().unwrap_or_default();
+                let surplus = flow.produced.clone() - flow.consumed.clone() - target;
+                (flow.produced > N::zero() && surplus.is_positive())
+                    .then(|| (item.clone(), surplus))
+            })
+            .collect();
+    }
+
+    /// The total power drawn while crafting, in watts.
+    pub fn total_power(&self) -> N {
+        self.steps.values().map(|step| step.power.clone()).sum()
+    }
+
+    /// The total number of machines in the plan, by machine name.
+    pub fn machine_totals(&self) -> BTreeMap<&str, N> {
+        let mut totals = BTreeMap::<_, N>::new();
+        for step in self.steps.values() {
+            *totals.entry(step.machine.as_str()).or_default() += step.machines.clone();
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use time::Duration;
+
+    use super::*;
+    use crate::{
+        machine::EnergySource,
+        modules::{Beacon, EffectKind, ModuleItem},
+        recipe::{Ingredient, Product},
+    };
+
+    pub(crate) fn recipe(
+        name: &str,
+        category: &str,
+        seconds: f64,
+        ingredients: &[(&str, ItemKind, u32)],
+        products: &[(&str, ItemKind, u32, f64)],
+    ) -> NormalizedRecipe {
+        NormalizedRecipe {
+            name: name.into(),
+            category: category.into(),
+            subgroup: None,
+            enabled: true,
+            hidden: false,
+            duration: Duration::seconds_f64(seconds),
+            emissions_multiplier: 1.0,
+            ingredients: ingredients
+                .iter()
+                .map(|&(name, kind, amount)| Ingredient {
+                    name: name.into(),
+                    kind,
+                    amount,
+                })
+                .collect(),
+            products: products
+                .iter()
+                .map(|&(name, kind, amount, probability)| Product {
+                    name: name.into(),
+                    kind,
+                    amount,
+                    probability,
+                })
+                .collect(),
+        }
+    }
+
+    fn machine(
+        name: &str,
+        crafting_speed: f64,
+        energy_usage: f64,
+        emissions_per_minute: f64,
+        module_slots: u32,
+        categories: &[&str],
+    ) -> Machine {
+        use EffectKind::*;
+
+        Machine {
+            name: name.into(),
+            kind: "assembling-machine".into(),
+            crafting_speed,
+            crafting_categories: categories.iter().map(|&c| c.to_owned()).collect(),
+            energy_usage,
+            base_productivity: 0.0,
+            module_slots,
+            allowed_effects: BTreeSet::from([Consumption, Speed, Productivity, Pollution]),
+            energy_source: EnergySource::Electric {
+                drain: energy_usage / 30.0,
+            },
+            emissions_per_minute,
+        }
+    }
+
+    fn module(name: &str, effects: Effects, limitation: &[&str]) -> ModuleItem {
+        ModuleItem {
+            name: name.into(),
+            category: name.trim_end_matches("-module").into(),
+            tier: 1,
+            effects,
+            limitation: limitation.iter().map(|&r| r.to_owned()).collect(),
+        }
+    }
+
+    /// A small database resembling the base game.
+    pub(crate) fn database() -> PrototypeDatabase {
+        use ItemKind::{Fluid, Item};
+
+        let recipes = [
+            recipe(
+                "copper-cable",
+                "crafting",
+                0.5,
+                &[("copper-plate", Item, 1)],
+                &[("copper-cable", Item, 2, 1.0)],
+            ),
+            recipe(
+                "electronic-circuit",
+                "crafting",
+                0.5,
+                &[("iron-plate", Item, 1), ("copper-cable", Item, 3)],
+                &[("electronic-circuit", Item, 1, 1.0)],
+            ),
+            recipe(
+                "iron-gear-wheel",
+                "crafting",
+                0.5,
+                &[("iron-plate", Item, 2)],
+                &[("iron-gear-wheel", Item, 1, 1.0)],
+            ),
+            recipe(
+                "basic-oil-processing",
+                "oil-processing",
+                5.0,
+                &[("crude-oil", Fluid, 100)],
+                &[("petroleum-gas", Fluid, 45, 1.0)],
+            ),
+            recipe(
+                "advanced-oil-processing",
+                "oil-processing",
+                5.0,
+                &[("water", Fluid, 50), ("crude-oil", Fluid, 100)],
+                &[
+                    ("heavy-oil", Fluid, 25, 1.0),
+                    ("light-oil", Fluid, 45, 1.0),
+                    ("petroleum-gas", Fluid, 55, 1.0),
+                ],
+            ),
+            recipe(
+                "heavy-oil-cracking",
+                "chemistry",
+                2.0,
+                &[("water", Fluid, 30), ("heavy-oil", Fluid, 40)],
+                &[("light-oil", Fluid, 30, 1.0)],
+            ),
+            recipe(
+                "light-oil-cracking",
+                "chemistry",
+                2.0,
+                &[("water", Fluid, 30), ("light-oil", Fluid, 30)],
+                &[("petroleum-gas", Fluid, 20, 1.0)],
+            ),
+            recipe(
+                "plastic-bar",
+                "chemistry",
+                1.0,
+                &[("petroleum-gas", Fluid, 20), ("coal", Item, 1)],
+                &[("plastic-bar", Item, 2, 1.0)],
+            ),
+            recipe(
+                "uranium-processing",
+                "centrifuging",
+                12.0,
+                &[("uranium-ore", Item, 10)],
+                &[
+                    ("uranium-235", Item, 1, 0.007),
+                    ("uranium-238", Item, 1, 0.993),
+                ],
+            ),
+            recipe(
+                "kovarex-enrichment-process",
+                "centrifuging",
+                60.0,
+                &[("uranium-235", Item, 40), ("uranium-238", Item, 5)],
+                &[
+                    ("uranium-235", Item, 41, 1.0),
+                    ("uranium-238", Item, 2, 1.0),
+                ],
+            ),
+        ];
+        let machines = [
+            machine(
+                "assembling-machine-2",
+                0.75,
+                150e3,
+                3.0,
+                2,
+                &["crafting", "crafting-with-fluid"],
+            ),
+            machine(
+                "assembling-machine-3",
+                1.25,
+                375e3,
+                2.0,
+                4,
+                &["crafting", "crafting-with-fluid"],
+            ),
+            machine("oil-refinery", 1.0, 420e3, 6.0, 3, &["oil-processing"]),
+            machine("chemical-plant", 1.0, 210e3, 4.0, 3, &["chemistry"]),
+            machine("centrifuge", 1.0, 350e3, 4.0, 2, &["centrifuging"]),
+        ];
+        let modules = [
+            module(
+                "speed-module",
+                Effects {
+                    consumption: 0.5,
+                    speed: 0.2,
+                    ..Default::default()
+                },
+                &[],
+            ),
+            module(
+                "efficiency-module",
+                Effects {
+                    consumption: -0.3,
+                    ..Default::default()
+                },
+                &[],
+            ),
+            module(
+                "productivity-module",
+                Effects {
+                    consumption: 0.4,
+                    speed: -0.05,
+                    productivity: 0.04,
+                    pollution: 0.05,
+                },
+                &["copper-cable", "electronic-circuit", "plastic-bar"],
+            ),
+        ];
+        let beacon = Beacon {
+            name: "beacon".into(),
+            energy_usage: 480e3,
+            distribution_effectivity: 0.5,
+            module_slots: 2,
+            allowed_effects: BTreeSet::from([
+                EffectKind::Consumption,
+                EffectKind::Speed,
+                EffectKind::Pollution,
+            ]),
+        };
+
+        PrototypeDatabase {
+            recipes: recipes
+                .into_iter()
+                .map(|recipe| (recipe.name.clone(), recipe))
+                .collect(),
+            machines: machines
+                .into_iter()
+                .map(|machine| (machine.name.clone(), machine))
+                .collect(),
+            modules: modules
+                .into_iter()
+                .map(|module| (module.name.clone(), module))
+                .collect(),
+            beacons: BTreeMap::from([(beacon.name.clone(), beacon)]),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn assert_close(found: f64, expect: f64) {
+        assert!(
+            (found - expect).abs() < 1e-6,
+            "expected {expect}, found {found}"
+        );
+    }
+
+    /// A request for `targets`, crafting in `assembling-machine-2`s with the
+    /// given module loadouts.
+    pub(crate) fn request<N: Number>(
+        targets: &[(&str, N)],
+        modules: &[(&str, ModuleLoadout)],
+    ) -> PlanRequest<N> {
+        PlanRequest {
+            targets: targets
+                .iter()
+                .map(|(item, rate)| (item.to_string(), rate.clone()))
+                .collect(),
+            machines: BTreeMap::from([("crafting".into(), "assembling-machine-2".into())]),
+            modules: modules
+                .iter()
+                .map(|(recipe, loadout)| (recipe.to_string(), loadout.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn red_circuits() {
+        let plan = ProductionPlan::new(&database(), &request(&[("electronic-circuit", 45.0)], &[]))
+            .unwrap();
+
+        // the classic 3:2 cable to circuit assembler ratio
+        assert_close(plan.steps["electronic-circuit"].machines, 30.0);
+        assert_close(plan.steps["copper-cable"].machines, 45.0);
+        assert_close(plan.flows["copper-cable"].produced, 135.0);
+        assert_close(plan.flows["copper-cable"].consumed, 135.0);
+        assert_close(plan.raw_inputs["iron-plate"], 45.0);
+        assert_close(plan.raw_inputs["copper-plate"], 67.5);
+        assert!(plan.surplus.is_empty());
+        assert_close(plan.machine_totals()["assembling-machine-2"], 75.0);
+    }
+
+    #[test]
+    fn shared_intermediate() {
+        let plan = ProductionPlan::new(
+            &database(),
+            &request(
+                &[("electronic-circuit", 1.0), ("iron-gear-wheel", 1.0)],
+                &[],
+            ),
+        )
+        .unwrap();
+        assert_close(plan.raw_inputs["iron-plate"], 3.0);
+    }
+
+    #[test]
+    fn byproducts_are_surplus() {
+        let mut request = request(&[("petroleum-gas", 11.0)], &[]);
+        request
+            .policy
+            .recipes
+            .insert("petroleum-gas".into(), "advanced-oil-processing".into());
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        assert_close(plan.steps["advanced-oil-processing"].crafts_per_second, 0.2);
+        assert_close(plan.steps["advanced-oil-processing"].machines, 1.0);
+        assert_close(plan.surplus["heavy-oil"], 5.0);
+        assert_close(plan.surplus["light-oil"], 9.0);
+        assert_close(plan.raw_inputs["crude-oil"], 20.0);
+        assert_close(plan.raw_inputs["water"], 10.0);
+    }
+
+    #[test]
+    fn catalysts_are_netted() {
+        let mut request = request(&[("uranium-235", 1.0)], &[]);
+        request
+            .policy
+            .recipes
+            .insert("uranium-235".into(), "kovarex-enrichment-process".into());
+        request
+            .policy
+            .recipes
+            .insert("uranium-238".into(), "uranium-processing".into());
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        // one net uranium-235 per 60 second craft
+        assert_close(plan.steps["kovarex-enrichment-process"].machines, 60.0);
+        // three net uranium-238 per craft
+        assert_close(
+            plan.steps["uranium-processing"].crafts_per_second,
+            3.0 / 0.993,
+        );
+        assert!(plan.surplus.contains_key("uranium-235"));
+    }
+
+    #[test]
+    fn supplied_items() {
+        let mut request = request(&[("electronic-circuit", 45.0)], &[]);
+        request
+            .supplied
+            .insert("copper-cable".into(), Supply::default());
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        assert_eq!(
+            plan.steps.keys().collect::<Vec<_>>(),
+            ["electronic-circuit"]
+        );
+        assert_close(plan.raw_inputs["copper-cable"], 135.0);
+        assert_close(plan.raw_inputs["iron-plate"], 45.0);
+
+        request
+            .supplied
+            .insert("copper-cable".into(), Supply { limit: Some(120.0) });
+        let err = ProductionPlan::new(&database(), &request).unwrap_err();
+        assert!(matches!(err, Error::SupplyLimitExceeded { item, .. } if item == "copper-cable"));
+    }
+
+    #[test]
+    fn exact_ratios() {
+        use crate::number::Rational;
+
+        let mut request = PlanRequest::<Rational> {
+            targets: BTreeMap::from([
+                ("electronic-circuit".into(), Rational::new(15, 2)),
+                ("uranium-238".into(), Rational::new(3, 1)),
+            ]),
+            ..Default::default()
+        };
+        request
+            .machines
+            .insert("crafting".into(), "assembling-machine-2".into());
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+
+        assert_eq!(
+            plan.steps["electronic-circuit"].machines,
+            Rational::new(5, 1)
+        );
+        assert_eq!(plan.steps["copper-cable"].machines.to_string(), "7 1/2");
+        // 3 / 0.993 crafts per second, with no rounding
+        assert_eq!(
+            plan.steps["uranium-processing"].crafts_per_second,
+            Rational::new(1000, 331)
+        );
+        assert_eq!(
+            plan.steps["uranium-processing"].machines.to_string(),
+            "36 84/331"
+        );
+    }
+
+    #[test]
+    fn ambiguous_recipe() {
+        let err =
+            ProductionPlan::new(&database(), &request(&[("petroleum-gas", 1.0)], &[])).unwrap_err();
+        assert!(matches!(err, Error::AmbiguousRecipe { item, .. } if item == "petroleum-gas"));
+    }
+
+    #[test]
+    fn ambiguous_machine() {
+        let mut request = request(&[("electronic-circuit", 1.0)], &[]);
+        request.machines.clear();
+        let err = ProductionPlan::new(&database(), &request).unwrap_err();
+        assert!(matches!(err, Error::MissingMachine { category, .. } if category == "crafting"));
+    }
+
+    #[test]
+    fn unsupported_category() {
+        let mut request = request(&[("plastic-bar", 1.0)], &[]);
+        request
+            .policy
+            .recipes
+            .insert("petroleum-gas".into(), "basic-oil-processing".into());
+        request
+            .machines
+            .insert("chemistry".into(), "assembling-machine-2".into());
+        let err = ProductionPlan::new(&database(), &request).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedCategory { .. }));
+    }
+
+    #[test]
+    fn recipe_cycle() {
+        let mut request = request(&[("light-oil", 1.0)], &[]);
+        // crack light oil from heavy oil, but source heavy oil from light oil
+        let mut database = database();
+        database.recipes.insert(
+            "light-to-heavy".into(),
+            recipe(
+                "light-to-heavy",
+                "chemistry",
+                1.0,
+                &[("light-oil", ItemKind::Fluid, 10)],
+                &[("heavy-oil", ItemKind::Fluid, 10, 1.0)],
+            ),
+        );
+        request
+            .policy
+            .recipes
+            .insert("light-oil".into(), "heavy-oil-cracking".into());
+        request
+            .policy
+            .recipes
+            .insert("heavy-oil".into(), "light-to-heavy".into());
+        let err = ProductionPlan::new(&database, &request).unwrap_err();
+        assert!(matches!(err, Error::RecipeCycle(_)));
+    }
+}
@@ -0,0 +1,510 @@
+//! Plan files.
+//!
+//! A factory module design is worth keeping: checked into a repository,
+//! reviewed like code, and re-planned when a mod update changes its recipes.
+//! A [`PlanFile`] declares one in TOML, YAML or JSON: the data dump and
+//! difficulty it was designed against, and a [`PlanRequest`] giving its
+//! targets, machines, module loadouts, supplied inputs and recipe policy.
+//!
+//! ```toml
+//! data = "../prototype-data"
+//! difficulty = "normal"
+//!
+//! [targets]
+//! electronic-circuit = 45
+//!
+//! [machines]
+//! crafting = "assembling-machine-2"
+//!
+//! [supplied.iron-plate]
+//! [supplied.copper-plate]
+//! limit = 70
+//! ```
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    number::Number,
+    plan::{PlanRequest, ProductionPlan},
+    recipe::Difficulty,
+    solver::SolverOptions,
+    Error, Result,
+};
+
+/// The formats in which a plan file may be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl PlanFormat {
+    /// The format implied by a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A declarative factory module design.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "N: Deserialize<'de> + Default"))]
+pub struct PlanFile<N = f64> {
+    /// The data dump the plan was designed against.
+    ///
+    /// When the file is [loaded][PlanFile::load], a relative path is resolved
+    /// against the directory containing the plan file; when it is
+    /// [saved][PlanFile::save], it is written relative to that directory.
+    pub data: PathBuf,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Plan by linear programming with these options, rather than by walking
+    /// the recipe tree.
+    #[serde(default)]
+    pub solver: Option<SolverOptions>,
+    #[serde(flatten)]
+    pub request: PlanRequest<N>,
+}
+
+/// A reference in a plan file which the data dump does not satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// No recipe produces or consumes the item.
+    UnknownItem(String),
+    UnknownRecipe(String),
+    RecipeDoesNotProduce {
+        recipe: String,
+        item: String,
+    },
+    /// No machine crafts the recipe category.
+    UnknownCategory(String),
+    UnknownMachine(String),
+    UnsupportedCategory {
+        machine: String,
+        category: String,
+    },
+    UnknownModule(String),
+    UnknownBeacon(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownItem(item) => write!(f, "unknown item \"{item}\""),
+            Self::UnknownRecipe(recipe) => write!(f, "unknown recipe \"{recipe}\""),
+            Self::RecipeDoesNotProduce { recipe, item } => {
+                write!(f, "recipe \"{recipe}\" does not produce \"{item}\"")
+            }
+            Self::UnknownCategory(category) => {
+                write!(f, "unknown recipe category \"{category}\"")
+            }
+            Self::UnknownMachine(machine) => write!(f, "unknown machine \"{machine}\""),
+            Self::UnsupportedCategory { machine, category } => {
+                write!(f, "machine \"{machine}\" cannot craft \"{category}\"")
+            }
+            Self::UnknownModule(module) => write!(f, "unknown module \"{module}\""),
+            Self::UnknownBeacon(beacon) => write!(f, "unknown beacon \"{beacon}\""),
+        }
+    }
+}
+
+/// Format a list of issues for an error message.
+pub(crate) fn explain(issues: &[Issue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Resolve `.` and `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Express `path`, relative to the working directory, relative to `dir`
+/// instead.
+///
+/// Absolute paths are made relative too, unless they share nothing with `dir`
+/// but the root, such as a dump installed elsewhere on the system. Returns
+/// `None` if the working directory is unavailable.
+fn relative_to(path: &Path, dir: &Path) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    let absolute = normalize(&cwd.join(path));
+    let dir = normalize(&cwd.join(dir));
+
+    let common = absolute
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let root = absolute
+        .components()
+        .take_while(|component| !matches!(component, Component::Normal(_)))
+        .count();
+    if path.is_absolute() && common <= root {
+        return Some(path.to_owned());
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(absolute.components().skip(common));
+    Some(relative)
+}
+
+impl<N: Serialize> PlanFile<N> {
+    pub fn render(&self, format: PlanFormat) -> Result<String> {
+        let rendered = match format {
+            PlanFormat::Toml => toml::to_string(self).map_err(|err| Box::new(err) as _),
+            PlanFormat::Yaml => serde_yaml::to_string(self).map_err(|err| Box::new(err) as _),
+            PlanFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|err| Box::new(err) as _)
+            }
+        };
+        rendered.map_err(Error::SerializePlanFile)
+    }
+}
+
+impl<N: Serialize + Clone> PlanFile<N> {
+    /// Write a plan file, choosing its format by extension.
+    ///
+    /// A relative data dump path is taken to be relative to the working
+    /// directory, as it is after [loading][PlanFile::load], and is written
+    /// relative to the directory containing `path`, so that loading the saved
+    /// file finds the same dump.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let format =
+            PlanFormat::from_path(path).ok_or_else(|| Error::UnknownPlanFormat(path.into()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let rendered = match relative_to(&self.data, dir) {
+            Some(data) if data != self.data => PlanFile {
+                data,
+                ..self.clone()
+            }
+            .render(format)?,
+            _ => self.render(format)?,
+        };
+        std::fs::write(path, rendered).map_err(|inner| Error::WriteFile {
+            path: path.into(),
+            inner,
+        })
+    }
+}
+
+impl<N: DeserializeOwned + Default> PlanFile<N> {
+    pub fn parse(text: &str, format: PlanFormat) -> Result<Self> {
+        let parsed = match format {
+            PlanFormat::Toml => toml::from_str(text).map_err(|err| Box::new(err) as _),
+            PlanFormat::Yaml => serde_yaml::from_str(text).map_err(|err| Box::new(err) as _),
+            PlanFormat::Json => serde_json::from_str(text).map_err(|err| Box::new(err) as _),
+        };
+        parsed.map_err(Error::ParsePlanFile)
+    }
+
+    /// Load a plan file, choosing its format by extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format =
+            PlanFormat::from_path(path).ok_or_else(|| Error::UnknownPlanFormat(path.into()))?;
+        let text = std::fs::read_to_string(path).map_err(|inner| Error::ReadFile {
+            path: path.into(),
+            inner,
+        })?;
+        let mut file = Self::parse(&text, format)?;
+        if let Some(dir) = path.parent() {
+            file.data = dir.join(&file.data);
+        }
+        Ok(file)
+    }
+}
+
+impl<N: Number> PlanFile<N> {
+    /// Load the data dump the plan was designed against.
+    pub fn database(&self) -> Result<PrototypeDatabase> {
+        PrototypeDatabase::load(&self.data, self.difficulty)
+    }
+
+    /// Check that every item, recipe, machine, module and beacon the plan
+    /// names exists in `database`.
+    ///
+    /// Fails with [`Error::InvalidPlanFile`], listing every issue found.
+    pub fn validate(&self, database: &PrototypeDatabase) -> Result<()> {
+        let mut issues = Vec::new();
+        let request = &self.request;
+
+        let items = database
+            .recipes
+            .values()
+            .flat_map(|recipe| {
+                let ingredients = recipe.ingredients.iter().map(|i| i.name.as_str());
+                let products = recipe.products.iter().map(|p| p.name.as_str());
+                ingredients.chain(products)
+            })
+            .collect::<BTreeSet<_>>();
+        let byproducts = self
+            .solver
+            .iter()
+            .flat_map(|options| options.byproducts.keys());
+        for item in request
+            .targets
+            .keys()
+            .chain(request.supplied.keys())
+            .chain(byproducts)
+        {
+            if !items.contains(item.as_str()) {
+                issues.push(Issue::UnknownItem(item.clone()));
+            }
+        }
+
+        for (item, name) in &request.policy.recipes {
+            match database.recipe(name) {
+                None => issues.push(Issue::UnknownRecipe(name.clone())),
+                Some(recipe) if recipe.product(item).is_none() => {
+                    issues.push(Issue::RecipeDoesNotProduce {
+                        recipe: name.clone(),
+                        item: item.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        for name in request
+            .policy
+            .excluded_recipes
+            .iter()
+            .chain(request.modules.keys())
+        {
+            if database.recipe(name).is_none() {
+                issues.push(Issue::UnknownRecipe(name.clone()));
+            }
+        }
+
+        for (category, name) in &request.machines {
+            if !database
+                .machines
+                .values()
+                .any(|machine| machine.can_craft(category))
+            {
+                issues.push(Issue::UnknownCategory(category.clone()));
+            }
+            match database.machine(name) {
+                None => issues.push(Issue::UnknownMachine(name.clone())),
+                Some(machine) if !machine.can_craft(category) => {
+                    issues.push(Issue::UnsupportedCategory {
+                        machine: name.clone(),
+                        category: category.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        for loadout in request.modules.values() {
+            let beacon_modules = loadout.beacons.iter().flat_map(|beacon| &beacon.modules);
+            for module in loadout.modules.iter().chain(beacon_modules) {
+                if database.module(module).is_none() {
+                    issues.push(Issue::UnknownModule(module.clone()));
+                }
+            }
+            for beacon in &loadout.beacons {
+                if database.beacon(&beacon.beacon).is_none() {
+                    issues.push(Issue::UnknownBeacon(beacon.beacon.clone()));
+                }
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            issues.dedup();
+            Err(Error::InvalidPlanFile(issues))
+        }
+    }
+
+    /// Validate the plan against `database`, then plan it.
+    pub fn plan(&self, database: &PrototypeDatabase) -> Result<ProductionPlan<N>> {
+        self.validate(database)?;
+        match &self.solver {
+            Some(options) => ProductionPlan::solve(database, &self.request, options),
+            None => ProductionPlan::new(database, &self.request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        number::Rational,
+        plan::tests::{assert_close, database},
+        solver::Objective,
+    };
+
+    const TOML: &str = r#"
+        data = "prototype-data"
+
+        [targets]
+        electronic-circuit = 45
+
+        [machines]
+        crafting = "assembling-machine-2"
+
+        [modules.copper-cable]
+        modules = ["speed-module", "speed-module"]
+
+        [supplied.copper-plate]
+        limit = 70
+    "#;
+
+    const YAML: &str = "
+        data: prototype-data
+        difficulty: normal
+        targets:
+          electronic-circuit: 45
+        machines:
+          crafting: assembling-machine-2
+        modules:
+          copper-cable:
+            modules: [speed-module, speed-module]
+        supplied:
+          copper-plate:
+            limit: 70
+    ";
+
+    #[test]
+    fn formats_agree() {
+        let toml = PlanFile::<f64>::parse(TOML, PlanFormat::Toml).unwrap();
+        let yaml = PlanFile::<f64>::parse(YAML, PlanFormat::Yaml).unwrap();
+        let json = PlanFile::<f64>::parse(&serde_json::to_string(&toml).unwrap(), PlanFormat::Json)
+            .unwrap();
+        assert_eq!(toml, yaml);
+        assert_eq!(toml, json);
+        for format in [PlanFormat::Toml, PlanFormat::Yaml] {
+            let rendered = toml.render(format).unwrap();
+            assert_eq!(PlanFile::parse(&rendered, format).unwrap(), toml);
+        }
+
+        assert_eq!(toml.difficulty, Difficulty::Normal);
+        assert_eq!(toml.request.targets["electronic-circuit"], 45.0);
+        assert_eq!(toml.request.supplied["copper-plate"].limit, Some(70.0));
+        // the recipe policy defaults apply
+        assert!(toml.request.policy.exclude_barreling);
+        assert_eq!(
+            PlanFormat::from_path(Path::new("modules/red.yml")),
+            Some(PlanFormat::Yaml)
+        );
+    }
+
+    #[test]
+    fn save_keeps_data_path() {
+        let root = std::env::temp_dir().join(format!("plan-file-{}", std::process::id()));
+        let original = root.join("plans/red.toml");
+        let copy = root.join("copies/nested/red.yaml");
+        std::fs::create_dir_all(original.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        std::fs::write(&original, TOML.replace("prototype-data", "../data")).unwrap();
+
+        let loaded = PlanFile::<f64>::load(&original).unwrap();
+        loaded.save(&original).unwrap();
+        loaded.save(&copy).unwrap();
+        let reloaded = PlanFile::<f64>::load(&original).unwrap();
+        let copied = PlanFile::<f64>::load(&copy).unwrap();
+        let text = std::fs::read_to_string(&copy).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(normalize(&reloaded.data), root.join("data"));
+        assert_eq!(normalize(&copied.data), root.join("data"));
+        assert!(text.contains("data: ../../data\n"), "{text}");
+        assert_eq!(copied.request, loaded.request);
+
+        // a dump elsewhere on the system stays where it is
+        let elsewhere = Path::new("/srv/factorio/data");
+        assert_eq!(relative_to(elsewhere, &root), Some(elsewhere.to_owned()));
+    }
+
+    #[test]
+    fn plans() {
+        let database = database();
+        let file = PlanFile::<Rational>::parse(TOML, PlanFormat::Toml).unwrap();
+        let plan = file.plan(&database).unwrap();
+        assert_eq!(plan.raw_inputs["copper-plate"], Rational::new(135, 2));
+
+        let mut file = PlanFile::<f64>::parse(TOML, PlanFormat::Toml).unwrap();
+        file.solver = Some(SolverOptions {
+            objective: Objective::Machines,
+            ..Default::default()
+        });
+        let plan = file.plan(&database).unwrap();
+        assert_close(plan.raw_inputs["iron-plate"], 45.0);
+    }
+
+    #[test]
+    fn validation() {
+        let database = database();
+        let mut file = PlanFile::<f64>::parse(TOML, PlanFormat::Toml).unwrap();
+        file.request.targets.insert("unobtainium".into(), 1.0);
+        file.request
+            .machines
+            .insert("smelting".into(), "assembling-machine-2".into());
+        file.request
+            .policy
+            .recipes
+            .insert("plastic-bar".into(), "copper-cable".into());
+        file.request
+            .modules
+            .get_mut("copper-cable")
+            .unwrap()
+            .modules
+            .push("quality-module".into());
+
+        let Err(Error::InvalidPlanFile(issues)) = file.plan(&database) else {
+            panic!("expected an invalid plan file");
+        };
+        assert_eq!(
+            issues,
+            [
+                Issue::UnknownItem("unobtainium".into()),
+                Issue::RecipeDoesNotProduce {
+                    recipe: "copper-cable".into(),
+                    item: "plastic-bar".into(),
+                },
+                Issue::UnknownCategory("smelting".into()),
+                Issue::UnsupportedCategory {
+                    machine: "assembling-machine-2".into(),
+                    category: "smelting".into(),
+                },
+                Issue::UnknownModule("quality-module".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Recipe selection policies.
+//!
+//! Many items have several producing recipes: solid fuel from light oil, heavy
+//! oil or petroleum gas, and whole families of alternatives in mod packs. A
+//! [`RecipePolicy`] decides which of them the planner, solver, cost table and
+//! graph tools may use, so that their results are deterministic and under the
+//! user's control.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cost::{recipe_cost, CostOptions, CostTable},
+    database::PrototypeDatabase,
+    recipe::NormalizedRecipe,
+    Error, Result,
+};
+
+fn true_() -> bool {
+    true
+}
+
+/// Which recipes may produce each item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipePolicy {
+    /// The recipe used to produce each item.
+    ///
+    /// An explicit choice overrides every other rule, so it may name a
+    /// barreling, hidden or excluded recipe.
+    #[serde(default)]
+    pub recipes: BTreeMap<String, String>,
+    /// Recipes which may not be used.
+    #[serde(default)]
+    pub excluded_recipes: BTreeSet<String>,
+    /// Exclude recipes which fill or empty fluid barrels.
+    #[serde(default = "true_")]
+    pub exclude_barreling: bool,
+    /// Exclude recipes marked `hidden`.
+    #[serde(default = "true_")]
+    pub exclude_hidden: bool,
+    /// Exclude recipes which produce nothing, such as fluid voiding.
+    #[serde(default = "true_")]
+    pub exclude_voiding: bool,
+    /// Where an item has several producing recipes, use only those enabled
+    /// at the start of the game, if there are any.
+    #[serde(default)]
+    pub prefer_enabled: bool,
+    /// Where an item has several producing recipes, use only the one with
+    /// the least raw-resource cost.
+    #[serde(default)]
+    pub prefer_cheapest: bool,
+}
+
+impl Default for RecipePolicy {
+    fn default() -> Self {
+        Self {
+            recipes: BTreeMap::new(),
+            excluded_recipes: BTreeSet::new(),
+            exclude_barreling: true,
+            exclude_hidden: true,
+            exclude_voiding: true,
+            prefer_enabled: false,
+            prefer_cheapest: false,
+        }
+    }
+}
+
+impl RecipePolicy {
+    /// A policy which admits every recipe and expresses no preference.
+    pub fn permissive() -> Self {
+        Self {
+            exclude_barreling: false,
+            exclude_hidden: false,
+            exclude_voiding: false,
+            ..Self::default()
+        }
+    }
+
+    /// `true` if the recipe is not excluded, whatever it produces.
+    pub fn admits(&self, recipe: &NormalizedRecipe) -> bool {
+        !(self.excluded_recipes.contains(&recipe.name)
+            || (self.exclude_barreling && recipe.is_barreling())
+            || (self.exclude_hidden && recipe.hidden)
+            || (self.exclude_voiding && recipe.is_voiding()))
+    }
+
+    /// The recipes which may produce `item`, in name order, before any
+    /// preference for the cheapest.
+    ///
+    /// Fails if an explicitly chosen recipe is unknown, or does not produce
+    /// the item.
+    pub fn producers<'a>(
+        &self,
+        database: &'a PrototypeDatabase,
+        item: &str,
+    ) -> Result<Vec<&'a NormalizedRecipe>> {
+        let produces = |recipe: &NormalizedRecipe| {
+            recipe
+                .net_amounts::<f64>()
+                .get(item)
+                .is_some_and(|&amount| amount > 0.0)
+        };
+
+        if let Some(name) = self.recipes.get(item) {
+            let recipe = database
+                .recipe(name)
+                .ok_or_else(|| Error::UnknownRecipe(name.clone()))?;
+            if !produces(recipe) {
+                return Err(Error::RecipeDoesNotProduce {
+                    recipe: name.clone(),
+                    item: item.into(),
+                });
+            }
+            return Ok(vec![recipe]);
+        }
+
+        let mut producers = database
+            .producers(item)
+            .filter(|recipe| self.admits(recipe) && produces(recipe))
+            .collect::<Vec<_>>();
+        if self.prefer_enabled && producers.iter().any(|recipe| recipe.enabled) {
+            producers.retain(|recipe| recipe.enabled);
+        }
+        Ok(producers)
+    }
+}
+
+/// A [`RecipePolicy`] applied to a database.
+#[derive(Debug, Clone)]
+pub struct RecipeSelector<'a> {
+    database: &'a PrototypeDatabase,
+    policy: RecipePolicy,
+    /// Computed only if the policy prefers the cheapest recipe.
+    costs: Option<CostTable>,
+}
+
+impl<'a> RecipeSelector<'a> {
+    /// Apply `policy` to `database`, treating `raw` items as raw when
+    /// comparing recipe costs.
+    pub fn new(
+        database: &'a PrototypeDatabase,
+        policy: &RecipePolicy,
+        raw: &BTreeSet<String>,
+    ) -> Result<Self> {
+        let costs = if policy.prefer_cheapest {
+            let options = CostOptions {
+                policy: policy.clone(),
+                raw: raw.clone(),
+            };
+            Some(CostTable::new(database, &options)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            database,
+            policy: policy.clone(),
+            costs,
+        })
+    }
+
+    /// The recipes which may produce `item`, in name order.
+    ///
+    /// If the policy prefers the cheapest recipe, there is only one whenever
+    /// any producer's cost is known; ties go to the recipe which takes less
+    /// crafting time, then to the first by name.
+    pub fn candidates(&self, item: &str) -> Result<Vec<&'a NormalizedRecipe>> {
+        let producers = self.policy.producers(self.database, item)?;
+        let Some(costs) = &self.costs else {
+            return Ok(producers);
+        };
+
+        let mut cheapest = None;
+        for &recipe in &producers {
+            let Some(cost) = recipe_cost(recipe, item, &costs.items) else {
+                continue;
+            };
+            if cheapest
+                .as_ref()
+                .is_none_or(|(_, best)| cost.is_cheaper_than(best))
+            {
+                cheapest = Some((recipe, cost));
+            }
+        }
+        // recipes whose cost cannot be resolved are left for the caller
+        Ok(match cheapest {
+            Some((recipe, _)) => vec![recipe],
+            None => producers,
+        })
+    }
+
+    /// The single recipe which produces `item`, or `None` if it is raw.
+    ///
+    /// Fails if the policy leaves a choice between several recipes.
+    pub fn choose(&self, item: &str) -> Result<Option<&'a NormalizedRecipe>> {
+        let candidates = self.candidates(item)?;
+        match candidates.as_slice() {
+            [] => Ok(None),
+            [recipe] => Ok(Some(recipe)),
+            _ => Err(Error::AmbiguousRecipe {
+                item: item.into(),
+                candidates: candidates
+                    .iter()
+                    .map(|recipe| recipe.name.clone())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        plan::tests::{database, recipe},
+        recipe::ItemKind,
+    };
+
+    /// The test database, with solid fuel from each oil product.
+    fn database_with_solid_fuel() -> PrototypeDatabase {
+        let mut database = database();
+        for (name, fluid, amount, enabled) in [
+            ("solid-fuel-from-heavy-oil", "heavy-oil", 20, false),
+            ("solid-fuel-from-light-oil", "light-oil", 10, true),
+            ("solid-fuel-from-petroleum-gas", "petroleum-gas", 20, false),
+        ] {
+            let mut recipe = recipe(
+                name,
+                "chemistry",
+                2.0,
+                &[(fluid, ItemKind::Fluid, amount)],
+                &[("solid-fuel", ItemKind::Item, 1, 1.0)],
+            );
+            recipe.enabled = enabled;
+            database.recipes.insert(name.into(), recipe);
+        }
+        let mut flare = recipe(
+            "flare-stack",
+            "chemistry",
+            1.0,
+            &[("heavy-oil", ItemKind::Fluid, 100)],
+            &[],
+        );
+        flare.hidden = true;
+        database.recipes.insert("flare-stack".into(), flare);
+        database
+    }
+
+    fn names(recipes: Vec<&NormalizedRecipe>) -> Vec<&str> {
+        recipes.iter().map(|recipe| recipe.name.as_str()).collect()
+    }
+
+    #[test]
+    fn ambiguous_by_default() {
+        let database = database_with_solid_fuel();
+        let policy = RecipePolicy::default();
+        let selector = RecipeSelector::new(&database, &policy, &BTreeSet::new()).unwrap();
+
+        assert_eq!(selector.candidates("solid-fuel").unwrap().len(), 3);
+        let err = selector.choose("solid-fuel").unwrap_err();
+        assert!(matches!(err, Error::AmbiguousRecipe { .. }));
+    }
+
+    #[test]
+    fn explicit_choice() {
+        let database = database_with_solid_fuel();
+        let policy = RecipePolicy {
+            recipes: BTreeMap::from([("solid-fuel".into(), "solid-fuel-from-heavy-oil".into())]),
+            ..Default::default()
+        };
+        let selector = RecipeSelector::new(&database, &policy, &BTreeSet::new()).unwrap();
+        let recipe = selector.choose("solid-fuel").unwrap().unwrap();
+        assert_eq!(recipe.name, "solid-fuel-from-heavy-oil");
+    }
+
+    #[test]
+    fn preferences() {
+        let database = database_with_solid_fuel();
+
+        let enabled = RecipePolicy {
+            prefer_enabled: true,
+            ..Default::default()
+        };
+        let producers = enabled.producers(&database, "solid-fuel").unwrap();
+        assert_eq!(names(producers), ["solid-fuel-from-light-oil"]);
+
+        // 10 light oil cost 33 1/3 crude oil and water, against 44 4/9 crude
+        // oil for 20 petroleum gas and 120 for 20 heavy oil
+        let cheapest = RecipePolicy {
+            prefer_cheapest: true,
+            ..Default::default()
+        };
+        let selector = RecipeSelector::new(&database, &cheapest, &BTreeSet::new()).unwrap();
+        let recipe = selector.choose("solid-fuel").unwrap().unwrap();
+        assert_eq!(recipe.name, "solid-fuel-from-light-oil");
+
+        let excluded = RecipePolicy {
+            excluded_recipes: BTreeSet::from(["solid-fuel-from-light-oil".into()]),
+            ..cheapest
+        };
+        let selector = RecipeSelector::new(&database, &excluded, &BTreeSet::new()).unwrap();
+        let recipe = selector.choose("solid-fuel").unwrap().unwrap();
+        assert_eq!(recipe.name, "solid-fuel-from-petroleum-gas");
+    }
+
+    #[test]
+    fn blacklists() {
+        let database = database_with_solid_fuel();
+        let flare = database.recipe("flare-stack").unwrap();
+        assert!(flare.is_voiding());
+
+        assert!(!RecipePolicy::default().admits(flare));
+        let hidden_only = RecipePolicy {
+            exclude_voiding: false,
+            ..Default::default()
+        };
+        assert!(!hidden_only.admits(flare));
+        assert!(RecipePolicy::permissive().admits(flare));
+    }
+}
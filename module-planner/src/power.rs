@@ -0,0 +1,381 @@
+//! Power, fuel and pollution.
+//!
+//! A [`PowerReport`] totals what a [`ProductionPlan`] costs to run: the
+//! electric power its machines and beacons draw, the fuel its burner machines
+//! consume, and the pollution they all emit. Grids are sized from these
+//! numbers, so the report also counts the generators needed to cover the
+//! electric load.
+
+use std::collections::BTreeMap;
+
+use data_parser::models;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase, machine::EnergySource, number::Number, plan::ProductionPlan,
+    units::parse_energy, Error, Result,
+};
+
+/// The heat capacity of steam, in joules per unit per degree.
+const STEAM_HEAT_CAPACITY: f64 = 200.0;
+
+/// The temperature of water from an offshore pump, in degrees.
+const WATER_TEMPERATURE: f64 = 15.0;
+
+/// The average output of a solar panel over a day, as a fraction of its peak
+/// output.
+const SOLAR_AVERAGE: f64 = 0.7;
+
+/// An item which burner machines can burn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fuel {
+    pub name: String,
+    pub category: String,
+    /// The energy released by burning one item, in joules.
+    pub value: f64,
+}
+
+impl Fuel {
+    /// Normalize an item, or `None` if it is not a fuel.
+    pub fn new(item: &models::Item) -> Result<Option<Self>> {
+        let (Some(value), Some(category)) = (&item.fuel_value, &item.fuel_category) else {
+            return Ok(None);
+        };
+        let value = parse_energy(value, 'J')?;
+        if value <= 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            name: item.name.clone(),
+            category: category.clone(),
+            value,
+        }))
+    }
+}
+
+/// An entity which supplies electric power.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generator {
+    pub name: String,
+    /// Average power output, in watts.
+    pub power: f64,
+}
+
+impl Generator {
+    /// Normalize a generator, such as a steam engine, running on steam at its
+    /// maximum temperature, or `None` if it supplies no power.
+    pub fn new(generator: &models::Generator) -> Result<Option<Self>> {
+        let power = match &generator.max_power_output {
+            Some(power) => parse_energy(power, 'W')?,
+            None => {
+                generator.fluid_usage_per_tick
+                    * 60.0
+                    * (generator.maximum_temperature - WATER_TEMPERATURE)
+                    * STEAM_HEAT_CAPACITY
+                    * generator.effectivity
+            }
+        };
+        Ok(Self::supplying(&generator.name, power))
+    }
+
+    /// Normalize a solar panel, averaging its output over a day, or `None` if
+    /// it supplies no power.
+    ///
+    /// This assumes accumulators store the daytime surplus for the night.
+    pub fn solar(panel: &models::SolarPanel) -> Result<Option<Self>> {
+        let power = parse_energy(&panel.production, 'W')? * SOLAR_AVERAGE;
+        Ok(Self::supplying(&panel.name, power))
+    }
+
+    fn supplying(name: &str, power: f64) -> Option<Self> {
+        // a generator without output could never cover any demand
+        (power > 0.0).then(|| Self {
+            name: name.into(),
+            power,
+        })
+    }
+}
+
+/// The power, fuel and pollution of one recipe step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepPower<N = f64> {
+    /// Electric power drawn by the machines, their drain and their beacons,
+    /// in watts.
+    pub electric: N,
+    /// Fuel energy burned by burner machines, in watts.
+    pub burner: N,
+    pub pollution_per_minute: N,
+}
+
+/// The fuel burned for one fuel category.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FuelDemand<N = f64> {
+    /// Fuel energy burned, in watts.
+    pub power: N,
+    /// The rate at which each fuel of the category would be burned, were it
+    /// the only fuel, in items per second.
+    pub items: BTreeMap<String, N>,
+}
+
+/// The power, fuel and pollution of a whole plan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PowerReport<N = f64> {
+    /// Per recipe step, by recipe name.
+    pub steps: BTreeMap<String, StepPower<N>>,
+    /// Total electric power, in watts.
+    pub electric: N,
+    /// Fuel burned, by fuel category.
+    pub fuel: BTreeMap<String, FuelDemand<N>>,
+    pub pollution_per_minute: N,
+    /// The number of each generator needed to supply the electric power.
+    pub generators: BTreeMap<String, N>,
+}
+
+impl<N: Number> PowerReport<N> {
+    /// Report on the power, fuel and pollution of `plan`.
+    ///
+    /// Every machine in a step draws its drain, including a final machine
+    /// which is not always busy. Machines powered by heat or fluid contribute
+    /// pollution only.
+    pub fn new(database: &PrototypeDatabase, plan: &ProductionPlan<N>) -> Result<Self> {
+        let mut report = Self::default();
+
+        for (name, step) in &plan.steps {
+            let recipe = database
+                .recipe(&step.recipe)
+                .ok_or_else(|| Error::UnknownRecipe(step.recipe.clone()))?;
+            let machine = database
+                .machine(&step.machine)
+                .ok_or_else(|| Error::UnknownMachine(step.machine.clone()))?;
+
+            let consumption = step.effects.consumption_multiplier();
+            let active =
+                step.machines.clone() * N::from_f64(machine.energy_usage) * consumption.clone();
+            // the step's power is that of its machines and their beacons
+            let beacons = step.power.clone() - active.clone();
+
+            let mut power = StepPower {
+                electric: beacons,
+                pollution_per_minute: step.machines.clone()
+                    * N::from_f64(machine.emissions_per_minute)
+                    * consumption
+                    * step.effects.pollution_multiplier()
+                    * N::from_f64(recipe.emissions_multiplier),
+                ..StepPower::default()
+            };
+            match &machine.energy_source {
+                EnergySource::Electric { drain } => {
+                    power.electric += active + step.machines.ceil() * N::from_f64(*drain);
+                }
+                EnergySource::Burner {
+                    fuel_category,
+                    effectivity,
+                } => {
+                    power.burner = active / N::from_f64(*effectivity);
+                    report.fuel.entry(fuel_category.clone()).or_default().power +=
+                        power.burner.clone();
+                }
+                EnergySource::Other(_) => {}
+            }
+
+            report.electric += power.electric.clone();
+            report.pollution_per_minute += power.pollution_per_minute.clone();
+            report.steps.insert(name.clone(), power);
+        }
+
+        for (category, demand) in &mut report.fuel {
+            demand.items = database
+                .fuels
+                .values()
+                .filter(|fuel| fuel.category == *category)
+                .map(|fuel| {
+                    let rate = demand.power.clone() / N::from_f64(fuel.value);
+                    (fuel.name.clone(), rate)
+                })
+                .collect();
+        }
+
+        report.generators = database
+            .generators
+            .values()
+            .map(|generator| {
+                let count = report.electric.clone() / N::from_f64(generator.power);
+                (generator.name.clone(), count)
+            })
+            .collect();
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{
+        machine::Machine,
+        modules::{BeaconLoadout, ModuleLoadout},
+        number::Rational,
+        plan::{
+            tests::{assert_close, database, recipe, request},
+            Supply,
+        },
+        recipe::ItemKind,
+    };
+
+    /// The test database, with smelting in stone furnaces and power from
+    /// steam engines and solar panels.
+    fn database_with_power() -> PrototypeDatabase {
+        let mut database = database();
+        let mut smelting = recipe(
+            "iron-plate",
+            "smelting",
+            3.2,
+            &[("iron-ore", ItemKind::Item, 1)],
+            &[("iron-plate", ItemKind::Item, 1, 1.0)],
+        );
+        smelting.emissions_multiplier = 2.0;
+        database.recipes.insert(smelting.name.clone(), smelting);
+        database.machines.insert(
+            "stone-furnace".into(),
+            Machine {
+                name: "stone-furnace".into(),
+                kind: "furnace".into(),
+                crafting_speed: 1.0,
+                crafting_categories: BTreeSet::from(["smelting".into()]),
+                energy_usage: 90e3,
+                base_productivity: 0.0,
+                module_slots: 0,
+                allowed_effects: BTreeSet::new(),
+                energy_source: EnergySource::Burner {
+                    fuel_category: "chemical".into(),
+                    effectivity: 1.0,
+                },
+                emissions_per_minute: 2.0,
+            },
+        );
+        for (name, value) in [("coal", 4e6), ("wood", 2e6)] {
+            database.fuels.insert(
+                name.into(),
+                Fuel {
+                    name: name.into(),
+                    category: "chemical".into(),
+                    value,
+                },
+            );
+        }
+        let steam_engine = models::Generator {
+            name: "steam-engine".into(),
+            r#type: "generator".into(),
+            fluid_usage_per_tick: 0.5,
+            maximum_temperature: 165.0,
+            effectivity: 1.0,
+            max_power_output: None,
+        };
+        let solar_panel = models::SolarPanel {
+            name: "solar-panel".into(),
+            r#type: "solar-panel".into(),
+            production: "60kW".into(),
+        };
+        for generator in [
+            Generator::new(&steam_engine).unwrap().unwrap(),
+            Generator::solar(&solar_panel).unwrap().unwrap(),
+        ] {
+            database
+                .generators
+                .insert(generator.name.clone(), generator);
+        }
+        database
+    }
+
+    #[test]
+    fn steam_engine_output() {
+        let database = database_with_power();
+        assert_close(database.generators["steam-engine"].power, 900e3);
+        assert_close(database.generators["solar-panel"].power, 42e3);
+    }
+
+    #[test]
+    fn skips_powerless_generators() {
+        let panel = models::SolarPanel {
+            name: "solar-panel".into(),
+            r#type: "solar-panel".into(),
+            production: "0kW".into(),
+        };
+        assert_eq!(Generator::solar(&panel).unwrap(), None);
+    }
+
+    #[test]
+    fn electric_power() {
+        let database = database_with_power();
+        let mut request = request(&[("iron-gear-wheel", Rational::new(3, 1))], &[]);
+        request
+            .supplied
+            .insert("iron-plate".into(), Supply::default());
+        let plan = ProductionPlan::new(&database, &request).unwrap();
+        let report = PowerReport::new(&database, &plan).unwrap();
+
+        // 2 assemblers at 150 kW, each with a 5 kW drain
+        let step = &report.steps["iron-gear-wheel"];
+        assert_eq!(step.electric, Rational::new(310_000, 1));
+        assert_eq!(step.pollution_per_minute, Rational::new(6, 1));
+        assert_eq!(report.electric, Rational::new(310_000, 1));
+        assert_eq!(report.generators["steam-engine"], Rational::new(310, 900));
+        assert!(report.fuel.is_empty());
+    }
+
+    #[test]
+    fn partial_machines_drain() {
+        let database = database_with_power();
+        let plan =
+            ProductionPlan::<f64>::new(&database, &request(&[("iron-gear-wheel", 2.0)], &[]))
+                .unwrap();
+        let report = PowerReport::new(&database, &plan).unwrap();
+
+        // 1 1/3 assemblers, and both drain
+        assert_close(report.electric, 4.0 / 3.0 * 150e3 + 2.0 * 5e3);
+    }
+
+    #[test]
+    fn burner_fuel_and_pollution() {
+        let database = database_with_power();
+        let plan = ProductionPlan::new(&database, &request(&[("iron-plate", 1.25)], &[])).unwrap();
+        let report = PowerReport::new(&database, &plan).unwrap();
+
+        // 4 furnaces burning 90 kW each
+        let chemical = &report.fuel["chemical"];
+        assert_close(chemical.power, 360e3);
+        assert_close(chemical.items["coal"], 0.09);
+        assert_close(chemical.items["wood"], 0.18);
+        assert_close(report.electric, 0.0);
+        // 2/min per furnace, doubled by the recipe
+        assert_close(report.pollution_per_minute, 16.0);
+    }
+
+    #[test]
+    fn modules_and_beacons() {
+        let database = database_with_power();
+        let mut request = request(&[("electronic-circuit", 1.5)], &[]);
+        request.modules.insert(
+            "electronic-circuit".into(),
+            ModuleLoadout {
+                modules: vec!["efficiency-module".into(); 2],
+                beacons: vec![BeaconLoadout {
+                    beacon: "beacon".into(),
+                    count: 1,
+                    modules: vec!["speed-module".into(); 2],
+                }],
+            },
+        );
+        let plan = ProductionPlan::new(&database, &request).unwrap();
+        let report = PowerReport::new(&database, &plan).unwrap();
+
+        // speed 1.2, so 1/1.2 machines; consumption 1 - 0.6 + 0.5 = 0.9,
+        // pollution unaffected by modules except through consumption
+        let machines = 1.0 / 1.2;
+        let step = &report.steps["electronic-circuit"];
+        assert_close(step.electric, machines * (150e3 * 0.9 + 480e3) + 5e3);
+        assert_close(step.pollution_per_minute, machines * 3.0 * 0.9);
+    }
+}
@@ -0,0 +1,248 @@
+//! Integer ratios for perfectly balanced builds.
+//!
+//! A tileable block is one in which every machine runs at 100%: the machine
+//! count of every recipe is a whole number. Exact planning makes every machine
+//! count a [`Rational`] proportional to the target rate, so the smallest
+//! balanced block is found by scaling the targets by the least factor which
+//! clears every denominator.
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    number::Rational,
+    plan::{PlanRequest, ProductionPlan},
+    recipe::ItemKind,
+    Error, Result,
+};
+
+/// Limits on the size of a balanced build.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RatioBounds {
+    /// The greatest total number of machines.
+    #[serde(default)]
+    pub max_machines: Option<u64>,
+    /// The greatest number of belts any solid item may need, whether it is
+    /// produced, consumed, or supplied as a raw input.
+    #[serde(default)]
+    pub max_belts: Option<BeltLimit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeltLimit {
+    pub belts: Rational,
+    /// The throughput of one belt, in items per second.
+    pub items_per_second: Rational,
+}
+
+/// The smallest build in which every machine runs at 100%.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalancedBuild {
+    /// The factor by which the requested targets were scaled.
+    pub scale: Rational,
+    /// The balanced plan. Its targets are the scaled output rates, and the
+    /// machine count of every step is a whole number.
+    pub plan: ProductionPlan<Rational>,
+}
+
+impl BalancedBuild {
+    /// Find the smallest balanced build producing the requested targets in
+    /// the requested proportions.
+    ///
+    /// Only the ratio between the target rates matters: targets of `{gear: 1,
+    /// circuit: 2}` and `{gear: 5, circuit: 10}` yield the same build.
+    ///
+    /// Fails if the smallest balanced build exceeds the bounds, since every
+    /// other balanced build is a multiple of it.
+    pub fn new(
+        database: &PrototypeDatabase,
+        request: &PlanRequest<Rational>,
+        bounds: &RatioBounds,
+    ) -> Result<Self> {
+        let unit = ProductionPlan::new(database, request)?;
+        let scale = Rational(least_integer_scale(
+            unit.steps.values().map(|step| &step.machines.0),
+        ));
+
+        let scaled = PlanRequest {
+            targets: request
+                .targets
+                .iter()
+                .map(|(item, rate)| (item.clone(), rate.clone() * scale.clone()))
+                .collect(),
+            ..request.clone()
+        };
+        let build = Self {
+            scale,
+            plan: ProductionPlan::new(database, &scaled)?,
+        };
+        build.check(bounds)?;
+        Ok(build)
+    }
+
+    /// The whole number of machines used by each recipe.
+    pub fn machines(&self) -> BTreeMap<&str, BigInt> {
+        self.plan
+            .steps
+            .iter()
+            .map(|(recipe, step)| (recipe.as_str(), step.machines.trunc()))
+            .collect()
+    }
+
+    /// The total number of machines.
+    pub fn total_machines(&self) -> BigInt {
+        self.machines().into_values().sum()
+    }
+
+    fn check(&self, bounds: &RatioBounds) -> Result<()> {
+        if let Some(limit) = bounds.max_machines {
+            let required = self.total_machines();
+            if required > BigInt::from(limit) {
+                return Err(Error::TooManyMachines { required, limit });
+            }
+        }
+
+        if let Some(limit) = &bounds.max_belts {
+            // raw inputs are consumed by some step, so they have flows too
+            let belt_rates = self
+                .plan
+                .flows
+                .iter()
+                .filter(|(_, flow)| flow.kind == ItemKind::Item)
+                .map(|(item, flow)| (item, flow.produced.clone().max(flow.consumed.clone())));
+            for (item, rate) in belt_rates {
+                let belts = rate / limit.items_per_second.clone();
+                if belts > limit.belts {
+                    return Err(Error::TooManyBelts {
+                        item: item.clone(),
+                        belts,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The least positive rational `t` such that `t * value` is an integer for
+/// every value.
+fn least_integer_scale<'a>(values: impl IntoIterator<Item = &'a BigRational>) -> BigRational {
+    let values = values
+        .into_iter()
+        .filter(|value| !value.is_zero())
+        .collect::<Vec<_>>();
+    let denominators = values
+        .iter()
+        .fold(BigInt::one(), |lcm, value| lcm.lcm(value.denom()));
+    let numerators = values.iter().fold(BigInt::zero(), |gcd, value| {
+        gcd.gcd(&(value.numer() * &denominators / value.denom()))
+    });
+    if numerators.is_zero() {
+        return BigRational::one();
+    }
+    BigRational::new(denominators, numerators)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        number::Number,
+        plan::tests::{database, request},
+    };
+
+    #[test]
+    fn red_circuits() {
+        let build = BalancedBuild::new(
+            &database(),
+            &request(&[("electronic-circuit", Rational::from_u32(100))], &[]),
+            &RatioBounds::default(),
+        )
+        .unwrap();
+
+        // the classic 3:2 cable to circuit assembler ratio
+        let machines = build.machines();
+        assert_eq!(machines["copper-cable"], BigInt::from(3));
+        assert_eq!(machines["electronic-circuit"], BigInt::from(2));
+        assert_eq!(
+            build.plan.targets["electronic-circuit"],
+            Rational::new(3, 1)
+        );
+    }
+
+    #[test]
+    fn proportional_targets() {
+        let build = BalancedBuild::new(
+            &database(),
+            &request(
+                &[
+                    ("electronic-circuit", Rational::from_u32(2)),
+                    ("iron-gear-wheel", Rational::from_u32(1)),
+                ],
+                &[],
+            ),
+            &RatioBounds::default(),
+        )
+        .unwrap();
+
+        let machines = build.machines();
+        assert_eq!(machines["copper-cable"], BigInt::from(3));
+        assert_eq!(machines["electronic-circuit"], BigInt::from(2));
+        assert_eq!(machines["iron-gear-wheel"], BigInt::from(1));
+        assert_eq!(build.total_machines(), BigInt::from(6));
+        assert_eq!(build.plan.targets["iron-gear-wheel"], Rational::new(3, 2));
+    }
+
+    #[test]
+    fn every_machine_is_whole() {
+        let mut request = request(&[("uranium-235", Rational::from_u32(1))], &[]);
+        request
+            .policy
+            .recipes
+            .insert("uranium-235".into(), "kovarex-enrichment-process".into());
+        request
+            .policy
+            .recipes
+            .insert("uranium-238".into(), "uranium-processing".into());
+        let build = BalancedBuild::new(&database(), &request, &RatioBounds::default()).unwrap();
+
+        for step in build.plan.steps.values() {
+            assert!(step.machines.is_positive());
+            assert_eq!(Rational(step.machines.trunc().into()), step.machines);
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let request = request(&[("electronic-circuit", Rational::from_u32(1))], &[]);
+        let err = BalancedBuild::new(
+            &database(),
+            &request,
+            &RatioBounds {
+                max_machines: Some(4),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::TooManyMachines { limit: 4, .. }));
+
+        // the balanced build moves 9 cables per second
+        let limit = |belts| RatioBounds {
+            max_belts: Some(BeltLimit {
+                belts: Rational::new(belts, 1),
+                items_per_second: Rational::new(7, 1),
+            }),
+            ..Default::default()
+        };
+        let err = BalancedBuild::new(&database(), &request, &limit(1)).unwrap_err();
+        assert!(matches!(err, Error::TooManyBelts { item, .. } if item == "copper-cable"));
+        assert!(BalancedBuild::new(&database(), &request, &limit(2)).is_ok());
+    }
+}
@@ -0,0 +1,220 @@
+//! Higher-level recipe models.
+//!
+//! The low-level [`Recipe`] model mirrors the Factorio definition closely,
+//! including its several ways of expressing the same thing. A
+//! [`NormalizedRecipe`] flattens all of that variance away for a particular
+//! [`Difficulty`].
+
+use std::collections::BTreeMap;
+
+use data_parser::models::recipe::{self, Output, Recipe, RecipeData, RecipeDataEnum};
+use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use crate::number::Number;
+
+/// The recipe category used by Factorio when a recipe does not specify one.
+pub const DEFAULT_CATEGORY: &str = "crafting";
+
+/// Recipe difficulty setting.
+///
+/// Some recipes specify distinct `normal` and `expensive` variants.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Difficulty {
+    #[default]
+    Normal,
+    Expensive,
+}
+
+/// Whether a recipe ingredient or product is an item or a fluid.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemKind {
+    #[default]
+    Item,
+    Fluid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ingredient {
+    pub name: String,
+    pub kind: ItemKind,
+    pub amount: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Product {
+    pub name: String,
+    pub kind: ItemKind,
+    pub amount: u32,
+    pub probability: f64,
+}
+
+impl Product {
+    /// The average amount of this product produced per craft.
+    pub fn expected_amount<N: Number>(&self) -> N {
+        N::from_u32(self.amount) * N::from_f64(self.probability)
+    }
+}
+
+/// A recipe with all representational variance resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedRecipe {
+    pub name: String,
+    pub category: String,
+    pub subgroup: Option<String>,
+    pub enabled: bool,
+    pub hidden: bool,
+    pub duration: Duration,
+    /// Scales the pollution emitted by machines crafting this recipe.
+    pub emissions_multiplier: f64,
+    pub ingredients: Vec<Ingredient>,
+    pub products: Vec<Product>,
+}
+
+impl NormalizedRecipe {
+    /// Normalize a recipe for the given difficulty.
+    pub fn new(recipe: &Recipe, difficulty: Difficulty) -> Self {
+        let data = match (&recipe.recipe_data, difficulty) {
+            (RecipeDataEnum::Simple(data), _) => data,
+            (RecipeDataEnum::WithHardMode(modes), Difficulty::Normal) => &modes.normal,
+            (RecipeDataEnum::WithHardMode(modes), Difficulty::Expensive) => &modes.expensive,
+        };
+
+        let RecipeData {
+            enabled,
+            hidden,
+            duration,
+            emissions_multiplier,
+            ingredients,
+            output,
+        } = data;
+
+        let ingredients = ingredients
+            .iter()
+            .map(|ingredient| match ingredient {
+                recipe::Ingredient::SimpleItem(name, amount) => Ingredient {
+                    name: name.clone(),
+                    kind: ItemKind::Item,
+                    amount: *amount,
+                },
+                recipe::Ingredient::Item(item) => Ingredient {
+                    name: item.name.clone(),
+                    kind: ItemKind::Item,
+                    amount: item.amount,
+                },
+                recipe::Ingredient::Fluid(fluid) => Ingredient {
+                    name: fluid.name.clone(),
+                    kind: ItemKind::Fluid,
+                    amount: fluid.amount,
+                },
+            })
+            .collect();
+
+        let products = match output {
+            Output::Single(single) => vec![Product {
+                name: single.name.clone(),
+                kind: ItemKind::Item,
+                amount: single.amount.into(),
+                probability: single.probability,
+            }],
+            Output::Many(many) => many
+                .outputs
+                .iter()
+                .map(|output| Product {
+                    name: output.name.clone(),
+                    kind: match output.r#type.as_deref() {
+                        Some("fluid") => ItemKind::Fluid,
+                        _ => ItemKind::Item,
+                    },
+                    amount: output.amount.into(),
+                    probability: output.probability,
+                })
+                .collect(),
+        };
+
+        Self {
+            name: recipe.name.clone(),
+            category: recipe
+                .category
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CATEGORY.into()),
+            subgroup: recipe.subgroup.clone(),
+            enabled: *enabled,
+            hidden: *hidden,
+            duration: *duration,
+            emissions_multiplier: *emissions_multiplier,
+            ingredients,
+            products,
+        }
+    }
+
+    /// The duration of one craft at crafting speed 1, in seconds.
+    pub fn seconds<N: Number>(&self) -> N {
+        N::from_duration(self.duration)
+    }
+
+    /// `true` when this recipe produces nothing, such as a recipe which voids
+    /// a fluid.
+    pub fn is_voiding(&self) -> bool {
+        self.products
+            .iter()
+            .all(|product| product.amount == 0 || product.probability == 0.0)
+    }
+
+    /// `true` when this recipe fills or empties a fluid barrel.
+    pub fn is_barreling(&self) -> bool {
+        // note that the recipe which crafts `empty-barrel` from steel is not
+        // itself a barreling recipe
+        let fluid = self
+            .name
+            .strip_prefix("fill-")
+            .or_else(|| self.name.strip_prefix("empty-"))
+            .and_then(|name| name.strip_suffix("-barrel"));
+        matches!(
+            self.subgroup.as_deref(),
+            Some("fill-barrel" | "empty-barrel")
+        ) || fluid.is_some()
+    }
+
+    /// The net amount of each item produced per craft.
+    ///
+    /// Positive amounts are net outputs and negative amounts are net inputs.
+    /// Items which appear as both ingredient and product, such as the
+    /// `uranium-235` catalyst of kovarex enrichment, are netted out.
+    pub fn net_amounts<N: Number>(&self) -> BTreeMap<&str, N> {
+        self.net_amounts_with_productivity(&N::zero())
+    }
+
+    /// The net amount of each item produced per craft, with every product
+    /// multiplied by `1 + productivity`.
+    pub fn net_amounts_with_productivity<N: Number>(&self, productivity: &N) -> BTreeMap<&str, N> {
+        let multiplier = N::one() + productivity.clone();
+        let mut net = BTreeMap::new();
+        for ingredient in &self.ingredients {
+            *net.entry(ingredient.name.as_str()).or_default() -= N::from_u32(ingredient.amount);
+        }
+        for product in &self.products {
+            *net.entry(product.name.as_str()).or_default() +=
+                product.expected_amount::<N>() * multiplier.clone();
+        }
+        net
+    }
+
+    /// Find the ingredient with the given name, if any.
+    pub fn ingredient(&self, name: &str) -> Option<&Ingredient> {
+        self.ingredients
+            .iter()
+            .find(|ingredient| ingredient.name == name)
+    }
+
+    /// Find the product with the given name, if any.
+    pub fn product(&self, name: &str) -> Option<&Product> {
+        self.products.iter().find(|product| product.name == name)
+    }
+}
@@ -0,0 +1,423 @@
+//! Commands for exploring a prototype database interactively.
+//!
+//! A [`Session`] holds a loaded database, so that many questions can be asked
+//! of a dump without parsing it again, along with the machine and recipe
+//! choices used to answer rate questions.
+
+use std::{collections::BTreeSet, fmt::Write, str::FromStr};
+
+use crate::{
+    database::PrototypeDatabase,
+    plan::{PlanRequest, ProductionPlan},
+    recipe::NormalizedRecipe,
+    units::{compact, parse_rate},
+    Error, Result,
+};
+
+/// Every command, with its arguments and a description.
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    (
+        "recipe",
+        "RECIPE",
+        "show a recipe's ingredients, products, machines and unlocks",
+    ),
+    ("makes", "ITEM", "list the recipes which produce an item"),
+    ("uses", "ITEM", "list the recipes which consume an item"),
+    (
+        "made-in",
+        "CATEGORY|MACHINE",
+        "list the recipes crafted in a category or machine",
+    ),
+    (
+        "unlocks",
+        "TECHNOLOGY",
+        "list the recipes a technology unlocks",
+    ),
+    (
+        "rate",
+        "ITEM RATE",
+        "plan making an item at a rate such as 30/s or 1800/min",
+    ),
+    (
+        "use",
+        "MACHINE|RECIPE",
+        "choose a machine for its categories, or a recipe for its products",
+    ),
+    ("reset", "", "forget machine and recipe choices"),
+    ("help", "", "list commands"),
+    ("quit", "", "leave"),
+];
+
+/// A parsed line of input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Recipe(String),
+    Makes(String),
+    Uses(String),
+    MadeIn(String),
+    Unlocks(String),
+    Rate { item: String, rate: f64 },
+    Use(String),
+    Reset,
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let command = match words.as_slice() {
+            ["recipe", name] => Self::Recipe((*name).into()),
+            ["makes", item] => Self::Makes((*item).into()),
+            ["uses", item] => Self::Uses((*item).into()),
+            ["made-in", name] => Self::MadeIn((*name).into()),
+            ["unlocks", name] => Self::Unlocks((*name).into()),
+            ["rate", item, rate] => Self::Rate {
+                item: (*item).into(),
+                rate: parse_rate(rate)?,
+            },
+            ["use", name] => Self::Use((*name).into()),
+            ["reset"] => Self::Reset,
+            ["help" | "?"] => Self::Help,
+            ["quit" | "exit"] => Self::Quit,
+            [name, ..] => {
+                let message = match COMMANDS.iter().find(|(command, ..)| command == name) {
+                    Some((command, args, _)) => format!("usage: {command} {args}"),
+                    None => format!("unknown command \"{name}\""),
+                };
+                return Err(Error::InvalidCommand(message.trim_end().into()));
+            }
+            [] => return Err(Error::InvalidCommand("empty command".into())),
+        };
+        Ok(command)
+    }
+}
+
+/// A database being queried, and the choices made so far.
+#[derive(Debug)]
+pub struct Session<'a> {
+    pub database: &'a PrototypeDatabase,
+    /// The machine and recipe choices used to answer rate questions.
+    pub request: PlanRequest,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(database: &'a PrototypeDatabase) -> Self {
+        Self {
+            database,
+            request: PlanRequest::default(),
+        }
+    }
+
+    /// Run a command, returning the text to show.
+    pub fn execute(&mut self, command: &Command) -> Result<String> {
+        match command {
+            Command::Recipe(name) => self.recipe(name),
+            Command::Makes(item) => Ok(names(
+                self.database.producers(item),
+                format!("no recipe makes \"{item}\""),
+            )),
+            Command::Uses(item) => Ok(names(
+                self.database.consumers(item),
+                format!("no recipe uses \"{item}\""),
+            )),
+            Command::MadeIn(name) => Ok(self.made_in(name)),
+            Command::Unlocks(name) => self.unlocks(name),
+            Command::Rate { item, rate } => self.rate(item, *rate),
+            Command::Use(name) => self.choose(name),
+            Command::Reset => {
+                self.request = PlanRequest::default();
+                Ok("forgot all choices".into())
+            }
+            Command::Help => Ok(help()),
+            Command::Quit => Ok(String::new()),
+        }
+    }
+
+    fn recipe(&self, name: &str) -> Result<String> {
+        let recipe = self
+            .database
+            .recipe(name)
+            .ok_or_else(|| Error::UnknownRecipe(name.into()))?;
+
+        let ingredients = recipe
+            .ingredients
+            .iter()
+            .map(|ingredient| format!("{} {}", ingredient.amount, ingredient.name))
+            .collect::<Vec<_>>();
+        let products = recipe
+            .products
+            .iter()
+            .map(|product| {
+                if product.probability < 1.0 {
+                    let percent = compact(product.probability * 100.0, 2);
+                    format!("{} {} ({percent}%)", product.amount, product.name)
+                } else {
+                    format!("{} {}", product.amount, product.name)
+                }
+            })
+            .collect::<Vec<_>>();
+        let machines = self
+            .database
+            .machines
+            .values()
+            .filter(|machine| machine.can_craft(&recipe.category))
+            .map(|machine| machine.name.as_str())
+            .collect::<Vec<_>>();
+        let mut unlocked_by = self
+            .database
+            .unlocked_by(name)
+            .map(|technology| technology.name.as_str())
+            .collect::<Vec<_>>();
+        if recipe.enabled {
+            unlocked_by.insert(0, "enabled from the start");
+        }
+
+        let mut out = format!(
+            "{name}: {}, {} s",
+            recipe.category,
+            compact(recipe.seconds(), 3)
+        );
+        for (label, list) in [
+            ("ingredients", ingredients.join(", ")),
+            ("products", products.join(", ")),
+            ("machines", machines.join(", ")),
+            ("unlocked by", unlocked_by.join(", ")),
+        ] {
+            if !list.is_empty() {
+                write!(out, "\n  {label}: {list}").unwrap();
+            }
+        }
+        Ok(out)
+    }
+
+    fn made_in(&self, name: &str) -> String {
+        let categories = match self.database.machine(name) {
+            Some(machine) => machine.crafting_categories.iter().cloned().collect(),
+            None => BTreeSet::from([name.to_owned()]),
+        };
+        names(
+            self.database
+                .recipes
+                .values()
+                .filter(|recipe| categories.contains(&recipe.category)),
+            format!("no recipe is made in \"{name}\""),
+        )
+    }
+
+    fn unlocks(&self, name: &str) -> Result<String> {
+        let technology = self
+            .database
+            .technologies
+            .get(name)
+            .ok_or_else(|| Error::UnknownTechnology(name.into()))?;
+
+        let mut out = format!("unlocks: {}", technology.unlocks.join(", "));
+        if !technology.prerequisites.is_empty() {
+            write!(out, "\nrequires: {}", technology.prerequisites.join(", ")).unwrap();
+        }
+        Ok(out)
+    }
+
+    fn rate(&self, item: &str, rate: f64) -> Result<String> {
+        let request = PlanRequest {
+            targets: [(item.to_owned(), rate)].into(),
+            ..self.request.clone()
+        };
+        let plan = ProductionPlan::new(self.database, &request)?;
+
+        let mut out = String::from("steps:");
+        for (recipe, step) in &plan.steps {
+            write!(
+                out,
+                "\n  {} × {}  {recipe}",
+                compact(step.machines, 2),
+                step.machine
+            )
+            .unwrap();
+        }
+        for (label, flows) in [("raw inputs", &plan.raw_inputs), ("surplus", &plan.surplus)] {
+            if !flows.is_empty() {
+                write!(out, "\n{label}:").unwrap();
+                for (item, rate) in flows {
+                    write!(out, "\n  {}/s {item}", compact(*rate, 3)).unwrap();
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn choose(&mut self, name: &str) -> Result<String> {
+        if let Some(machine) = self.database.machine(name) {
+            for category in &machine.crafting_categories {
+                self.request.machines.insert(category.clone(), name.into());
+            }
+            let categories = Vec::from_iter(machine.crafting_categories.iter().cloned());
+            return Ok(format!("using {name} for {}", categories.join(", ")));
+        }
+        if let Some(recipe) = self.database.recipe(name) {
+            for product in &recipe.products {
+                (self.request.policy.recipes).insert(product.name.clone(), name.into());
+            }
+            let products = Vec::from_iter(recipe.products.iter().map(|p| p.name.as_str()));
+            return Ok(format!("making {} with {name}", products.join(", ")));
+        }
+        Err(Error::InvalidCommand(format!(
+            "\"{name}\" is neither a machine nor a recipe"
+        )))
+    }
+}
+
+fn names<'a>(recipes: impl Iterator<Item = &'a NormalizedRecipe>, empty: String) -> String {
+    let names = recipes
+        .map(|recipe| recipe.name.as_str())
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        empty
+    } else {
+        names.join("\n")
+    }
+}
+
+fn help() -> String {
+    COMMANDS
+        .iter()
+        .map(|(command, args, description)| {
+            let usage = format!("{command} {args}");
+            format!("{usage:<28}{description}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Complete the word ending at byte offset `pos` of `line`.
+///
+/// Returns the offset at which the word starts, and every command or
+/// prototype name which it begins, chosen by the argument being completed.
+pub fn complete<'a>(
+    database: &'a PrototypeDatabase,
+    line: &str,
+    pos: usize,
+) -> (usize, Vec<&'a str>) {
+    let before = &line[..pos];
+    let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let prefix = &before[start..];
+    let words = before[..start].split_whitespace().collect::<Vec<_>>();
+
+    let recipes = || database.recipes.keys().map(String::as_str);
+    let machines = || database.machines.keys().map(String::as_str);
+    let candidates: BTreeSet<&str> = match words.as_slice() {
+        [] => COMMANDS.iter().map(|(command, ..)| *command).collect(),
+        ["recipe"] => recipes().collect(),
+        ["makes" | "uses" | "rate"] => database
+            .recipes
+            .values()
+            .flat_map(|recipe| {
+                let ingredients = recipe.ingredients.iter().map(|i| i.name.as_str());
+                let products = recipe.products.iter().map(|p| p.name.as_str());
+                ingredients.chain(products)
+            })
+            .collect(),
+        ["made-in"] => database
+            .recipes
+            .values()
+            .map(|recipe| recipe.category.as_str())
+            .chain(machines())
+            .collect(),
+        ["unlocks"] => database.technologies.keys().map(String::as_str).collect(),
+        ["use"] => machines().chain(recipes()).collect(),
+        _ => BTreeSet::new(),
+    };
+
+    let matches = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+    (start, matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plan::tests::database, technology::Technology};
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "recipe copper-cable".parse::<Command>().unwrap(),
+            Command::Recipe("copper-cable".into())
+        );
+        assert_eq!(
+            "  rate electronic-circuit 1800/min "
+                .parse::<Command>()
+                .unwrap(),
+            Command::Rate {
+                item: "electronic-circuit".into(),
+                rate: 30.0
+            }
+        );
+        let err = "uses".parse::<Command>().unwrap_err().to_string();
+        assert!(err.starts_with("usage: uses ITEM"), "{err}");
+        assert!("frobnicate x".parse::<Command>().is_err());
+        assert!("rate electronic-circuit fast".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn answers_queries() {
+        let mut database = database();
+        database.technologies.insert(
+            "electronics".into(),
+            Technology {
+                name: "electronics".into(),
+                prerequisites: vec!["automation".into()],
+                unlocks: vec!["electronic-circuit".into()],
+            },
+        );
+        let mut session = Session::new(&database);
+        let mut run = |line: &str| session.execute(&line.parse().unwrap());
+
+        let recipe = run("recipe electronic-circuit").unwrap();
+        assert!(recipe.starts_with("electronic-circuit: crafting, 0.5 s"));
+        assert!(recipe.contains("ingredients: 1 iron-plate, 3 copper-cable"));
+        assert!(recipe.ends_with(", electronics"), "{recipe}");
+        assert_eq!(run("uses copper-cable").unwrap(), "electronic-circuit");
+        assert_eq!(
+            run("makes iron-plate").unwrap(),
+            "no recipe makes \"iron-plate\""
+        );
+        assert!(run("made-in centrifuging")
+            .unwrap()
+            .contains("uranium-processing"));
+        assert_eq!(
+            run("unlocks electronics").unwrap(),
+            "unlocks: electronic-circuit\nrequires: automation"
+        );
+        assert!(run("unlocks logistics").is_err());
+
+        assert!(run("rate electronic-circuit 15/s").is_err());
+        run("use assembling-machine-2").unwrap();
+        let rate = run("rate electronic-circuit 15/s").unwrap();
+        assert!(
+            rate.contains("15 × assembling-machine-2  copper-cable"),
+            "{rate}"
+        );
+        assert!(rate.contains("22.5/s copper-plate"), "{rate}");
+    }
+
+    #[test]
+    fn completes_names() {
+        let database = database();
+        assert_eq!(complete(&database, "ma", 2), (0, vec!["made-in", "makes"]));
+        assert_eq!(
+            complete(&database, "uses copper-c", 13),
+            (5, vec!["copper-cable"])
+        );
+        assert_eq!(
+            complete(&database, "use assembling", 14),
+            (4, vec!["assembling-machine-2", "assembling-machine-3"])
+        );
+        assert!(complete(&database, "rate copper-cable 3", 19).1.is_empty());
+    }
+}
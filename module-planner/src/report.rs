@@ -0,0 +1,332 @@
+//! Throughput of a single machine.
+//!
+//! Where a [`ProductionPlan`][crate::plan::ProductionPlan] sizes a whole
+//! chain, a [`MachineReport`] answers what one machine does: how fast it
+//! crafts, what it consumes and produces, what it costs in power and
+//! pollution, and how many inserters it takes to keep it running.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    inserter::HandSize,
+    modules::{Effects, ModuleLoadout},
+    number::Number,
+    plan::choose_machine,
+    recipe::ItemKind,
+    Error, Result,
+};
+
+/// The rate at which a machine produces one of its products.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductRate<N = f64> {
+    /// The expected number produced per second, including productivity.
+    pub per_second: N,
+    /// The chance that each craft yields the product.
+    pub probability: f64,
+}
+
+/// The number of inserters of one type needed to feed a machine and empty it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InserterCount {
+    pub input: u64,
+    pub output: u64,
+}
+
+/// What a single machine does when crafting a recipe nonstop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MachineReport<N = f64> {
+    pub recipe: String,
+    pub machine: String,
+    pub effects: Effects<N>,
+    pub crafts_per_second: N,
+    pub products: BTreeMap<String, ProductRate<N>>,
+    /// The number of each ingredient consumed per second.
+    pub ingredients: BTreeMap<String, N>,
+    /// Power drawn by the machine while crafting, in watts, including its
+    /// drain.
+    pub power: N,
+    /// Power drawn by the machine even while idle, in watts.
+    pub drain: N,
+    /// Power drawn by the beacons affecting the machine, in watts.
+    pub beacon_power: N,
+    pub pollution_per_minute: N,
+    /// The number of inserters of each type needed to move the machine's
+    /// solid ingredients and products, chest to chest. Inserters which cannot
+    /// move items at all, such as with a hand size of 0, are omitted.
+    pub inserters: BTreeMap<String, InserterCount>,
+}
+
+impl<N: Number> MachineReport<N> {
+    /// Report on one machine crafting `recipe`.
+    ///
+    /// If `machine` is `None`, the recipe's category must be craftable by
+    /// exactly one machine.
+    pub fn new(
+        database: &PrototypeDatabase,
+        recipe: &str,
+        machine: Option<&str>,
+        loadout: &ModuleLoadout,
+        hand_size: HandSize,
+    ) -> Result<Self> {
+        let recipe = database
+            .recipe(recipe)
+            .ok_or_else(|| Error::UnknownRecipe(recipe.into()))?;
+        let machines = machine
+            .map(|machine| BTreeMap::from([(recipe.category.clone(), machine.to_owned())]))
+            .unwrap_or_default();
+        let machine = choose_machine(database, &machines, &recipe.category)?;
+        let effects = loadout.effects::<N>(database, recipe, machine)?;
+        if !recipe.seconds::<N>().is_positive() {
+            return Err(Error::InstantRecipe(recipe.name.clone()));
+        }
+
+        let crafts_per_second = N::from_f64(machine.crafting_speed) * effects.speed_multiplier()
+            / recipe.seconds::<N>();
+        let productivity = N::one() + effects.productivity.clone();
+
+        let mut products = BTreeMap::<String, ProductRate<N>>::new();
+        let mut output_rate = N::zero();
+        for product in &recipe.products {
+            let rate =
+                product.expected_amount::<N>() * productivity.clone() * crafts_per_second.clone();
+            if product.kind == ItemKind::Item {
+                output_rate += rate.clone();
+            }
+            products
+                .entry(product.name.clone())
+                .or_insert_with(|| ProductRate {
+                    per_second: N::zero(),
+                    probability: product.probability,
+                })
+                .per_second += rate;
+        }
+
+        let mut ingredients = BTreeMap::<String, N>::new();
+        let mut input_rate = N::zero();
+        for ingredient in &recipe.ingredients {
+            let rate = N::from_u32(ingredient.amount) * crafts_per_second.clone();
+            if ingredient.kind == ItemKind::Item {
+                input_rate += rate.clone();
+            }
+            *ingredients.entry(ingredient.name.clone()).or_default() += rate;
+        }
+
+        let inserters = database
+            .inserters
+            .values()
+            .filter_map(|inserter| {
+                let throughput = inserter.items_per_second::<N>(inserter.hand_size(hand_size));
+                // an inserter which cannot move items cannot keep up at any count
+                if !throughput.is_positive() {
+                    return None;
+                }
+                let count = |rate: &N| (rate.clone() / throughput.clone()).ceil().to_f64() as u64;
+                let count = InserterCount {
+                    input: count(&input_rate),
+                    output: count(&output_rate),
+                };
+                Some((inserter.name.clone(), count))
+            })
+            .collect();
+
+        let drain = N::from_f64(machine.energy_source.drain());
+        let consumption = effects.consumption_multiplier();
+        Ok(Self {
+            recipe: recipe.name.clone(),
+            machine: machine.name.clone(),
+            crafts_per_second,
+            products,
+            ingredients,
+            power: N::from_f64(machine.energy_usage) * consumption.clone() + drain.clone(),
+            drain,
+            beacon_power: loadout.beacon_power(database)?,
+            pollution_per_minute: N::from_f64(machine.emissions_per_minute)
+                * consumption
+                * effects.pollution_multiplier()
+                * N::from_f64(recipe.emissions_multiplier),
+            inserters,
+            effects,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inserter::Inserter,
+        modules::BeaconLoadout,
+        number::Rational,
+        plan::tests::{assert_close, database, recipe},
+    };
+
+    fn database_with_inserters() -> PrototypeDatabase {
+        let mut database = database();
+        for (name, rotation_speed, stack) in [
+            ("inserter", 0.014, false),
+            ("fast-inserter", 0.04, false),
+            ("stack-inserter", 0.04, true),
+        ] {
+            database.inserters.insert(
+                name.into(),
+                Inserter {
+                    name: name.into(),
+                    rotation_speed,
+                    extension_speed: 0.07,
+                    stack,
+                },
+            );
+        }
+        database
+    }
+
+    #[test]
+    fn circuit_assembler() {
+        let report = MachineReport::<Rational>::new(
+            &database_with_inserters(),
+            "electronic-circuit",
+            Some("assembling-machine-2"),
+            &ModuleLoadout::default(),
+            HandSize::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.crafts_per_second, Rational::new(3, 2));
+        assert_eq!(
+            report.products["electronic-circuit"].per_second,
+            Rational::new(3, 2)
+        );
+        assert_eq!(report.ingredients["copper-cable"], Rational::new(9, 2));
+        assert_eq!(report.ingredients["iron-plate"], Rational::new(3, 2));
+        assert_eq!(report.power, Rational::new(155_000, 1));
+        assert_eq!(report.drain, Rational::new(5_000, 1));
+        assert_eq!(report.pollution_per_minute, Rational::new(3, 1));
+
+        // 6 items in per second; a basic inserter moves 0.84
+        assert_eq!(
+            report.inserters["inserter"],
+            InserterCount {
+                input: 8,
+                output: 2
+            }
+        );
+        assert_eq!(
+            report.inserters["stack-inserter"],
+            InserterCount {
+                input: 2,
+                output: 1
+            }
+        );
+    }
+
+    #[test]
+    fn skips_immobile_inserters() {
+        let mut database = database_with_inserters();
+        database
+            .inserters
+            .get_mut("fast-inserter")
+            .unwrap()
+            .rotation_speed = 0.0;
+        let hand_size = HandSize {
+            inserter: 0,
+            ..HandSize::default()
+        };
+        let report = MachineReport::<Rational>::new(
+            &database,
+            "electronic-circuit",
+            Some("assembling-machine-2"),
+            &ModuleLoadout::default(),
+            hand_size,
+        )
+        .unwrap();
+        assert_eq!(
+            report.inserters.keys().collect::<Vec<_>>(),
+            ["stack-inserter"]
+        );
+    }
+
+    #[test]
+    fn modules_and_beacons() {
+        let loadout = ModuleLoadout {
+            modules: vec!["productivity-module".into(); 4],
+            beacons: vec![BeaconLoadout {
+                beacon: "beacon".into(),
+                count: 8,
+                modules: vec!["speed-module".into(); 2],
+            }],
+        };
+        let report = MachineReport::<f64>::new(
+            &database_with_inserters(),
+            "electronic-circuit",
+            Some("assembling-machine-3"),
+            &loadout,
+            HandSize::default(),
+        )
+        .unwrap();
+
+        // speed: 8 * 2 * 0.5 * 0.2 - 4 * 0.05 = 1.4
+        assert_close(report.crafts_per_second, 1.25 * 2.4 / 0.5);
+        assert_close(report.products["electronic-circuit"].per_second, 6.0 * 1.16);
+        // consumption: 8 * 2 * 0.5 * 0.5 + 4 * 0.4 = 5.6
+        assert_close(report.power, 375e3 * 6.6 + 12.5e3);
+        assert_close(report.beacon_power, 8.0 * 480e3);
+        // pollution: 4 * 0.05 = 0.2
+        assert_close(report.pollution_per_minute, 2.0 * 6.6 * 1.2);
+    }
+
+    #[test]
+    fn probabilistic_products() {
+        let report = MachineReport::<Rational>::new(
+            &database_with_inserters(),
+            "uranium-processing",
+            None,
+            &ModuleLoadout::default(),
+            HandSize::default(),
+        )
+        .unwrap();
+
+        let uranium_235 = &report.products["uranium-235"];
+        assert_eq!(uranium_235.probability, 0.007);
+        assert_eq!(uranium_235.per_second, Rational::new(7, 12_000));
+        assert_eq!(report.ingredients["uranium-ore"], Rational::new(5, 6));
+    }
+
+    #[test]
+    fn unknown_recipe() {
+        let err = MachineReport::<f64>::new(
+            &database_with_inserters(),
+            "rocket-part",
+            None,
+            &ModuleLoadout::default(),
+            HandSize::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::UnknownRecipe(_)));
+    }
+
+    #[test]
+    fn instant_recipe() {
+        let mut database = database_with_inserters();
+        let gear = recipe(
+            "iron-gear-wheel",
+            "crafting",
+            0.0,
+            &[("iron-plate", ItemKind::Item, 2)],
+            &[("iron-gear-wheel", ItemKind::Item, 1, 1.0)],
+        );
+        database.recipes.insert(gear.name.clone(), gear);
+
+        let err = MachineReport::<Rational>::new(
+            &database,
+            "iron-gear-wheel",
+            Some("assembling-machine-2"),
+            &ModuleLoadout::default(),
+            HandSize::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::InstantRecipe(_)));
+    }
+}
@@ -0,0 +1,390 @@
+//! A small dense two-phase simplex solver.
+//!
+//! This is not a general-purpose LP library: it is just enough to balance
+//! production chains of a few hundred recipes, without depending on external
+//! solvers. Like the planner, it is generic over its [`Number`] type; with
+//! [`Rational`][crate::number::Rational] it is exact, if slow.
+
+use crate::number::Number;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// `lhs == rhs`
+    Eq,
+    /// `lhs <= rhs`
+    Le,
+    /// `lhs >= rhs`
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint<N = f64> {
+    pub coefficients: Vec<N>,
+    pub relation: Relation,
+    pub rhs: N,
+}
+
+/// Minimize `costs · x` subject to the constraints and `x >= 0`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinearProgram<N = f64> {
+    pub costs: Vec<N>,
+    pub constraints: Vec<Constraint<N>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<N = f64> {
+    Optimal {
+        values: Vec<N>,
+        objective: N,
+    },
+    /// No assignment satisfies every constraint.
+    ///
+    /// `residuals` holds, per constraint, `rhs - lhs` at the assignment which
+    /// comes closest to feasibility (minimizing the total absolute residual).
+    /// Nonzero residuals identify the constraints which cannot be met.
+    /// `values` holds that assignment, so that constraints which are met only
+    /// with no slack to spare can be identified too.
+    Infeasible {
+        residuals: Vec<N>,
+        values: Vec<N>,
+    },
+    /// The objective can be decreased without bound.
+    Unbounded,
+}
+
+struct Tableau<N> {
+    /// `rows` rows of `columns + 1` entries; the last entry is the rhs.
+    cells: Vec<Vec<N>>,
+    basis: Vec<usize>,
+    columns: usize,
+}
+
+impl<N: Number> Tableau<N> {
+    fn rhs(&self, row: usize) -> &N {
+        &self.cells[row][self.columns]
+    }
+
+    fn pivot(&mut self, row: usize, column: usize) {
+        let divisor = self.cells[row][column].clone();
+        for cell in self.cells[row].iter_mut() {
+            *cell = cell.clone() / divisor.clone();
+        }
+        let pivot_row = self.cells[row].clone();
+        for (idx, other) in self.cells.iter_mut().enumerate() {
+            let factor = other[column].clone();
+            if idx == row || factor == N::zero() {
+                continue;
+            }
+            for (cell, pivot_cell) in other.iter_mut().zip(&pivot_row) {
+                *cell -= factor.clone() * pivot_cell.clone();
+            }
+        }
+        self.basis[row] = column;
+    }
+
+    /// Run the simplex method to minimize `costs`, entering only columns for
+    /// which `may_enter` holds.
+    ///
+    /// Uses Bland's rule, which is slow but never cycles.
+    fn minimize(&mut self, costs: &[N], may_enter: impl Fn(usize) -> bool) -> Result<(), ()> {
+        loop {
+            let entering = (0..self.columns).find(|&column| {
+                may_enter(column) && !self.basis.contains(&column) && {
+                    let reduced = costs[column].clone()
+                        - self
+                            .basis
+                            .iter()
+                            .enumerate()
+                            .map(|(row, &basic)| {
+                                costs[basic].clone() * self.cells[row][column].clone()
+                            })
+                            .sum::<N>();
+                    reduced.is_negative()
+                }
+            });
+            let Some(entering) = entering else {
+                return Ok(());
+            };
+
+            let leaving = (0..self.cells.len())
+                .filter(|&row| self.cells[row][entering].is_positive())
+                .min_by(|&a, &b| {
+                    let ratio_a = self.rhs(a).clone() / self.cells[a][entering].clone();
+                    let ratio_b = self.rhs(b).clone() / self.cells[b][entering].clone();
+                    ratio_a
+                        .partial_cmp(&ratio_b)
+                        .expect("ratios are finite")
+                        .then(self.basis[a].cmp(&self.basis[b]))
+                });
+            let Some(leaving) = leaving else {
+                return Err(());
+            };
+
+            self.pivot(leaving, entering);
+        }
+    }
+
+    fn objective(&self, costs: &[N]) -> N {
+        self.basis
+            .iter()
+            .enumerate()
+            .map(|(row, &basic)| costs[basic].clone() * self.rhs(row).clone())
+            .sum()
+    }
+
+    fn value(&self, column: usize) -> N {
+        self.basis
+            .iter()
+            .position(|&basic| basic == column)
+            .map_or_else(N::zero, |row| self.rhs(row).clone())
+    }
+}
+
+impl<N: Number> LinearProgram<N> {
+    pub fn solve(&self) -> Outcome<N> {
+        let variables = self.costs.len();
+        let rows = self.constraints.len();
+        let slacks = self
+            .constraints
+            .iter()
+            .filter(|constraint| constraint.relation != Relation::Eq)
+            .count();
+
+        // columns: variables, slacks, then a pair of artificials per row
+        let slack_start = variables;
+        let artificial_start = slack_start + slacks;
+        let columns = artificial_start + 2 * rows;
+
+        let mut cells = Vec::with_capacity(rows);
+        let mut flipped = Vec::with_capacity(rows);
+        let mut slack = slack_start;
+        for (row, constraint) in self.constraints.iter().enumerate() {
+            let mut cells_row = vec![N::zero(); columns + 1];
+            cells_row[..variables].clone_from_slice(&constraint.coefficients);
+            match constraint.relation {
+                Relation::Eq => {}
+                Relation::Le => {
+                    cells_row[slack] = N::one();
+                    slack += 1;
+                }
+                Relation::Ge => {
+                    cells_row[slack] = -N::one();
+                    slack += 1;
+                }
+            }
+            cells_row[columns] = constraint.rhs.clone();
+
+            // keep the rhs nonnegative so the positive artificials form a
+            // feasible initial basis
+            let flip = constraint.rhs < N::zero();
+            if flip {
+                for cell in cells_row.iter_mut() {
+                    *cell = -cell.clone();
+                }
+            }
+            cells_row[artificial_start + 2 * row] = N::one();
+            cells_row[artificial_start + 2 * row + 1] = -N::one();
+
+            flipped.push(flip);
+            cells.push(cells_row);
+        }
+
+        let mut tableau = Tableau {
+            cells,
+            basis: (0..rows).map(|row| artificial_start + 2 * row).collect(),
+            columns,
+        };
+
+        // phase 1: minimize the total absolute residual
+        let mut phase_1_costs = vec![N::zero(); columns];
+        for cost in &mut phase_1_costs[artificial_start..] {
+            *cost = N::one();
+        }
+        tableau
+            .minimize(&phase_1_costs, |_| true)
+            .expect("phase 1 is bounded below by zero");
+
+        if tableau.objective(&phase_1_costs).is_positive() {
+            let residuals = (0..rows)
+                .map(|row| {
+                    let positive = tableau.value(artificial_start + 2 * row);
+                    let negative = tableau.value(artificial_start + 2 * row + 1);
+                    let residual = positive - negative;
+                    if flipped[row] {
+                        -residual
+                    } else {
+                        residual
+                    }
+                })
+                .collect();
+            let values = (0..variables).map(|column| tableau.value(column)).collect();
+            return Outcome::Infeasible { residuals, values };
+        }
+
+        // drive any zero-valued artificials out of the basis where possible;
+        // those which remain belong to redundant rows and stay at zero
+        for row in 0..rows {
+            if tableau.basis[row] < artificial_start {
+                continue;
+            }
+            if let Some(column) =
+                (0..artificial_start).find(|&column| !tableau.cells[row][column].is_zero())
+            {
+                tableau.pivot(row, column);
+            }
+        }
+
+        // phase 2: minimize the real objective
+        let mut phase_2_costs = vec![N::zero(); columns];
+        phase_2_costs[..variables].clone_from_slice(&self.costs);
+        if tableau
+            .minimize(&phase_2_costs, |column| column < artificial_start)
+            .is_err()
+        {
+            return Outcome::Unbounded;
+        }
+
+        let values = (0..variables)
+            .map(|column| tableau.value(column))
+            .collect::<Vec<_>>();
+        let objective = values
+            .iter()
+            .zip(&self.costs)
+            .map(|(value, cost)| value.clone() * cost.clone())
+            .sum();
+        Outcome::Optimal { values, objective }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(coefficients: &[f64], relation: Relation, rhs: f64) -> Constraint {
+        Constraint {
+            coefficients: coefficients.to_vec(),
+            relation,
+            rhs,
+        }
+    }
+
+    fn assert_optimal(outcome: Outcome, expect_values: &[f64], expect_objective: f64) {
+        let Outcome::Optimal { values, objective } = outcome else {
+            panic!("expected optimal outcome, found {outcome:?}");
+        };
+        for (found, expect) in values.iter().zip(expect_values) {
+            assert!(
+                (found - expect).abs() < 1e-6,
+                "{values:?} != {expect_values:?}"
+            );
+        }
+        assert!((objective - expect_objective).abs() < 1e-6);
+    }
+
+    #[test]
+    fn textbook() {
+        // maximize 3x + 5y s.t. x <= 4, 2y <= 12, 3x + 2y <= 18
+        let lp = LinearProgram {
+            costs: vec![-3.0, -5.0],
+            constraints: vec![
+                constraint(&[1.0, 0.0], Relation::Le, 4.0),
+                constraint(&[0.0, 2.0], Relation::Le, 12.0),
+                constraint(&[3.0, 2.0], Relation::Le, 18.0),
+            ],
+        };
+        assert_optimal(lp.solve(), &[2.0, 6.0], -36.0);
+    }
+
+    #[test]
+    fn exact() {
+        use crate::number::Rational;
+
+        // minimize x + y s.t. 3x + y >= 1, x + 3y >= 1
+        let r = |numer| Rational::new(numer, 1);
+        let lp = LinearProgram {
+            costs: vec![r(1), r(1)],
+            constraints: vec![
+                Constraint {
+                    coefficients: vec![r(3), r(1)],
+                    relation: Relation::Ge,
+                    rhs: r(1),
+                },
+                Constraint {
+                    coefficients: vec![r(1), r(3)],
+                    relation: Relation::Ge,
+                    rhs: r(1),
+                },
+            ],
+        };
+        let Outcome::Optimal { values, objective } = lp.solve() else {
+            panic!("expected optimal outcome");
+        };
+        assert_eq!(values, [Rational::new(1, 4), Rational::new(1, 4)]);
+        assert_eq!(objective, Rational::new(1, 2));
+    }
+
+    #[test]
+    fn equality_and_ge() {
+        // minimize x + 2y s.t. x + y == 10, x <= 4, y >= 1
+        let lp = LinearProgram {
+            costs: vec![1.0, 2.0],
+            constraints: vec![
+                constraint(&[1.0, 1.0], Relation::Eq, 10.0),
+                constraint(&[1.0, 0.0], Relation::Le, 4.0),
+                constraint(&[0.0, 1.0], Relation::Ge, 1.0),
+            ],
+        };
+        assert_optimal(lp.solve(), &[4.0, 6.0], 16.0);
+    }
+
+    #[test]
+    fn negative_rhs() {
+        // minimize x s.t. -x <= -3
+        let lp = LinearProgram {
+            costs: vec![1.0],
+            constraints: vec![constraint(&[-1.0], Relation::Le, -3.0)],
+        };
+        assert_optimal(lp.solve(), &[3.0], 3.0);
+    }
+
+    #[test]
+    fn redundant_rows() {
+        let lp = LinearProgram {
+            costs: vec![1.0, 1.0],
+            constraints: vec![
+                constraint(&[1.0, 1.0], Relation::Eq, 2.0),
+                constraint(&[2.0, 2.0], Relation::Eq, 4.0),
+            ],
+        };
+        let Outcome::Optimal { objective, .. } = lp.solve() else {
+            panic!("expected optimal outcome");
+        };
+        assert!((objective - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn infeasible() {
+        // x == 5 and x <= 3
+        let lp = LinearProgram {
+            costs: vec![1.0],
+            constraints: vec![
+                constraint(&[1.0], Relation::Eq, 5.0),
+                constraint(&[1.0], Relation::Le, 3.0),
+            ],
+        };
+        let Outcome::Infeasible { residuals, .. } = lp.solve() else {
+            panic!("expected infeasible outcome");
+        };
+        let total = residuals.iter().map(|r| r.abs()).sum::<f64>();
+        assert!((total - 2.0).abs() < 1e-6, "{residuals:?}");
+    }
+
+    #[test]
+    fn unbounded() {
+        let lp = LinearProgram {
+            costs: vec![-1.0],
+            constraints: vec![constraint(&[1.0], Relation::Ge, 1.0)],
+        };
+        assert_eq!(lp.solve(), Outcome::Unbounded);
+    }
+}
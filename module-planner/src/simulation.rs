@@ -0,0 +1,729 @@
+//! Tick-by-tick simulation of a plan.
+//!
+//! A [`ProductionPlan`] describes a factory in steady state. It says nothing
+//! of how long the factory takes to get there, of inserters moving items in
+//! batches, of belts too slow or too short to keep up, or of machines idling
+//! while they wait for a rare product. A [`Simulation`] runs the plan one
+//! game tick at a time, with whole machines, input and output buffers, and
+//! probabilistic products rolled by a seeded random number generator, so
+//! that the same seed always gives the same [`SimulationReport`].
+//!
+//! The model is deliberately simple. Every item travels over a single link
+//! shared by all of its producers and consumers: a belt of limited capacity
+//! and throughput for solid items if a belt is given, and an unlimited pipe
+//! otherwise. Raw inputs arrive at the rate the plan requires them, and each
+//! machine has one inserter per solid ingredient and product.
+
+use std::collections::BTreeMap;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    inserter::HandSize,
+    number::Number,
+    plan::ProductionPlan,
+    recipe::{ItemKind, NormalizedRecipe},
+    Error, Result,
+};
+
+const TICKS_PER_SECOND: u64 = 60;
+
+/// Items held by each tile of belt, across both lanes.
+const ITEMS_PER_BELT_TILE: f64 = 8.0;
+
+/// How a plan is simulated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationOptions {
+    /// The number of ticks to simulate.
+    pub ticks: u64,
+    /// Seeds the rolls of probabilistic products.
+    pub seed: u64,
+    /// The belt carrying solid items; if `None`, items move instantly.
+    pub belt: Option<String>,
+    /// The length of each belt in tiles, which bounds the items it holds.
+    pub belt_length: u32,
+    /// The inserter loading and unloading solid items; if `None`, items move
+    /// instantly.
+    pub inserter: Option<String>,
+    pub hand_size: HandSize,
+    /// Machines stock ingredients for this many crafts.
+    pub input_crafts: u32,
+    /// Machines stop crafting while holding products of this many crafts.
+    pub output_crafts: u32,
+    /// The length of the windows over which output rates are measured.
+    pub sample_ticks: u64,
+    /// The fraction by which a window's output may fall short of the plan
+    /// while still counting as steady.
+    pub tolerance: f64,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            ticks: 10 * 60 * TICKS_PER_SECOND,
+            seed: 0,
+            belt: None,
+            belt_length: 10,
+            inserter: None,
+            hand_size: HandSize::default(),
+            input_crafts: 2,
+            output_crafts: 2,
+            sample_ticks: 10 * TICKS_PER_SECOND,
+            tolerance: 0.05,
+        }
+    }
+}
+
+/// What one machine did during the simulation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MachineActivity {
+    pub crafts: u64,
+    /// The fraction of ticks spent crafting.
+    pub utilization: f64,
+    /// Ticks spent idle for want of each ingredient.
+    pub starved: BTreeMap<String, u64>,
+    /// Ticks spent idle because each product could not be unloaded.
+    pub blocked: BTreeMap<String, u64>,
+}
+
+/// The rate at which a target item left the simulated factory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    pub items: f64,
+    pub per_second: f64,
+    pub planned_per_second: f64,
+}
+
+/// The outcome of a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub ticks: u64,
+    pub seed: u64,
+    /// The first tick from which every target was delivered within tolerance
+    /// of its planned rate in every sample window, or `None` if the factory
+    /// never settled.
+    pub ticks_to_steady_state: Option<u64>,
+    /// The activity of each whole machine, by recipe.
+    pub machines: BTreeMap<String, Vec<MachineActivity>>,
+    pub delivered: BTreeMap<String, Delivery>,
+}
+
+impl SimulationReport {
+    /// The fraction of ticks spent crafting, across every machine of a recipe.
+    pub fn utilization(&self, recipe: &str) -> Option<f64> {
+        let machines = self.machines.get(recipe)?;
+        let total = machines
+            .iter()
+            .map(|machine| machine.utilization)
+            .sum::<f64>();
+        Some(total / machines.len() as f64)
+    }
+}
+
+/// The belt or pipe carrying one item between machines.
+#[derive(Debug, Clone)]
+struct Link {
+    kind: ItemKind,
+    contents: f64,
+    capacity: Option<f64>,
+    /// Items which may enter per tick.
+    throughput: Option<f64>,
+    /// Items which may still enter during this tick.
+    credit: f64,
+}
+
+impl Link {
+    fn new(kind: ItemKind, belt: Option<(f64, f64)>) -> Self {
+        let (capacity, throughput) = match (kind, belt) {
+            (ItemKind::Item, Some((capacity, throughput))) => (Some(capacity), Some(throughput)),
+            _ => (None, None),
+        };
+        Self {
+            kind,
+            contents: 0.0,
+            capacity,
+            throughput,
+            credit: 0.0,
+        }
+    }
+
+    fn begin_tick(&mut self) {
+        if let Some(throughput) = self.throughput {
+            // a backed-up belt does not deliver a burst once it clears
+            self.credit = (self.credit + throughput).min(throughput + 1.0);
+        }
+    }
+
+    fn whole(&self, amount: f64) -> f64 {
+        match self.kind {
+            ItemKind::Item => amount.floor(),
+            ItemKind::Fluid => amount,
+        }
+    }
+
+    /// Add up to `amount`, returning how much was accepted.
+    fn push(&mut self, amount: f64) -> f64 {
+        let mut space = amount;
+        if let Some(capacity) = self.capacity {
+            space = space.min(capacity - self.contents);
+        }
+        if self.throughput.is_some() {
+            space = space.min(self.credit);
+            self.credit -= self.whole(space.max(0.0));
+        }
+        let accepted = self.whole(space.max(0.0));
+        self.contents += accepted;
+        accepted
+    }
+
+    /// Remove up to `amount`, returning how much was removed.
+    fn take(&mut self, amount: f64) -> f64 {
+        let taken = self.whole(amount.min(self.contents).max(0.0));
+        self.contents -= taken;
+        taken
+    }
+}
+
+/// Why a machine could not start a craft.
+enum Stall<'a> {
+    Starved(&'a str),
+    Blocked(&'a str),
+}
+
+#[derive(Debug, Clone, Default)]
+struct MachineState {
+    inputs: BTreeMap<String, f64>,
+    outputs: BTreeMap<String, f64>,
+    /// Ticks until each inserter, by item, completes its swing.
+    cooldowns: BTreeMap<String, u64>,
+    crafting: bool,
+    progress: f64,
+    bonus_progress: f64,
+    busy_ticks: u64,
+    activity: MachineActivity,
+}
+
+impl MachineState {
+    fn try_start<'a>(
+        &mut self,
+        recipe: &'a NormalizedRecipe,
+        output_crafts: u32,
+    ) -> Option<Stall<'a>> {
+        for product in &recipe.products {
+            let held = self.outputs.get(&product.name).copied().unwrap_or(0.0);
+            if product.amount > 0 && held >= f64::from(product.amount * output_crafts) {
+                return Some(Stall::Blocked(&product.name));
+            }
+        }
+        for ingredient in &recipe.ingredients {
+            let held = self.inputs.get(&ingredient.name).copied().unwrap_or(0.0);
+            if held < f64::from(ingredient.amount) {
+                return Some(Stall::Starved(&ingredient.name));
+            }
+        }
+        for ingredient in &recipe.ingredients {
+            *self.inputs.entry(ingredient.name.clone()).or_default() -=
+                f64::from(ingredient.amount);
+        }
+        self.crafting = true;
+        None
+    }
+
+    /// Start a craft if possible, counting a tick of stall if not. Returns
+    /// whether the craft started.
+    fn start(&mut self, recipe: &NormalizedRecipe, output_crafts: u32) -> bool {
+        let stalls = match self.try_start(recipe, output_crafts) {
+            None => return true,
+            Some(Stall::Starved(item)) => self.activity.starved.entry(item.into()),
+            Some(Stall::Blocked(item)) => self.activity.blocked.entry(item.into()),
+        };
+        *stalls.or_default() += 1;
+        false
+    }
+}
+
+/// The machines crafting one recipe.
+#[derive(Debug, Clone)]
+struct RecipeState<'a> {
+    recipe: &'a NormalizedRecipe,
+    /// Crafts completed per tick by one machine.
+    speed: f64,
+    productivity: f64,
+    machines: Vec<MachineState>,
+}
+
+/// A plan being simulated.
+#[derive(Debug, Clone)]
+pub struct Simulation<'a> {
+    options: SimulationOptions,
+    recipes: Vec<RecipeState<'a>>,
+    links: BTreeMap<String, Link>,
+    /// Raw inputs arriving per tick, with the fraction not yet delivered.
+    supplies: BTreeMap<String, (f64, f64)>,
+    /// Items leaving the factory per tick, with the amount which may still
+    /// leave, for every target and surplus item.
+    sinks: BTreeMap<String, Sink>,
+    /// Ticks per swing and items per swing of the inserter, if any.
+    inserter: Option<(u64, f64)>,
+    targets: BTreeMap<String, f64>,
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug, Clone)]
+struct Sink {
+    per_tick: f64,
+    /// Items consumed within the factory are only taken at the planned
+    /// rate; all others are taken as soon as they arrive.
+    limited: bool,
+    credit: f64,
+    delivered: f64,
+    /// Items delivered in each complete sample window.
+    samples: Vec<f64>,
+    sample: f64,
+}
+
+impl<'a> Simulation<'a> {
+    /// Prepare to simulate `plan`, rounding each step up to whole machines.
+    ///
+    /// Fails if the options simulate no ticks, or sample over windows of no
+    /// ticks.
+    pub fn new<N: Number>(
+        database: &'a PrototypeDatabase,
+        plan: &ProductionPlan<N>,
+        options: &SimulationOptions,
+    ) -> Result<Self> {
+        if options.ticks == 0 {
+            return Err(Error::InvalidSimulation(
+                "at least one tick must be simulated",
+            ));
+        }
+        if options.sample_ticks == 0 {
+            return Err(Error::InvalidSimulation(
+                "sample windows must last at least one tick",
+            ));
+        }
+
+        let belt = match &options.belt {
+            Some(name) => {
+                let belt = database
+                    .belt(name)
+                    .ok_or_else(|| Error::UnknownBelt(name.clone()))?;
+                Some((
+                    ITEMS_PER_BELT_TILE * f64::from(options.belt_length),
+                    belt.rate::<f64>() / TICKS_PER_SECOND as f64,
+                ))
+            }
+            None => None,
+        };
+        let inserter = match &options.inserter {
+            Some(name) => {
+                let inserter = database
+                    .inserter(name)
+                    .ok_or_else(|| Error::UnknownInserter(name.clone()))?;
+                let swing = (1.0 / inserter.rotation_speed).ceil() as u64;
+                Some((swing, f64::from(inserter.hand_size(options.hand_size))))
+            }
+            None => None,
+        };
+
+        let mut recipes = Vec::new();
+        for (name, step) in &plan.steps {
+            let recipe = database
+                .recipe(name)
+                .ok_or_else(|| Error::UnknownRecipe(name.clone()))?;
+            let machine = database
+                .machine(&step.machine)
+                .ok_or_else(|| Error::UnknownMachine(step.machine.clone()))?;
+            let speed = machine.crafting_speed * step.effects.speed_multiplier().to_f64()
+                / (recipe.seconds::<f64>() * TICKS_PER_SECOND as f64);
+            recipes.push(RecipeState {
+                recipe,
+                speed,
+                productivity: step.effects.productivity.to_f64(),
+                machines: vec![MachineState::default(); step.machines.ceil().to_f64() as usize],
+            });
+        }
+
+        let links = plan
+            .flows
+            .iter()
+            .map(|(item, flow)| (item.clone(), Link::new(flow.kind, belt)))
+            .collect();
+        let per_tick = |rate: &N| rate.to_f64() / TICKS_PER_SECOND as f64;
+        let supplies = plan
+            .raw_inputs
+            .iter()
+            .map(|(item, rate)| (item.clone(), (per_tick(rate), 0.0)))
+            .collect();
+        let mut sinks = BTreeMap::<String, Sink>::new();
+        for (item, rate) in plan.targets.iter().chain(&plan.surplus) {
+            let limited = plan
+                .flows
+                .get(item)
+                .is_some_and(|flow| flow.consumed.is_positive());
+            let sink = sinks.entry(item.clone()).or_insert(Sink {
+                per_tick: 0.0,
+                limited,
+                credit: 0.0,
+                delivered: 0.0,
+                samples: Vec::new(),
+                sample: 0.0,
+            });
+            sink.per_tick += per_tick(rate);
+        }
+
+        Ok(Self {
+            options: options.clone(),
+            recipes,
+            links,
+            supplies,
+            sinks,
+            inserter,
+            targets: plan
+                .targets
+                .iter()
+                .map(|(item, rate)| (item.clone(), rate.to_f64()))
+                .collect(),
+            rng: ChaCha8Rng::seed_from_u64(options.seed),
+        })
+    }
+
+    /// Run every tick and report what happened.
+    pub fn run(mut self) -> SimulationReport {
+        for tick in 0..self.options.ticks {
+            self.tick();
+            if (tick + 1) % self.options.sample_ticks == 0 {
+                for sink in self.sinks.values_mut() {
+                    sink.samples.push(std::mem::take(&mut sink.sample));
+                }
+            }
+        }
+        self.report()
+    }
+
+    fn tick(&mut self) {
+        for link in self.links.values_mut() {
+            link.begin_tick();
+        }
+
+        for (item, (per_tick, pending)) in &mut self.supplies {
+            let link = link(&mut self.links, item);
+            *pending = (*pending + *per_tick).min(*per_tick + 1.0);
+            *pending -= link.push(*pending);
+        }
+
+        let options = &self.options;
+        for state in &mut self.recipes {
+            let recipe = state.recipe;
+            for machine in &mut state.machines {
+                // unload products
+                for product in &recipe.products {
+                    let held = machine.outputs.get(&product.name).copied().unwrap_or(0.0);
+                    let link = link(&mut self.links, &product.name);
+                    let Some(amount) =
+                        transfer(machine, self.inserter, link.kind, &product.name, held)
+                    else {
+                        continue;
+                    };
+                    let moved = link.push(amount);
+                    if moved > 0.0 {
+                        *machine.outputs.get_mut(&product.name).unwrap() -= moved;
+                        swing(machine, self.inserter, link.kind, &product.name);
+                    }
+                }
+
+                // load ingredients
+                for ingredient in &recipe.ingredients {
+                    let held = machine.inputs.get(&ingredient.name).copied().unwrap_or(0.0);
+                    let wanted = f64::from(ingredient.amount * options.input_crafts) - held;
+                    let link = link(&mut self.links, &ingredient.name);
+                    let Some(amount) =
+                        transfer(machine, self.inserter, link.kind, &ingredient.name, wanted)
+                    else {
+                        continue;
+                    };
+                    let moved = link.take(amount);
+                    if moved > 0.0 {
+                        *machine.inputs.entry(ingredient.name.clone()).or_default() += moved;
+                        swing(machine, self.inserter, link.kind, &ingredient.name);
+                    }
+                }
+
+                // craft
+                if !machine.crafting {
+                    machine.start(recipe, options.output_crafts);
+                }
+                if machine.crafting {
+                    machine.busy_ticks += 1;
+                    machine.progress += state.speed;
+                    while machine.crafting && machine.progress >= 1.0 {
+                        machine.progress -= 1.0;
+                        machine.crafting = false;
+                        machine.activity.crafts += 1;
+                        machine.bonus_progress += state.productivity;
+                        let mut results = 1;
+                        if machine.bonus_progress >= 1.0 {
+                            machine.bonus_progress -= 1.0;
+                            results += 1;
+                        }
+                        for _ in 0..results {
+                            for product in &recipe.products {
+                                if self.rng.gen_bool(product.probability.clamp(0.0, 1.0)) {
+                                    *machine.outputs.entry(product.name.clone()).or_default() +=
+                                        f64::from(product.amount);
+                                }
+                            }
+                        }
+                        // carry the remaining progress into the next craft
+                        if !machine.start(recipe, options.output_crafts) {
+                            machine.progress = 0.0;
+                        }
+                    }
+                }
+
+                for cooldown in machine.cooldowns.values_mut() {
+                    *cooldown = cooldown.saturating_sub(1);
+                }
+            }
+        }
+
+        for (item, sink) in &mut self.sinks {
+            let link = link(&mut self.links, item);
+            let taken = if sink.limited {
+                sink.credit += sink.per_tick;
+                let taken = link.take(sink.credit);
+                sink.credit -= taken;
+                taken
+            } else {
+                link.take(link.contents)
+            };
+            sink.delivered += taken;
+            sink.sample += taken;
+        }
+    }
+
+    fn report(self) -> SimulationReport {
+        let ticks = self.options.ticks;
+        let seconds = ticks as f64 / TICKS_PER_SECOND as f64;
+        let window_seconds = self.options.sample_ticks as f64 / TICKS_PER_SECOND as f64;
+
+        // the last window from which the factory stays within tolerance
+        let windows = ticks / self.options.sample_ticks;
+        let steady_from = (0..windows)
+            .rev()
+            .take_while(|&window| {
+                self.targets.iter().all(|(item, rate)| {
+                    let delivered = self.sinks[item].samples[window as usize];
+                    delivered >= (1.0 - self.options.tolerance) * rate * window_seconds
+                })
+            })
+            .last();
+
+        let machines = self
+            .recipes
+            .into_iter()
+            .map(|state| {
+                let machines = state
+                    .machines
+                    .into_iter()
+                    .map(|machine| MachineActivity {
+                        utilization: machine.busy_ticks as f64 / ticks as f64,
+                        ..machine.activity
+                    })
+                    .collect();
+                (state.recipe.name.clone(), machines)
+            })
+            .collect();
+        let delivered = self
+            .targets
+            .iter()
+            .map(|(item, rate)| {
+                let items = self.sinks[item].delivered;
+                let delivery = Delivery {
+                    items,
+                    per_second: items / seconds,
+                    planned_per_second: *rate,
+                };
+                (item.clone(), delivery)
+            })
+            .collect();
+
+        SimulationReport {
+            ticks,
+            seed: self.options.seed,
+            ticks_to_steady_state: steady_from.map(|window| window * self.options.sample_ticks),
+            machines,
+            delivered,
+        }
+    }
+}
+
+fn link<'a>(links: &'a mut BTreeMap<String, Link>, item: &str) -> &'a mut Link {
+    links
+        .entry(item.into())
+        .or_insert_with(|| Link::new(ItemKind::Fluid, None))
+}
+
+/// The amount an inserter may move this tick, if it is ready to swing.
+fn transfer(
+    machine: &MachineState,
+    inserter: Option<(u64, f64)>,
+    kind: ItemKind,
+    item: &str,
+    wanted: f64,
+) -> Option<f64> {
+    if wanted <= 0.0 {
+        return None;
+    }
+    match (inserter, kind) {
+        (Some((_, hand)), ItemKind::Item) => {
+            let ready = machine.cooldowns.get(item).is_none_or(|&ticks| ticks == 0);
+            ready.then_some(wanted.min(hand))
+        }
+        _ => Some(wanted),
+    }
+}
+
+fn swing(machine: &mut MachineState, inserter: Option<(u64, f64)>, kind: ItemKind, item: &str) {
+    if let (Some((ticks, _)), ItemKind::Item) = (inserter, kind) {
+        machine.cooldowns.insert(item.into(), ticks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        belt::Belt,
+        inserter::Inserter,
+        plan::{
+            tests::{database, request},
+            PlanRequest,
+        },
+    };
+
+    fn plan(targets: &[(&str, f64)]) -> ProductionPlan {
+        ProductionPlan::new(&database(), &request(targets, &[])).unwrap()
+    }
+
+    #[test]
+    fn reaches_steady_state() {
+        let database = database();
+        let plan = plan(&[("electronic-circuit", 2.5)]);
+        let report = Simulation::new(&database, &plan, &SimulationOptions::default())
+            .unwrap()
+            .run();
+
+        let delivery = &report.delivered["electronic-circuit"];
+        assert!((delivery.per_second - 2.5).abs() < 0.1, "{delivery:?}");
+        let steady = report.ticks_to_steady_state.unwrap();
+        assert!(steady <= 1200, "{steady}");
+        // 2.5 cable machines round up to 3, fed copper for 2.5
+        assert_eq!(report.machines["copper-cable"].len(), 3);
+        let utilization = report.utilization("copper-cable").unwrap();
+        assert!((utilization - 2.5 / 3.0).abs() < 0.02, "{utilization}");
+        assert!(report.machines["copper-cable"][2].starved["copper-plate"] > 0);
+    }
+
+    #[test]
+    fn slow_belts_starve_machines() {
+        let mut database = database();
+        database.belts.insert(
+            "transport-belt".into(),
+            Belt {
+                name: "transport-belt".into(),
+                speed: 0.03125,
+            },
+        );
+        let plan = plan(&[("electronic-circuit", 30.0)]);
+        let options = SimulationOptions {
+            belt: Some("transport-belt".into()),
+            ticks: 3600,
+            ..Default::default()
+        };
+        let report = Simulation::new(&database, &plan, &options).unwrap().run();
+
+        // 90 cables per second cannot fit on a 15 per second belt
+        assert_eq!(report.ticks_to_steady_state, None);
+        assert!(report.delivered["electronic-circuit"].per_second < 6.0);
+        assert!(report.utilization("electronic-circuit").unwrap() < 0.3);
+    }
+
+    #[test]
+    fn slow_inserters_starve_machines() {
+        let mut database = database();
+        database.inserters.insert(
+            "inserter".into(),
+            Inserter {
+                name: "inserter".into(),
+                rotation_speed: 0.014,
+                extension_speed: 0.03,
+                stack: false,
+            },
+        );
+        let plan = plan(&[("iron-gear-wheel", 1.5)]);
+        let options = SimulationOptions {
+            inserter: Some("inserter".into()),
+            ticks: 3600,
+            ..Default::default()
+        };
+        let report = Simulation::new(&database, &plan, &options).unwrap().run();
+
+        // one plate per 72 tick swing, where 3 plates per second are needed
+        let machine = &report.machines["iron-gear-wheel"][0];
+        assert!(machine.utilization < 0.3, "{machine:?}");
+        assert!(machine.starved["iron-plate"] > 2400);
+    }
+
+    #[test]
+    fn rejects_empty_windows() {
+        let database = database();
+        let plan = plan(&[("electronic-circuit", 2.5)]);
+        for options in [
+            SimulationOptions {
+                ticks: 0,
+                ..Default::default()
+            },
+            SimulationOptions {
+                sample_ticks: 0,
+                ..Default::default()
+            },
+        ] {
+            let err = Simulation::new(&database, &plan, &options).unwrap_err();
+            assert!(matches!(err, Error::InvalidSimulation(_)), "{err:?}");
+        }
+    }
+
+    #[test]
+    fn seeded_rolls() {
+        let database = database();
+        let mut request = PlanRequest {
+            targets: BTreeMap::from([("uranium-238".into(), 0.993)]),
+            ..Default::default()
+        };
+        request
+            .policy
+            .recipes
+            .insert("uranium-238".into(), "uranium-processing".into());
+        let plan = ProductionPlan::new(&database, &request).unwrap();
+
+        let run = |seed| {
+            let options = SimulationOptions {
+                seed,
+                ..Default::default()
+            };
+            Simulation::new(&database, &plan, &options).unwrap().run()
+        };
+        let report = run(7);
+        assert_eq!(report, run(7));
+        assert_ne!(report.delivered, run(8).delivered);
+
+        // uranium-235 is surplus: delivered, but not a target
+        assert!(!report.delivered.contains_key("uranium-235"));
+        assert_eq!(report.machines["uranium-processing"].len(), 12);
+        assert!(report.machines["uranium-processing"][0].crafts >= 48);
+    }
+}
@@ -0,0 +1,514 @@
+//! Linear-programming production solver.
+//!
+//! The tree walk in [`ProductionPlan::new`] resolves one recipe per item, so it
+//! cannot balance recipes with several outputs against one another: oil
+//! processing, uranium processing, and many mod recipes. The solver instead
+//! considers every candidate recipe at once, and finds the combination of
+//! craft rates which meets the targets while minimizing an [`Objective`].
+//!
+//! For each item, the solver requires that production minus consumption,
+//! plus any raw supply, minus any discarded surplus, equals the target rate.
+//! Surplus may only be discarded for items whose [`Byproduct`] policy allows
+//! it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::PrototypeDatabase,
+    number::Number,
+    plan::{Configuration, PlanRequest, ProductionPlan},
+    recipe::NormalizedRecipe,
+    simplex::{Constraint, LinearProgram, Outcome, Relation},
+    Error, Result,
+};
+
+/// A small weight given to secondary costs, so that ties in the primary
+/// objective are broken consistently.
+const TIE_BREAK: f64 = 1e-6;
+
+/// The quantity which the solver minimizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    /// The total rate of raw inputs, in items or units of fluid per second.
+    #[default]
+    RawResources,
+    /// The total number of machines.
+    Machines,
+    /// The total power drawn by machines while crafting.
+    Power,
+}
+
+/// How surplus production of an item is treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Byproduct {
+    /// Surplus may be discarded: voided, stored, or shipped elsewhere.
+    #[default]
+    Sink,
+    /// Surplus is forbidden: every unit produced must be consumed within the
+    /// plan or delivered as a target.
+    Consume,
+}
+
+/// Options for the LP solver, beyond the [`PlanRequest`] shared with the
+/// tree-walking planner.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SolverOptions {
+    #[serde(default)]
+    pub objective: Objective,
+    /// Byproduct policy per item; items not listed are [`Byproduct::Sink`].
+    #[serde(default)]
+    pub byproducts: BTreeMap<String, Byproduct>,
+}
+
+impl SolverOptions {
+    fn byproduct(&self, item: &str) -> Byproduct {
+        self.byproducts.get(item).copied().unwrap_or_default()
+    }
+}
+
+/// An item whose balance could not be satisfied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfeasibleItem {
+    pub item: String,
+    /// How far the item balance is from being met, per second.
+    ///
+    /// Positive values are a shortfall: the item cannot be produced fast
+    /// enough. Negative values are an excess: the item is produced, but must
+    /// be consumed and nothing can consume it.
+    pub residual: f64,
+    /// The supply limit of the item, when the plan closest to feasibility
+    /// draws all of it: the limit is what binds, even if it is another item
+    /// which falls short.
+    pub limit: Option<f64>,
+}
+
+impl fmt::Display for InfeasibleItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.residual > 0.0 {
+            write!(f, "\"{}\" falls short by {}/s", self.item, self.residual)?;
+        } else if self.residual < 0.0 {
+            write!(
+                f,
+                "\"{}\" has an excess of {}/s which must be consumed but cannot be",
+                self.item, -self.residual
+            )?;
+        } else {
+            write!(f, "\"{}\" is supplied", self.item)?;
+        }
+        match self.limit {
+            Some(limit) if self.residual == 0.0 => write!(f, " at its limit of {limit}/s"),
+            Some(limit) => write!(f, ", with its supply at its limit of {limit}/s"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Format a list of infeasible items for an error message.
+pub(crate) fn explain(items: &[InfeasibleItem]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The recipes and items which take part in a solve.
+struct Problem<'a> {
+    recipes: BTreeMap<&'a str, &'a NormalizedRecipe>,
+    items: BTreeSet<String>,
+    raw: BTreeSet<String>,
+}
+
+impl<'a> Problem<'a> {
+    fn new<N>(
+        database: &'a PrototypeDatabase,
+        request: &PlanRequest<N>,
+        options: &SolverOptions,
+    ) -> Result<Self> {
+        let mut problem = Self {
+            recipes: BTreeMap::new(),
+            items: BTreeSet::new(),
+            raw: BTreeSet::new(),
+        };
+
+        let mut producers_expanded = BTreeSet::new();
+        let mut consumers_expanded = BTreeSet::new();
+        // (item, whether the item is demanded rather than merely produced)
+        let mut queue = request
+            .targets
+            .keys()
+            .map(|item| (item.clone(), true))
+            .collect::<Vec<_>>();
+
+        while let Some((item, demanded)) = queue.pop() {
+            problem.items.insert(item.clone());
+            let mut added = Vec::new();
+
+            if demanded && request.supplied.contains_key(&item) {
+                problem.raw.insert(item.clone());
+            } else if demanded && producers_expanded.insert(item.clone()) {
+                let producers = request.policy.producers(database, &item)?;
+                if producers.is_empty() {
+                    problem.raw.insert(item.clone());
+                }
+                added.extend(producers);
+            }
+
+            if options.byproduct(&item) == Byproduct::Consume
+                && consumers_expanded.insert(item.clone())
+            {
+                added.extend(database.consumers(&item).filter(|recipe| {
+                    request.policy.admits(recipe)
+                        && recipe.net_amounts::<f64>()[item.as_str()] < 0.0
+                }));
+            }
+
+            for recipe in added {
+                if problem.recipes.insert(&recipe.name, recipe).is_some() {
+                    continue;
+                }
+                for (name, amount) in recipe.net_amounts::<f64>() {
+                    queue.push((name.to_owned(), amount < 0.0));
+                }
+            }
+        }
+
+        Ok(problem)
+    }
+}
+
+impl<N: Number> ProductionPlan<N> {
+    /// Plan production to meet the requested targets by linear programming.
+    ///
+    /// Every recipe which the request's [`RecipePolicy`][crate::policy::RecipePolicy] allows to produce a
+    /// demanded item is a candidate, so alternative recipes compete on the
+    /// [`Objective`]. The policy's preference for the cheapest recipe is
+    /// ignored, since the solver makes its own choice.
+    ///
+    /// [Supplied][PlanRequest::supplied] items are treated as raw inputs, and
+    /// their limits as constraints on the supply rate.
+    ///
+    /// When no plan satisfies every item balance, fails with
+    /// [`Error::Infeasible`], listing the items whose balance could not be met
+    /// and the supply limits which bind.
+    pub fn solve(
+        database: &PrototypeDatabase,
+        request: &PlanRequest<N>,
+        options: &SolverOptions,
+    ) -> Result<Self> {
+        let problem = Problem::new(database, request, options)?;
+
+        let recipes = problem.recipes.values().copied().collect::<Vec<_>>();
+        let configurations = recipes
+            .iter()
+            .map(|recipe| Configuration::new(database, request, recipe))
+            .collect::<Result<Vec<_>>>()?;
+        let raw = problem.raw.iter().collect::<Vec<_>>();
+        let sinks = problem
+            .items
+            .iter()
+            .filter(|item| {
+                !problem.raw.contains(*item) && options.byproduct(item) == Byproduct::Sink
+            })
+            .collect::<Vec<_>>();
+
+        // variables: recipe craft rates, then raw supply rates, then discard rates
+        let supply_start = recipes.len();
+        let sink_start = supply_start + raw.len();
+        let variables = sink_start + sinks.len();
+
+        let tie_break = N::from_f64(TIE_BREAK);
+        let mut costs = vec![N::zero(); variables];
+        for (idx, (recipe, configuration)) in recipes.iter().zip(&configurations).enumerate() {
+            let machines_per_craft = configuration.machines_per_craft(recipe);
+            costs[idx] = match options.objective {
+                Objective::RawResources => tie_break.clone() * machines_per_craft,
+                Objective::Machines => machines_per_craft,
+                // megawatts, to keep coefficients near unity
+                Objective::Power => {
+                    machines_per_craft * configuration.power.clone() / N::from_u32(1_000_000)
+                }
+            };
+        }
+        for cost in &mut costs[supply_start..sink_start] {
+            *cost = match options.objective {
+                Objective::RawResources => N::one(),
+                Objective::Machines | Objective::Power => tie_break.clone(),
+            };
+        }
+
+        let items = problem.items.iter().collect::<Vec<_>>();
+        let mut constraints = items
+            .iter()
+            .map(|&item| {
+                let mut coefficients = vec![N::zero(); variables];
+                for (idx, (recipe, configuration)) in
+                    recipes.iter().zip(&configurations).enumerate()
+                {
+                    if let Some(amount) = configuration.net_amounts(recipe).remove(item.as_str()) {
+                        coefficients[idx] = amount;
+                    }
+                }
+                if let Some(idx) = raw.iter().position(|&raw| raw == item) {
+                    coefficients[supply_start + idx] = N::one();
+                }
+                if let Some(idx) = sinks.iter().position(|&sink| sink == item) {
+                    coefficients[sink_start + idx] = -N::one();
+                }
+                Constraint {
+                    coefficients,
+                    relation: Relation::Eq,
+                    rhs: request.targets.get(item).cloned().unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // one row per supply limit, after the item balances
+        let limited = raw
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &item)| {
+                let limit = request.supplied.get(item)?.limit.clone()?;
+                Some((idx, item, limit))
+            })
+            .collect::<Vec<_>>();
+        for (idx, _, limit) in &limited {
+            let mut coefficients = vec![N::zero(); variables];
+            coefficients[supply_start + idx] = N::one();
+            constraints.push(Constraint {
+                coefficients,
+                relation: Relation::Le,
+                rhs: limit.clone(),
+            });
+        }
+
+        let lp = LinearProgram { costs, constraints };
+        let values = match lp.solve() {
+            Outcome::Optimal { values, .. } => values,
+            Outcome::Infeasible { residuals, values } => {
+                // supply beyond a limit is reported as a shortfall of the item
+                let rows = items
+                    .iter()
+                    .map(|&item| (item, N::one()))
+                    .chain(limited.iter().map(|&(_, item, _)| (item, -N::one())));
+                let mut shortfalls = BTreeMap::<&String, N>::new();
+                for ((item, sign), residual) in rows.zip(residuals) {
+                    *shortfalls.entry(item).or_default() += sign * residual;
+                }
+                // the residuals are in units of each row, so a limit which
+                // binds may leave no residual of its own: name it anyway
+                let binding = limited
+                    .iter()
+                    .filter(|(idx, _, limit)| {
+                        !(values[supply_start + idx].clone() - limit.clone()).is_negative()
+                    })
+                    .map(|(_, item, limit)| (*item, limit.to_f64()))
+                    .collect::<BTreeMap<_, _>>();
+                return Err(Error::Infeasible(
+                    shortfalls
+                        .into_iter()
+                        .filter(|(item, residual)| {
+                            !residual.is_zero() || binding.contains_key(item)
+                        })
+                        .map(|(item, residual)| InfeasibleItem {
+                            item: item.clone(),
+                            residual: residual.to_f64(),
+                            limit: binding.get(item).copied(),
+                        })
+                        .collect(),
+                ));
+            }
+            Outcome::Unbounded => {
+                unreachable!("costs are nonnegative, so the objective is bounded below")
+            }
+        };
+
+        let mut plan = Self {
+            targets: request.targets.clone(),
+            ..Self::default()
+        };
+        let mut values = values.into_iter();
+        for ((recipe, configuration), crafts_per_second) in
+            recipes.iter().zip(&configurations).zip(&mut values)
+        {
+            if crafts_per_second.is_positive() {
+                plan.add_step(recipe, configuration, crafts_per_second);
+            }
+        }
+        for (&item, supply) in raw.iter().zip(values) {
+            if supply.is_positive() {
+                plan.raw_inputs.insert(item.clone(), supply);
+            }
+        }
+        plan.compute_surplus();
+
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::{
+        tests::{assert_close, database, request},
+        Supply,
+    };
+
+    #[test]
+    fn matches_tree_walk_without_alternatives() {
+        let database = database();
+        let request = request(&[("electronic-circuit", 45.0)], &[]);
+        let walked = ProductionPlan::new(&database, &request).unwrap();
+        let solved = ProductionPlan::solve(&database, &request, &SolverOptions::default()).unwrap();
+
+        assert_eq!(
+            walked.steps.keys().collect::<Vec<_>>(),
+            solved.steps.keys().collect::<Vec<_>>()
+        );
+        for (name, step) in &walked.steps {
+            assert_close(solved.steps[name].machines, step.machines);
+        }
+        assert_close(solved.raw_inputs["copper-plate"], 67.5);
+    }
+
+    #[test]
+    fn cheapest_alternative() {
+        // basic oil processing yields more gas per crude oil and needs no water
+        let plan = ProductionPlan::solve(
+            &database(),
+            &request(&[("petroleum-gas", 45.0)], &[]),
+            &SolverOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            plan.steps.keys().collect::<Vec<_>>(),
+            ["basic-oil-processing"]
+        );
+        assert_close(plan.raw_inputs["crude-oil"], 100.0);
+    }
+
+    #[test]
+    fn consumes_byproducts() {
+        let options = SolverOptions {
+            objective: Objective::Machines,
+            byproducts: BTreeMap::from([
+                ("heavy-oil".into(), Byproduct::Consume),
+                ("light-oil".into(), Byproduct::Consume),
+            ]),
+        };
+        let mut request = request(&[("petroleum-gas", 97.5)], &[]);
+        request.policy.excluded_recipes = BTreeSet::from(["basic-oil-processing".into()]);
+        let plan = ProductionPlan::solve(&database(), &request, &options).unwrap();
+
+        assert_close(plan.steps["advanced-oil-processing"].crafts_per_second, 1.0);
+        assert_close(plan.steps["heavy-oil-cracking"].crafts_per_second, 0.625);
+        assert_close(plan.steps["light-oil-cracking"].crafts_per_second, 2.125);
+        assert_close(plan.raw_inputs["water"], 132.5);
+        assert!(plan.surplus.is_empty(), "{:?}", plan.surplus);
+    }
+
+    #[test]
+    fn explains_infeasibility() {
+        let options = SolverOptions {
+            byproducts: BTreeMap::from([("heavy-oil".into(), Byproduct::Consume)]),
+            ..Default::default()
+        };
+        let mut request = request(&[("petroleum-gas", 11.0)], &[]);
+        request.policy.excluded_recipes =
+            BTreeSet::from(["basic-oil-processing".into(), "heavy-oil-cracking".into()]);
+        let err = ProductionPlan::solve(&database(), &request, &options).unwrap_err();
+
+        let Error::Infeasible(items) = err else {
+            panic!("expected infeasibility, found {err:?}");
+        };
+        // cracking the light oil, each refinery craft yields 85 petroleum gas
+        // and 25 heavy oil which nothing can consume
+        let heavy = items.iter().find(|item| item.item == "heavy-oil").unwrap();
+        assert_close(heavy.residual, -25.0 * 11.0 / 85.0);
+    }
+
+    #[test]
+    fn catalyst_loop() {
+        let mut request = request(&[("uranium-235", 1.0)], &[]);
+        request
+            .policy
+            .recipes
+            .insert("uranium-235".into(), "kovarex-enrichment-process".into());
+        let plan = ProductionPlan::solve(&database(), &request, &SolverOptions::default()).unwrap();
+
+        // uranium processing contributes a little uranium-235 of its own; it
+        // runs just fast enough to supply the uranium-238 kovarex consumes
+        let processing = 3.0 / (0.993 + 3.0 * 0.007);
+        assert_close(
+            plan.steps["uranium-processing"].crafts_per_second,
+            processing,
+        );
+        assert_close(
+            plan.steps["kovarex-enrichment-process"].crafts_per_second,
+            1.0 - 0.007 * processing,
+        );
+        assert_close(plan.raw_inputs["uranium-ore"], 10.0 * processing);
+    }
+
+    #[test]
+    fn exact_catalyst_loop() {
+        use crate::number::Rational;
+
+        let mut request = PlanRequest {
+            targets: BTreeMap::from([("uranium-235".into(), Rational::new(1, 1))]),
+            ..Default::default()
+        };
+        request
+            .policy
+            .recipes
+            .insert("uranium-235".into(), "kovarex-enrichment-process".into());
+        let plan = ProductionPlan::solve(&database(), &request, &SolverOptions::default()).unwrap();
+
+        assert_eq!(
+            plan.steps["uranium-processing"].crafts_per_second,
+            Rational::new(500, 169)
+        );
+        assert_eq!(
+            plan.steps["kovarex-enrichment-process"].crafts_per_second,
+            Rational::new(331, 338)
+        );
+    }
+
+    #[test]
+    fn supply_limits() {
+        let mut request = request(&[("plastic-bar", 2.0)], &[]);
+        request
+            .supplied
+            .insert("petroleum-gas".into(), Supply::default());
+        let plan = ProductionPlan::solve(&database(), &request, &SolverOptions::default()).unwrap();
+        assert_eq!(plan.steps.keys().collect::<Vec<_>>(), ["plastic-bar"]);
+        assert_close(plan.raw_inputs["petroleum-gas"], 20.0);
+
+        request.targets.insert("plastic-bar".into(), 3.0);
+        request
+            .supplied
+            .insert("petroleum-gas".into(), Supply { limit: Some(20.0) });
+        let err =
+            ProductionPlan::solve(&database(), &request, &SolverOptions::default()).unwrap_err();
+        let Error::Infeasible(items) = err else {
+            panic!("expected infeasibility, found {err:?}");
+        };
+        // 20 gas per second makes only 2 of the 3 bars
+        assert_eq!(items.len(), 2, "{items:?}");
+        assert_eq!(items[0].item, "petroleum-gas");
+        assert_eq!(items[0].limit, Some(20.0));
+        assert_eq!(items[1].item, "plastic-bar");
+        assert_close(items[1].residual, 1.0);
+        assert_eq!(
+            explain(&items),
+            "\"petroleum-gas\" is supplied at its limit of 20/s; \"plastic-bar\" falls short by 1/s"
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Statistics of probabilistic products.
+//!
+//! A plan's rates are expected values: uranium processing yields uranium-235
+//! with probability 0.7%, so the plan credits it with 0.007 per craft. Over a
+//! finite window the actual count varies, and a build which needs a steady
+//! supply must allow for that. Each craft rolls each probabilistic product
+//! independently, so the number of successes over a window follows a binomial
+//! distribution, from which [`OutputStatistics`] derives the variance,
+//! confidence bounds, and the buffer needed to smooth the output.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use crate::{
+    database::PrototypeDatabase, number::Number, plan::ProductionPlan, recipe::Product, Error,
+    Result,
+};
+
+/// Probability masses smaller than this fraction of the most likely outcome
+/// are neglected.
+const NEGLIGIBLE_MASS: f64 = 1e-18;
+
+/// The number of successes in a fixed number of independent trials.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Binomial {
+    pub trials: u64,
+    /// The chance that each trial succeeds, between 0 and 1.
+    pub probability: f64,
+}
+
+impl Binomial {
+    pub fn mean(&self) -> f64 {
+        self.trials as f64 * self.probability
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.mean() * (1.0 - self.probability)
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// The chance of at least `successes` successes.
+    pub fn at_least(&self, successes: u64) -> f64 {
+        let (first, masses) = self.masses();
+        let skip = successes.saturating_sub(first) as usize;
+        masses.iter().skip(skip).sum::<f64>().min(1.0)
+    }
+
+    /// The chance of at most `successes` successes.
+    pub fn at_most(&self, successes: u64) -> f64 {
+        let (first, masses) = self.masses();
+        let Some(count) = (successes + 1).checked_sub(first) else {
+            return 0.0;
+        };
+        masses.iter().take(count as usize).sum::<f64>().min(1.0)
+    }
+
+    /// The greatest number of successes which occur with at least the given
+    /// confidence.
+    pub fn lower_bound(&self, confidence: f64) -> u64 {
+        let (first, masses) = self.masses();
+        let mut tail = 0.0;
+        for (offset, mass) in masses.iter().enumerate().rev() {
+            tail += mass;
+            if tail >= confidence - f64::EPSILON {
+                return first + offset as u64;
+            }
+        }
+        first
+    }
+
+    /// The least number of successes which is not exceeded with at least the
+    /// given confidence.
+    pub fn upper_bound(&self, confidence: f64) -> u64 {
+        let (first, masses) = self.masses();
+        let mut head = 0.0;
+        for (offset, mass) in masses.iter().enumerate() {
+            head += mass;
+            if head >= confidence - f64::EPSILON {
+                return first + offset as u64;
+            }
+        }
+        first + masses.len().saturating_sub(1) as u64
+    }
+
+    /// The probability of each number of successes with non-negligible mass,
+    /// starting from the first such number.
+    ///
+    /// Masses are computed outwards from the most likely outcome, which
+    /// avoids the underflow of `(1 - p)^n` for large `n`.
+    fn masses(&self) -> (u64, Vec<f64>) {
+        let (n, p) = (self.trials, self.probability);
+        if n == 0 || p <= 0.0 {
+            return (0, vec![1.0]);
+        }
+        if p >= 1.0 {
+            return (n, vec![1.0]);
+        }
+
+        let mode = (((n + 1) as f64 * p).floor() as u64).min(n);
+        let smaller = mode.min(n - mode);
+        let ln_choose = (1..=smaller)
+            .map(|i| ((n - smaller + i) as f64 / i as f64).ln())
+            .sum::<f64>();
+        let ln_mode = ln_choose + mode as f64 * p.ln() + (n - mode) as f64 * (1.0 - p).ln();
+        let peak = ln_mode.exp();
+        let odds = p / (1.0 - p);
+
+        let mut below = Vec::new();
+        let mut mass = peak;
+        let mut k = mode;
+        while k > 0 {
+            mass *= k as f64 / (n - k + 1) as f64 / odds;
+            if mass < peak * NEGLIGIBLE_MASS {
+                break;
+            }
+            below.push(mass);
+            k -= 1;
+        }
+        let first = mode - below.len() as u64;
+
+        let mut masses = below.into_iter().rev().collect::<Vec<_>>();
+        masses.push(peak);
+        let mut mass = peak;
+        for k in mode..n {
+            mass *= (n - k) as f64 / (k + 1) as f64 * odds;
+            if mass < peak * NEGLIGIBLE_MASS {
+                break;
+            }
+            masses.push(mass);
+        }
+
+        // the terms are accurate relative to each other, so normalize away
+        // the rounding error in the peak
+        let total = masses.iter().sum::<f64>();
+        masses.iter_mut().for_each(|mass| *mass /= total);
+        (first, masses)
+    }
+}
+
+/// The spread of a probabilistic product over a window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductStatistics {
+    /// The amount yielded by each successful roll.
+    pub amount: u32,
+    /// The chance that each roll succeeds.
+    pub probability: f64,
+    /// The number of times the product is rolled during the window: one per
+    /// craft, including bonus crafts from productivity.
+    pub rolls: u64,
+    /// The expected number of items produced.
+    pub expected: f64,
+    /// The standard deviation of the number of items produced.
+    pub std_dev: f64,
+    /// At least this many items are produced, with the requested confidence.
+    pub lower_bound: u64,
+    /// At most this many items are produced, with the requested confidence.
+    pub upper_bound: u64,
+    /// The stock needed at the start of the window so that a consumer drawing
+    /// the expected rate is not starved, with the requested confidence.
+    pub buffer: u64,
+}
+
+impl ProductStatistics {
+    pub fn new(product: &Product, rolls: u64, confidence: f64) -> Self {
+        let distribution = Binomial {
+            trials: rolls,
+            probability: product.probability,
+        };
+        let amount = u64::from(product.amount);
+        let expected = distribution.mean() * amount as f64;
+        let lower_bound = distribution.lower_bound(confidence) * amount;
+        Self {
+            amount: product.amount,
+            probability: product.probability,
+            rolls,
+            expected,
+            std_dev: distribution.std_dev() * amount as f64,
+            lower_bound,
+            upper_bound: distribution.upper_bound(confidence) * amount,
+            buffer: Number::ceil(&(expected - lower_bound as f64)).max(0.0) as u64,
+        }
+    }
+
+    /// The chance that at least `items` are produced.
+    pub fn at_least(&self, items: u64) -> f64 {
+        let distribution = Binomial {
+            trials: self.rolls,
+            probability: self.probability,
+        };
+        distribution.at_least(items.div_ceil(u64::from(self.amount.max(1))))
+    }
+}
+
+/// The window and confidence over which to describe probabilistic products.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatisticsOptions {
+    pub window: Duration,
+    /// The confidence of the reported bounds, between 0 and 1.
+    pub confidence: f64,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        Self {
+            window: Duration::minutes(10),
+            confidence: 0.95,
+        }
+    }
+}
+
+/// The statistics of every probabilistic product of a plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputStatistics {
+    pub window_seconds: f64,
+    pub confidence: f64,
+    /// Statistics by recipe, then by product. Products which are certain are
+    /// omitted.
+    pub recipes: BTreeMap<String, BTreeMap<String, ProductStatistics>>,
+}
+
+impl OutputStatistics {
+    /// Describe the probabilistic products of every step in `plan`.
+    ///
+    /// A fractional number of crafts in the window is rounded down, so the
+    /// bounds are slightly conservative.
+    pub fn new<N: Number>(
+        database: &PrototypeDatabase,
+        plan: &ProductionPlan<N>,
+        options: &StatisticsOptions,
+    ) -> Result<Self> {
+        let window = N::from_duration(options.window);
+        let mut recipes = BTreeMap::new();
+        for (name, step) in &plan.steps {
+            let recipe = database
+                .recipe(name)
+                .ok_or_else(|| Error::UnknownRecipe(name.clone()))?;
+            let rolls = (step.crafts_per_second.clone()
+                * (N::one() + step.effects.productivity.clone())
+                * window.clone())
+            .to_f64();
+            // within rounding error of a whole number of crafts
+            let rolls = (rolls + 1e-9).floor() as u64;
+
+            let products = recipe
+                .products
+                .iter()
+                .filter(|product| product.probability < 1.0)
+                .map(|product| {
+                    (
+                        product.name.clone(),
+                        ProductStatistics::new(product, rolls, options.confidence),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>();
+            if !products.is_empty() {
+                recipes.insert(name.clone(), products);
+            }
+        }
+
+        Ok(Self {
+            window_seconds: options.window.as_seconds_f64(),
+            confidence: options.confidence,
+            recipes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        plan::{
+            tests::{assert_close, database},
+            PlanRequest,
+        },
+        recipe::ItemKind,
+    };
+
+    #[test]
+    fn binomial() {
+        let coin = Binomial {
+            trials: 10,
+            probability: 0.5,
+        };
+        assert_close(coin.mean(), 5.0);
+        assert_close(coin.variance(), 2.5);
+        assert_close(coin.at_least(5), 638.0 / 1024.0);
+        assert_close(coin.at_most(4), 386.0 / 1024.0);
+        assert_close(coin.at_least(0), 1.0);
+        assert_close(coin.at_least(11), 0.0);
+        // P(X >= 2) = 1013/1024, P(X >= 3) = 968/1024
+        assert_eq!(coin.lower_bound(0.95), 2);
+        assert_eq!(coin.upper_bound(0.95), 8);
+
+        let certain = Binomial {
+            trials: 7,
+            probability: 1.0,
+        };
+        assert_eq!(certain.lower_bound(0.99), 7);
+        assert_close(certain.at_least(7), 1.0);
+
+        // (1 - p)^n underflows to zero for this many trials
+        let many = Binomial {
+            trials: 1_000_000,
+            probability: 0.993,
+        };
+        assert_close(many.at_least(0), 1.0);
+        let spread = many.std_dev();
+        assert!(many.lower_bound(0.95) as f64 > many.mean() - 2.0 * spread);
+        assert!((many.lower_bound(0.95) as f64) < many.mean() - spread);
+    }
+
+    #[test]
+    fn ten_centrifuges() {
+        let u235 = Product {
+            name: "uranium-235".into(),
+            kind: ItemKind::Item,
+            amount: 1,
+            probability: 0.007,
+        };
+        // 10 centrifuges crafting 12 second uranium processing for 10 minutes
+        let stats = ProductStatistics::new(&u235, 500, 0.95);
+        assert_close(stats.expected, 3.5);
+        assert_eq!(stats.lower_bound, 1);
+        assert!(stats.at_least(1) > 0.95);
+        assert!(stats.at_least(2) < 0.95);
+        assert!(stats.upper_bound >= 6);
+        assert_eq!(stats.buffer, 3);
+    }
+
+    #[test]
+    fn plan_statistics() {
+        let mut request = PlanRequest {
+            targets: BTreeMap::from([("uranium-238".into(), 0.993)]),
+            machines: BTreeMap::from([("crafting".into(), "assembling-machine-2".into())]),
+            ..Default::default()
+        };
+        request
+            .policy
+            .recipes
+            .insert("uranium-238".into(), "uranium-processing".into());
+        let plan = ProductionPlan::new(&database(), &request).unwrap();
+        let stats = OutputStatistics::new(
+            &database(),
+            &plan,
+            &StatisticsOptions {
+                window: Duration::minutes(1),
+                confidence: 0.9,
+            },
+        )
+        .unwrap();
+
+        let products = &stats.recipes["uranium-processing"];
+        assert_eq!(products["uranium-235"].rolls, 60);
+        assert_close(products["uranium-235"].expected, 0.42);
+        assert_eq!(products["uranium-235"].lower_bound, 0);
+        assert_close(products["uranium-238"].expected, 59.58);
+        assert!(products["uranium-238"].lower_bound < 60);
+        assert_eq!(stats.recipes.len(), 1);
+    }
+}
@@ -0,0 +1,23 @@
+//! Research which unlocks recipes.
+
+use data_parser::models;
+
+/// A technology, reduced to the recipes it unlocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Technology {
+    pub name: String,
+    /// Technologies which must be researched first.
+    pub prerequisites: Vec<String>,
+    /// Recipes enabled by researching this technology.
+    pub unlocks: Vec<String>,
+}
+
+impl Technology {
+    pub fn new(technology: &models::Technology) -> Self {
+        Self {
+            name: technology.name.clone(),
+            prerequisites: technology.prerequisites.clone(),
+            unlocks: technology.unlocked_recipes().map(Into::into).collect(),
+        }
+    }
+}
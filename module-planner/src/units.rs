@@ -0,0 +1,111 @@
+//! Parsing of Factorio's unit-suffixed quantities.
+
+use crate::{Error, Result};
+
+/// Parse an energy or power string such as `"150kW"` or `"2.5MJ"`.
+///
+/// Returns the quantity in base units: joules for energy, watts for power.
+/// The unit suffix (`J` or `W`) is required; which one is expected is up to
+/// the caller, so `unit` names it.
+pub fn parse_energy(value: &str, unit: char) -> Result<f64> {
+    let invalid = || Error::InvalidEnergy(value.to_owned());
+
+    let number = value.strip_suffix(unit).ok_or_else(invalid)?;
+    let (number, multiplier) = match number.chars().last() {
+        Some('k' | 'K') => (&number[..number.len() - 1], 1e3),
+        Some('M') => (&number[..number.len() - 1], 1e6),
+        Some('G') => (&number[..number.len() - 1], 1e9),
+        Some('T') => (&number[..number.len() - 1], 1e12),
+        Some('P') => (&number[..number.len() - 1], 1e15),
+        _ => (number, 1.0),
+    };
+    let number = number.parse::<f64>().map_err(|_| invalid())? * multiplier;
+    if !number.is_finite() {
+        return Err(invalid());
+    }
+    Ok(number)
+}
+
+/// Parse a rate such as `"30/s"`, `"1800/min"` or `"7.5"`.
+///
+/// Returns the rate per second. A bare number is taken to be per second.
+pub fn parse_rate(value: &str) -> Result<f64> {
+    let invalid = || Error::InvalidRate(value.to_owned());
+
+    let (number, seconds) = match value.split_once('/') {
+        None => (value, 1.0),
+        Some((number, "s" | "sec")) => (number, 1.0),
+        Some((number, "m" | "min")) => (number, 60.0),
+        Some((number, "h")) => (number, 3600.0),
+        Some(_) => return Err(invalid()),
+    };
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(invalid());
+    }
+    Ok(number / seconds)
+}
+
+/// Format a quantity in base units with an SI prefix, such as `"155 kW"`.
+pub fn format_energy(value: f64, unit: char) -> String {
+    const PREFIXES: [(f64, &str); 5] =
+        [(1e15, "P"), (1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+
+    let (divisor, prefix) = PREFIXES
+        .into_iter()
+        .find(|&(divisor, _)| value.abs() >= divisor)
+        .unwrap_or((1.0, ""));
+    format!("{} {prefix}{unit}", compact(value / divisor, 3))
+}
+
+/// Format a float compactly, with at most `precision` decimal places.
+pub fn compact(value: f64, precision: usize) -> String {
+    let formatted = format!("{value:.precision$}");
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_prefixes() {
+        assert_eq!(parse_energy("150kW", 'W').unwrap(), 150e3);
+        assert_eq!(parse_energy("2.5MJ", 'J').unwrap(), 2.5e6);
+        assert_eq!(parse_energy("0.2KJ", 'J').unwrap(), 200.0);
+        assert_eq!(parse_energy("90W", 'W').unwrap(), 90.0);
+    }
+
+    #[test]
+    fn rejects_wrong_unit() {
+        assert!(parse_energy("150kW", 'J').is_err());
+        assert!(parse_energy("fast", 'W').is_err());
+        assert!(parse_energy("infW", 'W').is_err());
+        assert!(parse_energy("NaNkW", 'W').is_err());
+        assert!(parse_energy("1e300PW", 'W').is_err());
+    }
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(parse_rate("30/s").unwrap(), 30.0);
+        assert_eq!(parse_rate("1800/min").unwrap(), 30.0);
+        assert_eq!(parse_rate("7.5").unwrap(), 7.5);
+        assert!(parse_rate("30/day").is_err());
+        assert!(parse_rate("-1").is_err());
+    }
+
+    #[test]
+    fn formats_prefixes() {
+        assert_eq!(format_energy(155e3, 'W'), "155 kW");
+        assert_eq!(format_energy(1.41e6, 'W'), "1.41 MW");
+        assert_eq!(format_energy(2.5e6, 'J'), "2.5 MJ");
+        assert_eq!(format_energy(90.0, 'W'), "90 W");
+    }
+}